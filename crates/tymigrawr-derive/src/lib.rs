@@ -1,16 +1,21 @@
-//! Provides derive macros for `tymigrawr::HasCrudFields`.
+//! Provides derive macros for `tymigrawr::HasCrudFields` and
+//! `tymigrawr::IsCrudField`.
 use quote::quote;
 use syn::{
-    Attribute, Data, DataStruct, DeriveInput, Fields, FieldsNamed, Ident, Type, WhereClause,
-    WherePredicate,
+    Attribute, Data, DataEnum, DataStruct, DeriveInput, Fields, FieldsNamed, Ident, Lit, Meta,
+    MetaList, MetaNameValue, NestedMeta, Type, WhereClause, WherePredicate,
 };
 
 fn get_fields(ast: &Data) -> (Vec<Ident>, Vec<Type>, Vec<Vec<Attribute>>) {
-    let fields = match *ast {
-        Data::Struct(DataStruct {
-            fields: Fields::Named(FieldsNamed { named: ref x, .. }),
-            ..
-        }) => x,
+    match *ast {
+        Data::Struct(DataStruct { ref fields, .. }) => get_named_fields(fields),
+        _ => panic!("Only named fields supported"),
+    }
+}
+
+fn get_named_fields(fields: &Fields) -> (Vec<Ident>, Vec<Type>, Vec<Vec<Attribute>>) {
+    let fields = match fields {
+        Fields::Named(FieldsNamed { named: ref x, .. }) => x,
         _ => panic!("Only named fields supported"),
     };
 
@@ -21,6 +26,24 @@ fn get_fields(ast: &Data) -> (Vec<Ident>, Vec<Type>, Vec<Vec<Attribute>>) {
     (identifiers, tys, atts)
 }
 
+/// The `T` of an `Option<T>` type.
+fn option_inner(ty: &Type) -> Option<&Type> {
+    let Type::Path(path) = ty else {
+        return None;
+    };
+    let segment = path.path.segments.last()?;
+    if segment.ident != "Option" {
+        return None;
+    }
+    let syn::PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return None;
+    };
+    match args.args.first()? {
+        syn::GenericArgument::Type(inner) => Some(inner),
+        _ => None,
+    }
+}
+
 fn gen_crud_fields(
     idents: &[Ident],
     tys: &[Type],
@@ -30,23 +53,127 @@ fn gen_crud_fields(
         .iter()
         .zip(tys.iter().zip(atts))
         .map(|(ident, (ty, atts))| {
-            let atts = atts
-                .iter()
-                .filter_map(|att| att.path.get_ident())
-                .map(|id| format!("{}", id));
             let mut extras = vec![];
+            if option_inner(ty).and_then(option_inner).is_some() {
+                extras.push(
+                    syn::Error::new_spanned(
+                        ty,
+                        "Option<Option<_>> can't be stored, a nullable column has only one NULL",
+                    )
+                    .to_compile_error(),
+                );
+            }
             for att in atts {
-                match att.as_str() {
+                let Some(name) = att.path.get_ident() else {
+                    continue;
+                };
+                match name.to_string().as_str() {
                     "primary_key" => {
                         extras.push(quote! {
                             #ident.primary_key = true;
                         });
+                        match att.parse_meta() {
+                            Ok(Meta::Path(_)) => {}
+                            Ok(Meta::List(MetaList { nested, .. }))
+                                if nested.len() == 1
+                                    && matches!(
+                                        nested.first(),
+                                        Some(NestedMeta::Meta(Meta::Path(path)))
+                                            if path.is_ident("auto_increment")
+                                    ) =>
+                            {
+                                extras.push(quote! {
+                                    #ident.auto_increment = true;
+                                });
+                            }
+                            _ => {
+                                extras.push(
+                                    syn::Error::new_spanned(
+                                        att,
+                                        "expected #[primary_key] or #[primary_key(auto_increment)]",
+                                    )
+                                    .to_compile_error(),
+                                );
+                            }
+                        }
+                    }
+                    "compress" => {
+                        extras.push(quote! {
+                            #ident.ty = tymigrawr::ValueType::Bytes;
+                        });
+                    }
+                    "nullable" => {
+                        extras.push(quote! {
+                            #ident.nullable = true;
+                        });
                     }
+                    "check" => match att.parse_meta() {
+                        Ok(Meta::NameValue(MetaNameValue {
+                            lit: Lit::Str(check),
+                            ..
+                        })) => {
+                            extras.push(quote! {
+                                #ident.check = Some(#check);
+                            });
+                        }
+                        _ => {
+                            extras.push(
+                                syn::Error::new_spanned(att, "expected #[check = \"...\"]")
+                                    .to_compile_error(),
+                            );
+                        }
+                    },
+                    "collate" => match att.parse_meta() {
+                        Ok(Meta::NameValue(MetaNameValue {
+                            lit: Lit::Str(collation),
+                            ..
+                        })) => {
+                            extras.push(quote! {
+                                #ident.collation = Some(#collation);
+                            });
+                        }
+                        _ => {
+                            extras.push(
+                                syn::Error::new_spanned(att, "expected #[collate = \"...\"]")
+                                    .to_compile_error(),
+                            );
+                        }
+                    },
+                    "references" => match att.parse_meta() {
+                        Ok(Meta::List(MetaList { nested, .. })) if nested.len() == 2 => {
+                            let mut paths = nested.iter().filter_map(|nested| match nested {
+                                NestedMeta::Meta(Meta::Path(path)) => path.get_ident(),
+                                _ => None,
+                            });
+                            if let (Some(table), Some(column)) = (paths.next(), paths.next()) {
+                                extras.push(quote! {
+                                    #ident.references = Some((stringify!(#table), stringify!(#column)));
+                                });
+                            } else {
+                                extras.push(
+                                    syn::Error::new_spanned(
+                                        att,
+                                        "expected #[references(table, column)]",
+                                    )
+                                    .to_compile_error(),
+                                );
+                            }
+                        }
+                        _ => {
+                            extras.push(
+                                syn::Error::new_spanned(
+                                    att,
+                                    "expected #[references(table, column)]",
+                                )
+                                .to_compile_error(),
+                            );
+                        }
+                    },
                     _ => {}
                 }
             }
             quote! {
-                let mut #ident = #ty::field();
+                let mut #ident = <#ty>::field();
                 #ident.name = stringify!(#ident);
                 #(#extras)*
                 #ident
@@ -55,96 +182,703 @@ fn gen_crud_fields(
         .collect()
 }
 
+/// The name, value and type of the primary key.
+///
+/// On errors the type is that of the first field, so the error isn't
+/// followed by others about the type.
 fn get_primary_key(
     idents: &[Ident],
+    tys: &[Type],
     atts: &[Vec<Attribute>],
-) -> (proc_macro2::TokenStream, proc_macro2::TokenStream) {
-    let mut keys = idents.iter().zip(atts).filter_map(|(ident, atts)| {
-        for att in atts.iter() {
-            let att = att.path.get_ident()?;
-            if format!("{}", att) == "primary_key" {
-                return Some(ident.clone());
-            }
-        }
-        None
-    });
-    let may_ident = if let Some(ident) = keys.next() {
-        Some(ident)
+    implicit: bool,
+) -> (
+    proc_macro2::TokenStream,
+    proc_macro2::TokenStream,
+    proc_macro2::TokenStream,
+) {
+    let fallback_ty = match tys.first() {
+        Some(ty) => quote! { #ty },
+        None => quote! { i64 },
+    };
+    let keys = idents
+        .iter()
+        .zip(tys.iter().zip(atts))
+        .filter_map(|(ident, (ty, atts))| {
+            let att = atts.iter().find(|att| att.path.is_ident("primary_key"))?;
+            Some((ident.clone(), ty, att))
+        })
+        .collect::<Vec<_>>();
+    if keys.len() > 1 {
+        let error = keys
+            .iter()
+            .map(|(_, _, att)| {
+                syn::Error::new_spanned(
+                    att,
+                    "only one field can be the #[primary_key], composite keys aren't supported yet",
+                )
+            })
+            .reduce(|mut errors, error| {
+                errors.combine(error);
+                errors
+            })
+            .expect("more than one key");
+        let error = error.to_compile_error();
+        return (
+            quote! { { #error } },
+            quote! { unreachable!() },
+            fallback_ty,
+        );
+    }
+    let may_key = if let Some((ident, ty, _)) = keys.into_iter().next() {
+        Some((ident, quote! { #ty }))
+    } else if implicit {
+        idents
+            .first()
+            .cloned()
+            .map(|ident| (ident, fallback_ty.clone()))
     } else {
-        idents.first().cloned()
+        return (
+            quote! {
+                compile_error!(
+                    "mark a field with #[primary_key], \
+                     or add #[implicit_primary_key] to use the first field"
+                )
+            },
+            quote! { unreachable!() },
+            fallback_ty,
+        );
     };
 
-    if let Some(ident) = may_ident {
-        (quote! {stringify!(#ident)}, quote! {self.#ident.into_value()})
+    if let Some((ident, ty)) = may_key {
+        (
+            quote! {stringify!(#ident)},
+            quote! {self.#ident.into_value()},
+            ty,
+        )
     } else {
         (
             quote! {
                 compile_error!("must have at least one field")
             },
             quote! {},
+            fallback_ty,
         )
     }
 }
 
-fn gen_from_crud_fields(idents: &[Ident], tys: &[Type]) -> Vec<proc_macro2::TokenStream> {
+/// Looks up a field in the `fields` map of `try_from_crud_fields`.
+fn get_from_map(ident: &Ident) -> proc_macro2::TokenStream {
+    quote! {
+        fields
+            .get(stringify!(#ident))
+            .whatever_context(concat!("missing ", stringify!(#ident)))?
+    }
+}
+
+/// Converts each field, looked up by `get`, into a local of the same name.
+///
+/// `get` produces a `&tymigrawr::Value` for a field.
+fn gen_from_crud_fields(
+    idents: &[Ident],
+    tys: &[Type],
+    atts: &[Vec<Attribute>],
+    get: impl Fn(&Ident) -> proc_macro2::TokenStream,
+) -> Vec<proc_macro2::TokenStream> {
     idents
         .iter()
-        .zip(tys.iter())
-        .map(|(ident, ty)| {
+        .zip(tys.iter().zip(atts))
+        .map(|(ident, (ty, atts))| {
+            let decompress = if has_att(atts, "compress") {
+                quote! {
+                    let #ident = &tymigrawr::decompress_value(#ident)
+                        .whatever_context(concat!("decompress ", stringify!(#ident)))?;
+                }
+            } else {
+                quote! {}
+            };
+            let get = get(ident);
+            let convert = quote! {
+                tymigrawr::IntoCrudResult::into_crud_result(<#ty>::maybe_from_value(#ident))
+                    .whatever_context(concat!("convert ", stringify!(#ident)))?
+            };
+            let convert = if has_att(atts, "nullable") {
+                quote! {
+                    if matches!(#ident, tymigrawr::Value::None) {
+                        <#ty as Default>::default()
+                    } else {
+                        #convert
+                    }
+                }
+            } else {
+                convert
+            };
             quote! {
-                let #ident = fields
-                    .get(stringify!(#ident))
-                    .whatever_context(concat!("missing ", stringify!(#ident)))?;
-                let #ident = #ty::maybe_from_value(#ident)
-                    .whatever_context(concat!("convert ", stringify!(#ident)))?;
+                let #ident = #get;
+                #decompress
+                let #ident = #convert;
             }
         })
         .collect()
 }
 
+/// Like `gen_from_crud_fields`, for `try_from_row_ref`, where `column`
+/// returns a `tymigrawr::ValueRef`.
+///
+/// `#[compress]` fields are decompressed into an owned value first.
+fn gen_from_row_ref(
+    idents: &[Ident],
+    tys: &[Type],
+    atts: &[Vec<Attribute>],
+) -> Vec<proc_macro2::TokenStream> {
+    idents
+        .iter()
+        .zip(tys.iter().zip(atts))
+        .map(|(ident, (ty, atts))| {
+            let from_value = if has_att(atts, "compress") {
+                quote! {
+                    <#ty>::maybe_from_value(
+                        &tymigrawr::decompress_value(&#ident.to_value())
+                            .whatever_context(concat!("decompress ", stringify!(#ident)))?,
+                    )
+                }
+            } else {
+                quote! { <#ty>::maybe_from_value_ref(#ident) }
+            };
+            let convert = quote! {
+                tymigrawr::IntoCrudResult::into_crud_result(#from_value)
+                    .whatever_context(concat!("convert ", stringify!(#ident)))?
+            };
+            let convert = if has_att(atts, "nullable") {
+                quote! {
+                    if matches!(#ident, tymigrawr::ValueRef::None) {
+                        <#ty as Default>::default()
+                    } else {
+                        #convert
+                    }
+                }
+            } else {
+                convert
+            };
+            quote! {
+                let #ident = column(stringify!(#ident));
+                let #ident = #convert;
+            }
+        })
+        .collect()
+}
+
+/// Converts `field` into a `tymigrawr::Value`, compressing it if it's marked
+/// `#[compress]`.
+fn gen_into_value(field: proc_macro2::TokenStream, atts: &[Attribute]) -> proc_macro2::TokenStream {
+    if has_att(atts, "compress") {
+        quote! { tymigrawr::compress_value(#field.into_value()) }
+    } else {
+        quote! { #field.into_value() }
+    }
+}
+
+/// `name` in snake case.
+///
+/// A word starts at an uppercase letter that follows a lowercase letter or a
+/// digit, or that follows an uppercase letter and is followed by a lowercase
+/// one. Digits stay with the word before them, so `PlayerV3` becomes
+/// `player_v3` and `HTTPServer` becomes `http_server`.
+fn snake_case(name: &str) -> String {
+    let chars = name.chars().collect::<Vec<_>>();
+    let mut snake = String::new();
+    for (i, c) in chars.iter().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            let prev = chars[i - 1];
+            let next_is_lower = chars.get(i + 1).is_some_and(|next| next.is_lowercase());
+            if prev.is_lowercase()
+                || prev.is_ascii_digit()
+                || (prev.is_uppercase() && next_is_lower)
+            {
+                snake.push('_');
+            }
+        }
+        snake.extend(c.to_lowercase());
+    }
+    snake
+}
+
+/// The table name of `name`, following its `#[table_naming = "..."]`
+/// attribute, if any.
+fn gen_table_name(name: &Ident, atts: &[Attribute]) -> proc_macro2::TokenStream {
+    let name = name.to_string();
+    let Some(att) = atts.iter().find(|att| att.path.is_ident("table_naming")) else {
+        let table_name = name.to_ascii_lowercase();
+        return quote! { #table_name };
+    };
+    let table_name = match att.parse_meta() {
+        Ok(Meta::NameValue(MetaNameValue {
+            lit: Lit::Str(naming),
+            ..
+        })) => match naming.value().as_str() {
+            "lowercase" => Some(name.to_ascii_lowercase()),
+            "snake_case" => Some(snake_case(&name)),
+            "as_is" => Some(name),
+            _ => None,
+        },
+        _ => None,
+    };
+    match table_name {
+        Some(table_name) => quote! { #table_name },
+        None => syn::Error::new_spanned(
+            att,
+            "expected #[table_naming = \"lowercase\"], \"snake_case\" or \"as_is\"",
+        )
+        .to_compile_error(),
+    }
+}
+
+/// `From` and `MigrationStep` impls for each `#[migrate_from(Prev)]` on the
+/// struct.
+///
+/// Fields are moved from the same-named fields of `Prev`, except those marked
+/// `#[migrate_default]` or `#[from_expr = "..."]`.
+fn gen_migrate_from(
+    name: &Ident,
+    generics: &syn::Generics,
+    atts: &[Attribute],
+    idents: &[Ident],
+    field_atts: &[Vec<Attribute>],
+) -> proc_macro2::TokenStream {
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let mut new_fields = vec![];
+    let mut moved_fields = vec![];
+    for (ident, atts) in idents.iter().zip(field_atts) {
+        if let Some(att) = atts.iter().find(|att| att.path.is_ident("from_expr")) {
+            let expr = match att.parse_meta() {
+                Ok(Meta::NameValue(MetaNameValue {
+                    lit: Lit::Str(expr),
+                    ..
+                })) => expr.parse::<proc_macro2::TokenStream>(),
+                _ => Err(syn::Error::new_spanned(
+                    att,
+                    "expected #[from_expr = \"...\"]",
+                )),
+            };
+            match expr {
+                Ok(expr) => new_fields.push(quote! { let #ident = { #expr }; }),
+                Err(e) => return e.to_compile_error(),
+            }
+        } else if has_att(atts, "migrate_default") {
+            new_fields.push(quote! { let #ident = Default::default(); });
+        } else {
+            moved_fields.push(ident);
+        }
+    }
+    let new_idents = idents.iter().filter(|ident| !moved_fields.contains(ident));
+    let mut impls = vec![];
+    for att in atts.iter().filter(|att| att.path.is_ident("migrate_from")) {
+        let prev = match att.parse_args::<Type>() {
+            Ok(prev) => prev,
+            Err(e) => return e.to_compile_error(),
+        };
+        let new_idents = new_idents.clone();
+        impls.push(quote! {
+            #[automatically_derived]
+            impl #impl_generics From<#prev> for #name #ty_generics #where_clause {
+                #[allow(unused_variables)]
+                fn from(prev: #prev) -> Self {
+                    #(#new_fields)*
+                    Self {
+                        #(#new_idents,)*
+                        #(#moved_fields: prev.#moved_fields),*
+                    }
+                }
+            }
+
+            #[automatically_derived]
+            impl #impl_generics tymigrawr::MigrationStep<#prev> for #name #ty_generics #where_clause {}
+        });
+    }
+    quote! { #(#impls)* }
+}
+
+fn has_att(atts: &[Attribute], name: &str) -> bool {
+    atts.iter()
+        .filter_map(|att| att.path.get_ident())
+        .any(|id| id == name)
+}
+
+/// Adds a `IsCrudField` constraint on each of the field types.
+///
+/// Field types that are generic parameters, like `T` in `payload: T`, get
+/// the constraint on the parameter itself. Their `MaybeSelf` is constrained
+/// too, so that it can be converted into a `Result` without knowing `T`.
+fn constrain_field_types(clause: &mut WhereClause, tys: &[Type]) {
+    for ty in tys.iter() {
+        let where_predicate: WherePredicate = syn::parse_quote!(#ty : tymigrawr::IsCrudField);
+        clause.predicates.push(where_predicate);
+        let where_predicate: WherePredicate = syn::parse_quote!(
+            <#ty as tymigrawr::IsCrudField>::MaybeSelf : tymigrawr::IntoCrudResult<#ty>
+        );
+        clause.predicates.push(where_predicate);
+    }
+}
+
+/// The body of `crud_fields()`, caching the fields that `build` evaluates to.
+///
+/// Non-generic types use a `static`. Generic types are cached by `TypeId`,
+/// since a `static` would be shared by every instantiation, so they must be
+/// `'static`.
+fn gen_cached_crud_fields(
+    generics: &mut syn::Generics,
+    build: proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    if generics.params.is_empty() {
+        quote! {
+            static FIELDS: std::sync::OnceLock<Vec<tymigrawr::CrudField>> =
+                std::sync::OnceLock::new();
+            FIELDS.get_or_init(|| { #build })
+        }
+    } else {
+        let where_predicate: WherePredicate = syn::parse_quote!(Self: 'static);
+        generics
+            .make_where_clause()
+            .predicates
+            .push(where_predicate);
+        quote! {
+            tymigrawr::cached_crud_fields::<Self>(|| { #build })
+        }
+    }
+}
+
+/// The name of the discriminator column of enums.
+const DISCRIMINATOR: &str = "kind";
+
+/// One variant of an enum deriving `HasCrudFields`.
+struct Variant {
+    ident: Ident,
+    field_idents: Vec<Ident>,
+    field_tys: Vec<Type>,
+}
+
+/// Derives `HasCrudFields` for an enum with named-field variants.
+///
+/// All variants share one table. The variant is stored in a `kind` column
+/// and the table has a column for every field of every variant. Fields that
+/// don't appear in every variant are nullable. Fields of the same name in
+/// different variants share a column, and should have the same type.
+fn derive_enum_crud_fields(
+    name: Ident,
+    mut generics: syn::Generics,
+    data: &DataEnum,
+    table_name: proc_macro2::TokenStream,
+    table_options: proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    let mut variants = vec![];
+    // The union of all variants' fields, in order of first appearance.
+    let mut col_idents: Vec<Ident> = vec![];
+    let mut col_tys: Vec<Type> = vec![];
+    let mut col_atts: Vec<Vec<Attribute>> = vec![];
+    for variant in data.variants.iter() {
+        if !matches!(variant.fields, Fields::Named(_)) {
+            return quote! {
+                compile_error!("Only variants with named fields are supported");
+            };
+        }
+        let (field_idents, field_tys, field_atts) = get_named_fields(&variant.fields);
+        for ((ident, ty), atts) in field_idents.iter().zip(&field_tys).zip(field_atts) {
+            if let Some(i) = col_idents.iter().position(|col| col == ident) {
+                col_atts[i].extend(atts);
+            } else {
+                col_idents.push(ident.clone());
+                col_tys.push(ty.clone());
+                col_atts.push(atts);
+            }
+        }
+        variants.push(Variant {
+            ident: variant.ident.clone(),
+            field_idents,
+            field_tys,
+        });
+    }
+
+    let in_all_variants =
+        |col: &Ident| -> bool { variants.iter().all(|v| v.field_idents.contains(col)) };
+    let (primary_key, primary_key_ty) = match col_idents
+        .iter()
+        .zip(&col_tys)
+        .zip(&col_atts)
+        .find(|(_, atts)| has_att(atts, "primary_key"))
+    {
+        Some(((ident, ty), _)) if in_all_variants(ident) => (ident.clone(), ty.clone()),
+        Some(_) => {
+            return quote! {
+                compile_error!("the #[primary_key] field must appear in every variant");
+            }
+        }
+        None => {
+            return quote! {
+                compile_error!("enums must mark a field with #[primary_key]");
+            }
+        }
+    };
+
+    constrain_field_types(generics.make_where_clause(), &col_tys);
+    let crud_fields = gen_crud_fields(&col_idents, &col_tys, &col_atts)
+        .into_iter()
+        .zip(&col_idents)
+        .map(|(field, ident)| {
+            if in_all_variants(ident) {
+                field
+            } else {
+                quote! {
+                    let mut field = {#field};
+                    field.nullable = true;
+                    field
+                }
+            }
+        });
+    let crud_fields = gen_cached_crud_fields(
+        &mut generics,
+        quote! {
+            let mut r = Vec::new();
+            r.push({
+                let mut kind = <String as tymigrawr::IsCrudField>::field();
+                kind.name = #DISCRIMINATOR;
+                kind
+            });
+            #(r.push({#crud_fields});)*
+            r
+        },
+    );
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let mut as_crud_fields_arms = vec![];
+    let mut primary_key_val_arms = vec![];
+    let mut try_from_crud_fields_arms = vec![];
+    for Variant {
+        ident,
+        field_idents,
+        field_tys,
+    } in variants.iter()
+    {
+        let variant_name = ident.to_string();
+        let values = col_idents.iter().zip(&col_atts).map(|(col, atts)| {
+            if field_idents.contains(col) {
+                gen_into_value(quote! { #col }, atts)
+            } else {
+                quote! { tymigrawr::Value::None }
+            }
+        });
+        as_crud_fields_arms.push(quote! {
+            #name::#ident { #(#field_idents),* } => tymigrawr::FieldMap::from_iter([
+                (#DISCRIMINATOR, tymigrawr::Value::String(#variant_name.to_string())),
+                #((stringify!(#col_idents), #values)),*
+            ]),
+        });
+        primary_key_val_arms.push(quote! {
+            #name::#ident { #primary_key, .. } => #primary_key.into_value(),
+        });
+        // a column's attributes are shared by every variant that has it
+        let field_atts = field_idents
+            .iter()
+            .map(|ident| {
+                let i = col_idents.iter().position(|col| col == ident).unwrap();
+                col_atts[i].clone()
+            })
+            .collect::<Vec<_>>();
+        let from_crud_fields =
+            gen_from_crud_fields(field_idents, field_tys, &field_atts, get_from_map);
+        try_from_crud_fields_arms.push(quote! {
+            #variant_name => {
+                #(#from_crud_fields)*
+                Ok(#name::#ident {
+                    #(#field_idents),*
+                })
+            }
+        });
+    }
+
+    quote! {
+        #[automatically_derived]
+        impl #impl_generics tymigrawr::HasCrudFields for #name #ty_generics #where_clause {
+            type PrimaryKey = #primary_key_ty;
+
+            fn table_name() -> &'static str {
+                tymigrawr::prefixed_table_name(#table_name)
+            }
+
+            fn crud_fields() -> &'static [tymigrawr::CrudField] {
+                #crud_fields
+            }
+
+            fn as_crud_fields(&self) -> tymigrawr::FieldMap<'_> {
+                match self {
+                    #(#as_crud_fields_arms)*
+                }
+            }
+
+            fn primary_key_name() -> &'static str {
+                stringify!(#primary_key)
+            }
+
+            fn primary_key_val(&self) -> tymigrawr::Value {
+                match self {
+                    #(#primary_key_val_arms)*
+                }
+            }
+
+            #table_options
+
+            fn try_from_crud_fields(
+                fields: &tymigrawr::FieldMap<'_>,
+            ) -> Result<Self, snafu::Whatever> {
+                let kind = fields
+                    .get(#DISCRIMINATOR)
+                    .whatever_context(concat!("missing ", #DISCRIMINATOR))?;
+                let kind = <String as tymigrawr::IsCrudField>::maybe_from_value(kind)
+                    .whatever_context(concat!("convert ", #DISCRIMINATOR))?;
+                match kind.as_str() {
+                    #(#try_from_crud_fields_arms)*
+                    unknown => snafu::whatever!(
+                        "unknown {} variant '{unknown}'",
+                        stringify!(#name)
+                    ),
+                }
+            }
+        }
+    }
+}
+
 /// Macro for deriving structs that have normal CRUD-worthy fields.
-#[proc_macro_derive(HasCrudFields, attributes(primary_key))]
+///
+/// One field must be marked `#[primary_key]`. Adding `#[implicit_primary_key]`
+/// to the struct instead uses the first field as the key.
+///
+/// Marking the key `#[primary_key(auto_increment)]` declares its column
+/// `AUTOINCREMENT`.
+///
+/// Enums whose variants have named fields are also supported. They are stored
+/// in a single table with a `kind` discriminator column.
+///
+/// Adding `#[append_only]` makes `update` and `delete` return an error.
+///
+/// Adding `#[audited]` keeps a `{table}_history` table of every version of
+/// each row.
+///
+/// Fields marked `#[compress]` are stored as compressed bytes. This requires
+/// tymigrawr's `compress` feature.
+///
+/// Fields marked `#[nullable]` are nullable columns without being `Option`s.
+/// A NULL reads as the field's `Default`.
+///
+/// Fields marked `#[check = "age >= 0"]` get that `CHECK` constraint, passed
+/// to the backend verbatim.
+///
+/// Fields marked `#[collate = "NOCASE"]` use that collation, so comparisons
+/// and unique keys on the column ignore case in SQLite.
+///
+/// Fields marked `#[references(players, id)]` are foreign keys to the `id`
+/// column of the `players` table.
+///
+/// Adding `#[migrate_from(PlayerV1)]` to a struct implements
+/// `From<PlayerV1>` and `MigrationStep<PlayerV1>` for it. Each field is moved
+/// from the same-named field of `PlayerV1`, which must exist. Mark new fields
+/// `#[migrate_default]` to use their `Default`, or `#[from_expr = "..."]` to
+/// compute them from `prev`, the `PlayerV1` being converted.
+///
+/// The table name is the lowercased type name, eg. `playerv3` for
+/// `PlayerV3`. Add `#[table_naming = "snake_case"]` for `player_v3`, or
+/// `#[table_naming = "as_is"]` for `PlayerV3`. Within
+/// `tymigrawr::with_table_prefix` it's prefixed.
+#[proc_macro_derive(
+    HasCrudFields,
+    attributes(
+        primary_key,
+        implicit_primary_key,
+        append_only,
+        audited,
+        compress,
+        nullable,
+        check,
+        collate,
+        references,
+        table_naming,
+        migrate_from,
+        migrate_default,
+        from_expr
+    )
+)]
 pub fn derive_crud_fields(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let input: DeriveInput = syn::parse_macro_input!(input);
     let name = input.ident;
-    let (field_idents, field_tys, field_atts) = get_fields(&input.data);
-    let mut generics = input.generics;
-    {
-        /// Adds a `HasCrudFields` constraint on each of the field types.
-        fn constrain_field_types(clause: &mut WhereClause, tys: &[Type]) {
-            for ty in tys.iter() {
-                let where_predicate: WherePredicate =
-                    syn::parse_quote!(#ty : tymigrawr::IsCrudField);
-                clause.predicates.push(where_predicate);
+    let mut table_options = vec![];
+    if has_att(&input.attrs, "append_only") {
+        table_options.push(quote! {
+            fn append_only() -> bool {
+                true
             }
+        });
+    }
+    if has_att(&input.attrs, "audited") {
+        table_options.push(quote! {
+            fn audited() -> bool {
+                true
+            }
+        });
+    }
+    let table_options = quote! { #(#table_options)* };
+    let table_name = gen_table_name(&name, &input.attrs);
+    if let Data::Enum(data) = &input.data {
+        if has_att(&input.attrs, "migrate_from") {
+            return quote! {
+                compile_error!("#[migrate_from] is only supported on structs");
+            }
+            .into();
         }
-
-        let where_clause = generics.make_where_clause();
-        constrain_field_types(where_clause, &field_tys)
+        return derive_enum_crud_fields(name, input.generics, data, table_name, table_options)
+            .into();
     }
+    let (field_idents, field_tys, field_atts) = get_fields(&input.data);
+    let mut generics = input.generics;
+    constrain_field_types(generics.make_where_clause(), &field_tys);
+    let migrate_from = gen_migrate_from(&name, &generics, &input.attrs, &field_idents, &field_atts);
 
+    let crud_fields = gen_crud_fields(&field_idents, &field_tys, &field_atts);
+    let crud_fields = gen_cached_crud_fields(
+        &mut generics,
+        quote! {
+            let mut r = Vec::new();
+            #(r.push({#crud_fields});)*
+            r
+        },
+    );
     let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
 
-    let table_name = name.to_string().to_ascii_lowercase();
-    let crud_fields = gen_crud_fields(&field_idents, &field_tys, &field_atts);
-    let from_crud_fields = gen_from_crud_fields(&field_idents, &field_tys);
-    let (primary_key, primary_key_val) = get_primary_key(&field_idents, &field_atts);
+    let from_crud_fields =
+        gen_from_crud_fields(&field_idents, &field_tys, &field_atts, get_from_map);
+    let from_row = gen_from_crud_fields(&field_idents, &field_tys, &field_atts, |ident| {
+        quote! { &column(stringify!(#ident)) }
+    });
+    let from_row_ref = gen_from_row_ref(&field_idents, &field_tys, &field_atts);
+    let values = field_idents
+        .iter()
+        .zip(&field_atts)
+        .map(|(ident, atts)| gen_into_value(quote! { self.#ident }, atts));
+    let implicit_primary_key = has_att(&input.attrs, "implicit_primary_key");
+    let (primary_key, primary_key_val, primary_key_ty) =
+        get_primary_key(&field_idents, &field_tys, &field_atts, implicit_primary_key);
     let output = quote! {
         #[automatically_derived]
         impl #impl_generics tymigrawr::HasCrudFields for #name #ty_generics #where_clause {
+            type PrimaryKey = #primary_key_ty;
+
             fn table_name() -> &'static str {
-                #table_name
+                tymigrawr::prefixed_table_name(#table_name)
             }
 
-            fn crud_fields() -> Vec<tymigrawr::CrudField> {
-                let mut r = Vec::new();
-                #(r.push({#crud_fields});)*
-                r
+            fn crud_fields() -> &'static [tymigrawr::CrudField] {
+                #crud_fields
             }
 
-            fn as_crud_fields(&self) -> std::collections::HashMap<&str, tymigrawr::Value> {
-                std::collections::HashMap::from_iter([
-                    #((stringify!(#field_idents), self.#field_idents.into_value())),*
+            fn as_crud_fields(&self) -> tymigrawr::FieldMap<'_> {
+                tymigrawr::FieldMap::from_iter([
+                    #((stringify!(#field_idents), #values)),*
                 ])
             }
 
@@ -156,16 +890,211 @@ pub fn derive_crud_fields(input: proc_macro::TokenStream) -> proc_macro::TokenSt
                 #primary_key_val
             }
 
+            #table_options
+
             fn try_from_crud_fields(
-                fields: &std::collections::HashMap<&str, tymigrawr::Value>,
+                fields: &tymigrawr::FieldMap<'_>,
             ) -> Result<Self, snafu::Whatever> {
                 #(#from_crud_fields)*
                 Ok(Self{
                     #(#field_idents),*
                 })
             }
+
+            fn try_from_row(
+                mut column: impl FnMut(&'static str) -> tymigrawr::Value,
+            ) -> Result<Self, snafu::Whatever> {
+                #(#from_row)*
+                Ok(Self{
+                    #(#field_idents),*
+                })
+            }
+
+            fn try_from_row_ref<'r>(
+                mut column: impl FnMut(&'static str) -> tymigrawr::ValueRef<'r>,
+            ) -> Result<Self, snafu::Whatever> {
+                #(#from_row_ref)*
+                Ok(Self{
+                    #(#field_idents),*
+                })
+            }
         }
+
+        #migrate_from
     };
 
     output.into()
 }
+
+/// Macro for deriving `IsCrudField` on newtypes like `struct Email(String)`.
+///
+/// The newtype is stored exactly like the single field it wraps.
+#[proc_macro_derive(CrudNewtype)]
+pub fn derive_crud_newtype(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input: DeriveInput = syn::parse_macro_input!(input);
+    let name = input.ident;
+    let fields = match &input.data {
+        Data::Struct(DataStruct { fields, .. }) if fields.len() == 1 => fields,
+        _ => return quote! {
+            compile_error!("CrudNewtype can only be derived for structs with exactly one field");
+        }
+        .into(),
+    };
+    let field = fields.iter().next().unwrap();
+    let ty = &field.ty;
+    let (inner, construct) = if let Some(ident) = &field.ident {
+        (quote! { self.#ident }, quote! { Self { #ident: inner } })
+    } else {
+        (quote! { self.0 }, quote! { Self(inner) })
+    };
+
+    let mut generics = input.generics;
+    let clause = generics.make_where_clause();
+    constrain_field_types(clause, std::slice::from_ref(ty));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let output = quote! {
+        #[automatically_derived]
+        impl #impl_generics tymigrawr::IsCrudField for #name #ty_generics #where_clause {
+            type MaybeSelf = Result<Self, snafu::Whatever>;
+
+            fn field() -> tymigrawr::CrudField {
+                <#ty as tymigrawr::IsCrudField>::field()
+            }
+
+            fn into_value(&self) -> tymigrawr::Value {
+                tymigrawr::IsCrudField::into_value(&#inner)
+            }
+
+            fn maybe_from_value(value: &tymigrawr::Value) -> Self::MaybeSelf {
+                let inner = tymigrawr::IntoCrudResult::into_crud_result(
+                    <#ty as tymigrawr::IsCrudField>::maybe_from_value(value),
+                )?;
+                Ok(#construct)
+            }
+
+            fn maybe_from_value_ref(value: tymigrawr::ValueRef<'_>) -> Self::MaybeSelf {
+                let inner = tymigrawr::IntoCrudResult::into_crud_result(
+                    <#ty as tymigrawr::IsCrudField>::maybe_from_value_ref(value),
+                )?;
+                Ok(#construct)
+            }
+        }
+    };
+    output.into()
+}
+
+/// Macro for deriving `IsCrudField` on fieldless enums like
+/// `enum Rank { Bronze, Silver, Gold }`.
+///
+/// Variants are stored by name as strings, eg. `"Silver"`. Add
+/// `#[crud_enum = "discriminant"]` to store them as their integer
+/// discriminant instead, eg. `1`. Unknown values fail to convert.
+#[proc_macro_derive(CrudEnum, attributes(crud_enum))]
+pub fn derive_crud_enum(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input: DeriveInput = syn::parse_macro_input!(input);
+    let name = input.ident;
+    let Data::Enum(DataEnum { variants, .. }) = &input.data else {
+        return syn::Error::new_spanned(&name, "CrudEnum can only be derived for enums")
+            .to_compile_error()
+            .into();
+    };
+    if let Some(variant) = variants.iter().find(|v| !matches!(v.fields, Fields::Unit)) {
+        return syn::Error::new_spanned(
+            variant,
+            "CrudEnum variants can't have fields, the column only stores which variant it is",
+        )
+        .to_compile_error()
+        .into();
+    }
+    let variants = variants.iter().map(|v| v.ident.clone()).collect::<Vec<_>>();
+    let by_discriminant = match input
+        .attrs
+        .iter()
+        .find(|att| att.path.is_ident("crud_enum"))
+    {
+        None => false,
+        Some(att) => match att.parse_meta() {
+            Ok(Meta::NameValue(MetaNameValue {
+                lit: Lit::Str(storage),
+                ..
+            })) if storage.value() == "name" => false,
+            Ok(Meta::NameValue(MetaNameValue {
+                lit: Lit::Str(storage),
+                ..
+            })) if storage.value() == "discriminant" => true,
+            _ => {
+                return syn::Error::new_spanned(
+                    att,
+                    "expected #[crud_enum = \"name\"] or #[crud_enum = \"discriminant\"]",
+                )
+                .to_compile_error()
+                .into()
+            }
+        },
+    };
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let (ty, into_value, from_value) = if by_discriminant {
+        (
+            quote! { tymigrawr::ValueType::Integer },
+            quote! {
+                match self {
+                    #(Self::#variants => tymigrawr::Value::Integer(Self::#variants as i64),)*
+                }
+            },
+            quote! {
+                let Some(i) = value.as_i64() else {
+                    snafu::whatever!("not an integer")
+                };
+                #(
+                    if i == Self::#variants as i64 {
+                        return Ok(Self::#variants);
+                    }
+                )*
+                snafu::whatever!("{i} is not a {} discriminant", stringify!(#name))
+            },
+        )
+    } else {
+        (
+            quote! { tymigrawr::ValueType::String },
+            quote! {
+                match self {
+                    #(Self::#variants => tymigrawr::Value::from(stringify!(#variants).to_string()),)*
+                }
+            },
+            quote! {
+                let Some(s) = value.as_string() else {
+                    snafu::whatever!("not a string")
+                };
+                match s.as_str() {
+                    #(stringify!(#variants) => Ok(Self::#variants),)*
+                    _ => snafu::whatever!("'{s}' is not a {} variant", stringify!(#name)),
+                }
+            },
+        )
+    };
+
+    let output = quote! {
+        #[automatically_derived]
+        impl #impl_generics tymigrawr::IsCrudField for #name #ty_generics #where_clause {
+            type MaybeSelf = Result<Self, snafu::Whatever>;
+
+            fn field() -> tymigrawr::CrudField {
+                tymigrawr::CrudField {
+                    ty: #ty,
+                    ..Default::default()
+                }
+            }
+
+            fn into_value(&self) -> tymigrawr::Value {
+                #into_value
+            }
+
+            fn maybe_from_value(value: &tymigrawr::Value) -> Self::MaybeSelf {
+                #from_value
+            }
+        }
+    };
+    output.into()
+}
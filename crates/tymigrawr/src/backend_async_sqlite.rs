@@ -0,0 +1,247 @@
+//! Async sqlite impl.
+//!
+//! Runs the [`Rusqlite`] backend on a [`tokio_rusqlite`] connection, whose
+//! own thread does the blocking so sqlite doesn't stall the executor.
+use std::{fmt, path::Path};
+
+use snafu::{FromString, ResultExt};
+
+use crate::{
+    table_prefix, with_table_prefix, AsyncCrud, Crud, HasCrudFields, IsCrudField, Page, Query,
+    Rusqlite,
+};
+
+/// A sqlite connection shared by async tasks.
+///
+/// Cloning is cheap and shares the connection. Operations on one connection
+/// run one at a time.
+#[derive(Clone)]
+pub struct AsyncSqliteConnection {
+    inner: tokio_rusqlite::Connection,
+}
+
+impl From<rusqlite::Connection> for AsyncSqliteConnection {
+    fn from(connection: rusqlite::Connection) -> Self {
+        AsyncSqliteConnection {
+            inner: connection.into(),
+        }
+    }
+}
+
+/// A `Send` copy of a [`snafu::Whatever`] and its sources.
+///
+/// `snafu::Whatever` isn't `Send`, so it can't leave the connection's
+/// thread. Each error in the chain keeps its message, and is rebuilt into a
+/// `snafu::Whatever` with the same sources on the other side.
+#[derive(Debug)]
+struct ErrorChain {
+    message: String,
+    source: Option<Box<ErrorChain>>,
+}
+
+impl ErrorChain {
+    fn new(error: &(dyn std::error::Error + 'static)) -> Self {
+        ErrorChain {
+            message: error.to_string(),
+            source: error
+                .source()
+                .map(|source| Box::new(ErrorChain::new(source))),
+        }
+    }
+
+    fn into_whatever(self) -> snafu::Whatever {
+        match self.source {
+            Some(source) => snafu::Whatever::with_source(source, self.message),
+            None => snafu::Whatever::without_source(self.message),
+        }
+    }
+}
+
+impl fmt::Display for ErrorChain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for ErrorChain {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.source
+            .as_deref()
+            .map(|source| source as &(dyn std::error::Error + 'static))
+    }
+}
+
+impl AsyncSqliteConnection {
+    pub async fn open(path: impl AsRef<Path>) -> Result<Self, snafu::Whatever> {
+        let inner = tokio_rusqlite::Connection::open(path)
+            .await
+            .whatever_context("could not open sqlite database")?;
+        Ok(AsyncSqliteConnection { inner })
+    }
+
+    pub async fn open_in_memory() -> Result<Self, snafu::Whatever> {
+        let inner = tokio_rusqlite::Connection::open_in_memory()
+            .await
+            .whatever_context("could not open in-memory sqlite database")?;
+        Ok(AsyncSqliteConnection { inner })
+    }
+
+    /// Run `f` with the connection on the connection's thread.
+    ///
+    /// The [`table_prefix`] of the polling thread is set for `f` too, since
+    /// it's thread local and `f` runs on another thread.
+    pub async fn call<R, F>(&self, f: F) -> Result<R, snafu::Whatever>
+    where
+        R: Send + 'static,
+        F: FnOnce(&rusqlite::Connection) -> Result<R, snafu::Whatever> + Send + 'static,
+    {
+        let prefix = table_prefix();
+        let result = self
+            .inner
+            .call(move |connection| {
+                let f = || f(connection).map_err(|error| ErrorChain::new(&error));
+                match prefix {
+                    Some(prefix) => with_table_prefix(prefix, f),
+                    None => f(),
+                }
+            })
+            .await;
+        match result {
+            Ok(r) => Ok(r),
+            Err(tokio_rusqlite::Error::Error(chain)) => Err(chain.into_whatever()),
+            Err(error) => Err(error).whatever_context("sqlite connection is closed"),
+        }
+    }
+}
+
+pub struct AsyncSqlite;
+
+impl<T: HasCrudFields + Clone + Send + Sync + 'static> AsyncCrud<AsyncSqlite> for T
+where
+    T::PrimaryKey: Send + 'static,
+{
+    type Connection = AsyncSqliteConnection;
+
+    async fn create(connection: &AsyncSqliteConnection) -> Result<(), snafu::Whatever> {
+        connection
+            .call(|conn| <T as Crud<Rusqlite>>::create(conn))
+            .await
+    }
+
+    async fn validate_schema(connection: &AsyncSqliteConnection) -> Result<(), snafu::Whatever> {
+        connection
+            .call(|conn| <T as Crud<Rusqlite>>::validate_schema(conn))
+            .await
+    }
+
+    async fn insert(&self, connection: &AsyncSqliteConnection) -> Result<(), snafu::Whatever> {
+        let row = self.clone();
+        connection
+            .call(move |conn| <T as Crud<Rusqlite>>::insert(&row, conn))
+            .await
+    }
+
+    async fn insert_or_ignore(
+        &self,
+        connection: &AsyncSqliteConnection,
+    ) -> Result<bool, snafu::Whatever> {
+        let row = self.clone();
+        connection
+            .call(move |conn| <T as Crud<Rusqlite>>::insert_or_ignore(&row, conn))
+            .await
+    }
+
+    async fn insert_many(
+        connection: &AsyncSqliteConnection,
+        items: Vec<Self>,
+    ) -> Result<(), snafu::Whatever> {
+        connection
+            .call(move |conn| <T as Crud<Rusqlite>>::insert_many(conn, &items))
+            .await
+    }
+
+    async fn read_all(connection: &AsyncSqliteConnection) -> Result<Vec<Self>, snafu::Whatever> {
+        connection
+            .call(|conn| <T as Crud<Rusqlite>>::read_all(conn)?.collect())
+            .await
+    }
+
+    async fn count(connection: &AsyncSqliteConnection) -> Result<usize, snafu::Whatever> {
+        connection
+            .call(|conn| <T as Crud<Rusqlite>>::count(conn))
+            .await
+    }
+
+    async fn read_page_typed(
+        connection: &AsyncSqliteConnection,
+        limit: usize,
+        offset: usize,
+    ) -> Result<Page<Self>, snafu::Whatever> {
+        connection
+            .call(move |conn| <T as Crud<Rusqlite>>::read_page_typed(conn, limit, offset))
+            .await
+    }
+
+    async fn read_where<Key: IsCrudField + Send + 'static>(
+        connection: &AsyncSqliteConnection,
+        key_name: &str,
+        comparison: &str,
+        key_value: Key,
+    ) -> Result<Vec<Self>, snafu::Whatever> {
+        let key_name = key_name.to_string();
+        let comparison = comparison.to_string();
+        connection
+            .call(move |conn| {
+                <T as Crud<Rusqlite>>::read_where(conn, &key_name, &comparison, key_value)?
+                    .collect()
+            })
+            .await
+    }
+
+    async fn read(
+        connection: &AsyncSqliteConnection,
+        key: Self::PrimaryKey,
+    ) -> Result<Vec<Self>, snafu::Whatever> {
+        connection
+            .call(move |conn| <T as Crud<Rusqlite>>::read(conn, key)?.collect())
+            .await
+    }
+
+    async fn read_by_keys(
+        connection: &AsyncSqliteConnection,
+        keys: Vec<Self::PrimaryKey>,
+    ) -> Result<Vec<Self>, snafu::Whatever> {
+        connection
+            .call(move |conn| <T as Crud<Rusqlite>>::read_by_keys(conn, keys))
+            .await
+    }
+
+    async fn update(&self, connection: &AsyncSqliteConnection) -> Result<(), snafu::Whatever> {
+        let row = self.clone();
+        connection
+            .call(move |conn| <T as Crud<Rusqlite>>::update(&row, conn))
+            .await
+    }
+
+    async fn delete(self, connection: &AsyncSqliteConnection) -> Result<bool, snafu::Whatever> {
+        connection
+            .call(move |conn| <T as Crud<Rusqlite>>::delete(self, conn))
+            .await
+    }
+
+    async fn delete_where(
+        connection: &AsyncSqliteConnection,
+        query: &Query<Self>,
+    ) -> Result<usize, snafu::Whatever> {
+        let query = query.clone();
+        connection
+            .call(move |conn| <T as Crud<Rusqlite>>::delete_where(conn, &query))
+            .await
+    }
+
+    async fn clear(connection: &AsyncSqliteConnection) -> Result<usize, snafu::Whatever> {
+        connection
+            .call(|conn| <T as Crud<Rusqlite>>::clear(conn))
+            .await
+    }
+}
@@ -1,17 +1,57 @@
 //! Dynamo Db implementation.
-use aws_sdk_dynamodb::types::AttributeValue;
+use std::{collections::HashMap, time::Duration};
 
-use crate::{Value, HasCrudFields, Crud};
+use aws_sdk_dynamodb::{
+    client::Waiters,
+    error::SdkError,
+    operation::{batch_write_item::BatchWriteItemError, scan::builders::ScanFluentBuilder},
+    types::{
+        AttributeDefinition, AttributeValue, BillingMode, DeleteRequest, KeySchemaElement, KeyType,
+        KeysAndAttributes, PutRequest, ReturnValue, ScalarAttributeType, Select, WriteRequest,
+    },
+};
+use snafu::{OptionExt, ResultExt};
 
-impl From<Value> for AttributeValue {
-    fn from(value: Value) -> Self {
-        match value {
+use crate::{
+    ensure_all_finite, ensure_finite, ensure_mutable, try_from_rows_parallel, Comparison,
+    Condition, Crud, FieldMap, HasCrudFields, Page, Query, Value, ValueType,
+};
+
+/// Drive a future from the SDK to completion.
+///
+/// `Crud` is synchronous, so when called from within a tokio runtime this
+/// blocks the current worker thread (which requires the multi-threaded
+/// runtime). Otherwise a temporary runtime is used.
+pub(crate) fn block_on<F: std::future::Future>(future: F) -> F::Output {
+    match tokio::runtime::Handle::try_current() {
+        Ok(handle) => tokio::task::block_in_place(|| handle.block_on(future)),
+        Err(_) => tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("could not build a tokio runtime")
+            .block_on(future),
+    }
+}
+
+/// DynamoDB numbers are finite, so NaN and infinite floats are errors.
+impl TryFrom<Value> for AttributeValue {
+    type Error = snafu::Whatever;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        Ok(match value {
             Value::Integer(i) => AttributeValue::N(i.to_string()),
-            Value::Float(i) => AttributeValue::N(i.to_string()),
+            Value::Float(i) => {
+                snafu::ensure_whatever!(
+                    i.is_finite(),
+                    "can't store the float {i}, DynamoDB doesn't support NaN or infinite numbers"
+                );
+                AttributeValue::N(i.to_string())
+            }
             Value::String(i) => AttributeValue::S(i),
             Value::Bytes(i) => AttributeValue::B(aws_sdk_dynamodb::primitives::Blob::new(i)),
+            Value::Boolean(i) => AttributeValue::Bool(i),
             Value::None => AttributeValue::Null(true),
-        }
+        })
     }
 }
 
@@ -29,54 +69,952 @@ impl From<AttributeValue> for Value {
                 }
             }
             AttributeValue::S(s) => Value::String(s),
+            AttributeValue::Bool(b) => Value::Boolean(b),
             _ => Value::None,
         }
     }
 }
 
+/// Convert `T` into a DynamoDB item.
+///
+/// NaN and infinite floats are errors, see [`ensure_finite`].
+fn to_item<T: HasCrudFields>(t: &T) -> Result<HashMap<String, AttributeValue>, snafu::Whatever> {
+    let fields = t.as_crud_fields();
+    ensure_all_finite::<T>("DynamoDB", &fields)?;
+    fields
+        .into_iter()
+        .map(|(k, v)| Ok((k.to_string(), AttributeValue::try_from(v)?)))
+        .collect()
+}
+
+/// Convert a DynamoDB item into the fields of `T`.
+///
+/// Attributes missing from the item are read as `Value::None`. Numbers are
+/// stored as text, so they're coerced to the type of their field, eg. a
+/// float `600.0` that reads as the integer `600`.
+fn item_to_fields<T: HasCrudFields>(
+    mut item: HashMap<String, AttributeValue>,
+) -> Result<FieldMap<'static>, snafu::Whatever> {
+    T::crud_fields()
+        .iter()
+        .map(|field| {
+            let value = item
+                .remove(field.name)
+                .map(Value::from)
+                .unwrap_or(Value::None)
+                .coerce_to(field.ty)
+                .whatever_context(format!("could not read `{}`", field.name))?;
+            Ok((field.name, value))
+        })
+        .collect()
+}
+
+/// Convert a DynamoDB item into `T`.
+fn from_item<T: HasCrudFields>(
+    item: HashMap<String, AttributeValue>,
+) -> Result<T, snafu::Whatever> {
+    T::try_from_crud_fields(&item_to_fields::<T>(item)?)
+}
+
+/// The DynamoDB condition operator for `comparison`.
+fn condition_operator(comparison: Comparison) -> Result<&'static str, snafu::Whatever> {
+    match comparison {
+        Comparison::Ne => Ok("<>"),
+        Comparison::Like => snafu::whatever!(
+            "DynamoDB does not support the comparison `{}`",
+            comparison.as_sql()
+        ),
+        comparison => Ok(comparison.as_sql()),
+    }
+}
+
+/// `n` as a `Scan` limit, which is an `i32`.
+fn scan_limit(n: usize) -> i32 {
+    i32::try_from(n).unwrap_or(i32::MAX)
+}
+
+/// Controls how [`DynamoDb::insert_many_with`] batches writes.
+#[derive(Debug, Clone)]
+pub struct BatchWriteConfig {
+    /// Maximum number of `BatchWriteItem` requests in flight at once.
+    pub concurrency: usize,
+    /// How many times to retry a batch's unprocessed items.
+    pub max_retries: usize,
+    /// Delay before the first retry, doubled on each subsequent retry.
+    pub initial_backoff: Duration,
+}
+
+impl Default for BatchWriteConfig {
+    fn default() -> Self {
+        Self {
+            concurrency: 4,
+            max_retries: 8,
+            initial_backoff: Duration::from_millis(50),
+        }
+    }
+}
+
 pub struct DynamoDb;
 
+impl DynamoDb {
+    /// The most items DynamoDB accepts in one `BatchWriteItem` call.
+    pub const BATCH_WRITE_LIMIT: usize = 25;
+
+    /// The most keys DynamoDB accepts in one `BatchGetItem` call.
+    pub const BATCH_GET_LIMIT: usize = 100;
+
+    /// How long [`Crud::create`] waits for a new table to become `ACTIVE`.
+    pub const CREATE_TABLE_TIMEOUT: Duration = Duration::from_secs(300);
+
+    /// Insert many items using `BatchWriteItem`.
+    ///
+    /// Items are chunked into batches of [`DynamoDb::BATCH_WRITE_LIMIT`] and up
+    /// to `config.concurrency` batches are written in parallel. Unprocessed
+    /// items are retried with exponential backoff.
+    pub fn insert_many_with<T: HasCrudFields>(
+        client: &aws_sdk_dynamodb::Client,
+        items: &[T],
+        config: &BatchWriteConfig,
+    ) -> Result<(), snafu::Whatever> {
+        let table_name = T::table_name();
+        let batches = items
+            .chunks(Self::BATCH_WRITE_LIMIT)
+            .map(|chunk| {
+                chunk
+                    .iter()
+                    .map(|t| {
+                        let put = PutRequest::builder()
+                            .set_item(Some(to_item(t)?))
+                            .build()
+                            .whatever_context("build put request")?;
+                        Ok(WriteRequest::builder().put_request(put).build())
+                    })
+                    .collect::<Result<Vec<_>, snafu::Whatever>>()
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Self::write_batches(client, table_name, batches, config)
+    }
+
+    /// Delete the items with the given primary keys using `BatchWriteItem`.
+    ///
+    /// Batched the same way as [`DynamoDb::insert_many_with`].
+    pub fn delete_many_with<T: HasCrudFields>(
+        client: &aws_sdk_dynamodb::Client,
+        keys: Vec<Value>,
+        config: &BatchWriteConfig,
+    ) -> Result<(), snafu::Whatever> {
+        let table_name = T::table_name();
+        let key_name = T::primary_key_name();
+        let batches = keys
+            .chunks(Self::BATCH_WRITE_LIMIT)
+            .map(|chunk| {
+                chunk
+                    .iter()
+                    .map(|key| {
+                        let delete = DeleteRequest::builder()
+                            .key(key_name, AttributeValue::try_from(key.clone())?)
+                            .build()
+                            .whatever_context("build delete request")?;
+                        Ok(WriteRequest::builder().delete_request(delete).build())
+                    })
+                    .collect::<Result<Vec<_>, snafu::Whatever>>()
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Self::write_batches(client, table_name, batches, config)
+    }
+
+    /// Write `batches` to `table_name`, up to `config.concurrency` at once.
+    fn write_batches(
+        client: &aws_sdk_dynamodb::Client,
+        table_name: &'static str,
+        batches: Vec<Vec<WriteRequest>>,
+        config: &BatchWriteConfig,
+    ) -> Result<(), snafu::Whatever> {
+        let concurrency = config.concurrency.max(1);
+        let max_retries = config.max_retries;
+        let check = |joined: Result<
+            Result<Vec<WriteRequest>, SdkError<BatchWriteItemError>>,
+            tokio::task::JoinError,
+        >|
+         -> Result<(), snafu::Whatever> {
+            let unprocessed: Vec<WriteRequest> = joined
+                .whatever_context("batch write task")?
+                .whatever_context(format!("could not batch write to `{table_name}`"))?;
+            if !unprocessed.is_empty() {
+                snafu::whatever!(
+                    "{} items were still unprocessed by `{table_name}` after {max_retries} retries",
+                    unprocessed.len()
+                );
+            }
+            Ok(())
+        };
+        block_on(async {
+            let mut in_flight = tokio::task::JoinSet::new();
+            for batch in batches {
+                if in_flight.len() >= concurrency {
+                    if let Some(joined) = in_flight.join_next().await {
+                        check(joined)?;
+                    }
+                }
+                in_flight.spawn(Self::batch_write(
+                    client.clone(),
+                    table_name,
+                    batch,
+                    config.clone(),
+                ));
+            }
+            while let Some(joined) = in_flight.join_next().await {
+                check(joined)?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Query `T`'s table for items whose primary key equals `key`, following
+    /// pagination to the end.
+    fn query_key<T: HasCrudFields>(
+        client: &aws_sdk_dynamodb::Client,
+        key: Value,
+    ) -> Result<Vec<T>, snafu::Whatever> {
+        let table_name = T::table_name();
+        let mut items = vec![];
+        let mut start_key = None;
+        loop {
+            let output = block_on(
+                client
+                    .query()
+                    .table_name(table_name)
+                    .key_condition_expression("#k = :v")
+                    .expression_attribute_names("#k", T::primary_key_name())
+                    .expression_attribute_values(":v", AttributeValue::try_from(key.clone())?)
+                    .set_exclusive_start_key(start_key)
+                    .send(),
+            )
+            .whatever_context(format!("could not query `{table_name}`"))?;
+            for item in output.items().iter() {
+                items.push(from_item(item.clone())?);
+            }
+            start_key = output.last_evaluated_key().cloned();
+            if start_key.is_none() {
+                return Ok(items);
+            }
+        }
+    }
+
+    /// Write one batch, retrying unprocessed items.
+    ///
+    /// Returns the items that were still unprocessed after all retries.
+    async fn batch_write(
+        client: aws_sdk_dynamodb::Client,
+        table_name: &'static str,
+        mut requests: Vec<WriteRequest>,
+        config: BatchWriteConfig,
+    ) -> Result<Vec<WriteRequest>, SdkError<BatchWriteItemError>> {
+        let mut backoff = config.initial_backoff;
+        for attempt in 0..=config.max_retries {
+            if attempt > 0 {
+                tokio::time::sleep(backoff).await;
+                backoff *= 2;
+            }
+            let output = client
+                .batch_write_item()
+                .request_items(table_name, requests)
+                .send()
+                .await?;
+            requests = output
+                .unprocessed_items()
+                .and_then(|unprocessed| unprocessed.get(table_name))
+                .cloned()
+                .unwrap_or_default();
+            if requests.is_empty() {
+                break;
+            }
+        }
+        Ok(requests)
+    }
+
+    /// Scan `T`'s table, following pagination to the end.
+    ///
+    /// Only items matching all `conditions` are returned.
+    fn scan<T: HasCrudFields>(
+        client: &aws_sdk_dynamodb::Client,
+        conditions: &[Condition],
+    ) -> Result<Vec<T>, snafu::Whatever> {
+        let mut clauses = vec![];
+        let mut names = HashMap::new();
+        let mut values = HashMap::new();
+        for (i, condition) in conditions.iter().enumerate() {
+            let op = condition_operator(condition.comparison)?;
+            clauses.push(format!("#k{i} {op} :v{i}"));
+            names.insert(format!("#k{i}"), condition.column.clone());
+            values.insert(
+                format!(":v{i}"),
+                AttributeValue::try_from(condition.value.clone())?,
+            );
+        }
+        Self::scan_items::<T>(client, |scan| {
+            if clauses.is_empty() {
+                scan
+            } else {
+                scan.filter_expression(clauses.join(" AND "))
+                    .set_expression_attribute_names(Some(names.clone()))
+                    .set_expression_attribute_values(Some(values.clone()))
+            }
+        })?
+        .into_iter()
+        .map(from_item)
+        .collect()
+    }
+
+    /// Scan `T`'s table with the request built by `customize`, following
+    /// pagination to the end, and return the raw items.
+    fn scan_items<T: HasCrudFields>(
+        client: &aws_sdk_dynamodb::Client,
+        customize: impl Fn(ScanFluentBuilder) -> ScanFluentBuilder,
+    ) -> Result<Vec<HashMap<String, AttributeValue>>, snafu::Whatever> {
+        let table_name = T::table_name();
+        let mut items = vec![];
+        let mut start_key = None;
+        loop {
+            let scan = client
+                .scan()
+                .table_name(table_name)
+                .set_exclusive_start_key(start_key);
+            let output = block_on(customize(scan).send())
+                .whatever_context(format!("could not scan `{table_name}`"))?;
+            items.extend(output.items.unwrap_or_default());
+            start_key = output.last_evaluated_key;
+            if start_key.is_none() {
+                return Ok(items);
+            }
+        }
+    }
+
+    /// Get the items of `T`'s table with the primary keys `keys` using
+    /// `BatchGetItem`, sorted by primary key.
+    ///
+    /// Keys are requested in chunks of [`DynamoDb::BATCH_GET_LIMIT`], and
+    /// unprocessed keys are retried with the backoff of a default
+    /// [`BatchWriteConfig`]. Keys without an item are skipped.
+    fn batch_get<T: HasCrudFields>(
+        client: &aws_sdk_dynamodb::Client,
+        keys: Vec<Value>,
+    ) -> Result<Vec<T>, snafu::Whatever> {
+        let table_name = T::table_name();
+        let key_name = T::primary_key_name();
+        let config = BatchWriteConfig::default();
+        let mut items = vec![];
+        for chunk in keys.chunks(Self::BATCH_GET_LIMIT) {
+            let keys = chunk
+                .iter()
+                .map(|key| {
+                    let key = AttributeValue::try_from(key.clone())?;
+                    Ok(HashMap::from([(key_name.to_string(), key)]))
+                })
+                .collect::<Result<Vec<_>, snafu::Whatever>>()?;
+            let mut request = Some(
+                KeysAndAttributes::builder()
+                    .set_keys(Some(keys))
+                    .consistent_read(true)
+                    .build()
+                    .whatever_context("build keys and attributes")?,
+            );
+            let mut backoff = config.initial_backoff;
+            for attempt in 0..=config.max_retries {
+                let Some(keys) = request.take() else {
+                    break;
+                };
+                if attempt > 0 {
+                    block_on(tokio::time::sleep(backoff));
+                    backoff *= 2;
+                }
+                let output = block_on(
+                    client
+                        .batch_get_item()
+                        .request_items(table_name, keys)
+                        .send(),
+                )
+                .whatever_context(format!("could not batch get from `{table_name}`"))?;
+                if let Some(mut responses) = output.responses {
+                    for item in responses.remove(table_name).unwrap_or_default() {
+                        items.push(from_item::<T>(item)?);
+                    }
+                }
+                request = output
+                    .unprocessed_keys
+                    .and_then(|mut unprocessed| unprocessed.remove(table_name))
+                    .filter(|keys| !keys.keys().is_empty());
+            }
+            snafu::ensure_whatever!(
+                request.is_none(),
+                "keys were still unprocessed by `{table_name}` after {} retries",
+                config.max_retries
+            );
+        }
+        items.sort_by(|a, b| {
+            a.primary_key_val()
+                .partial_cmp(&b.primary_key_val())
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        Ok(items)
+    }
+
+    /// Count the items of `T`'s table from `start_key`, at most `limit` of
+    /// them if given, returning the count and the key to continue from.
+    fn count_from<T: HasCrudFields>(
+        client: &aws_sdk_dynamodb::Client,
+        start_key: Option<HashMap<String, AttributeValue>>,
+        limit: Option<usize>,
+    ) -> Result<(usize, Option<HashMap<String, AttributeValue>>), snafu::Whatever> {
+        let table_name = T::table_name();
+        let output = block_on(
+            client
+                .scan()
+                .table_name(table_name)
+                .select(Select::Count)
+                .set_limit(limit.map(scan_limit))
+                .set_exclusive_start_key(start_key)
+                .send(),
+        )
+        .whatever_context(format!("could not count `{table_name}`"))?;
+        let count = usize::try_from(output.count).whatever_context("negative count")?;
+        Ok((count, output.last_evaluated_key))
+    }
+}
+
 impl<T: HasCrudFields + Clone + Sized + 'static> Crud<DynamoDb> for T {
     type Connection<'a> = &'a aws_sdk_dynamodb::Client;
 
-    fn create(_: Self::Connection<'_>) -> Result<(), snafu::Whatever> {
+    /// Creates the table with the primary key as its partition key and
+    /// on-demand billing, then waits until it's `ACTIVE`.
+    ///
+    /// A table that already exists is left as is.
+    fn create(client: Self::Connection<'_>) -> Result<(), snafu::Whatever> {
+        let table_name = Self::table_name();
+        let key_name = Self::primary_key_name();
+        let key_field = Self::crud_fields()
+            .iter()
+            .find(|field| field.name == key_name)
+            .whatever_context(format!(
+                "`{table_name}` has no primary key field `{key_name}`"
+            ))?;
+        let attribute_type = match key_field.ty {
+            ValueType::Integer | ValueType::Float => ScalarAttributeType::N,
+            ValueType::String => ScalarAttributeType::S,
+            ValueType::Bytes => ScalarAttributeType::B,
+        };
+        let key_schema = KeySchemaElement::builder()
+            .attribute_name(key_name)
+            .key_type(KeyType::Hash)
+            .build()
+            .whatever_context("build key schema")?;
+        let attribute = AttributeDefinition::builder()
+            .attribute_name(key_name)
+            .attribute_type(attribute_type)
+            .build()
+            .whatever_context("build attribute definition")?;
+        block_on(async {
+            let created = client
+                .create_table()
+                .table_name(table_name)
+                .key_schema(key_schema)
+                .attribute_definitions(attribute)
+                .billing_mode(BillingMode::PayPerRequest)
+                .send()
+                .await;
+            if let Err(err) = created {
+                let exists = err
+                    .as_service_error()
+                    .is_some_and(|err| err.is_resource_in_use_exception());
+                if !exists {
+                    return Err(err)
+                        .whatever_context(format!("could not create table `{table_name}`"));
+                }
+            }
+            client
+                .wait_until_table_exists()
+                .table_name(table_name)
+                .wait(DynamoDb::CREATE_TABLE_TIMEOUT)
+                .await
+                .whatever_context(format!("table `{table_name}` did not become active"))?;
+            Ok(())
+        })
+    }
+
+    fn healthcheck(client: Self::Connection<'_>) -> Result<(), snafu::Whatever> {
+        let table_name = Self::table_name();
+        block_on(client.describe_table().table_name(table_name).send())
+            .whatever_context(format!("could not describe table `{table_name}`"))?;
         Ok(())
     }
 
     fn insert(&self, client: Self::Connection<'_>) -> Result<(), snafu::Whatever> {
-        client
-            .put_item()
-            .
+        let table_name = Self::table_name();
+        block_on(
+            client
+                .put_item()
+                .table_name(table_name)
+                .set_item(Some(to_item(self)?))
+                .send(),
+        )
+        .whatever_context(format!("could not put item into `{table_name}`"))?;
+        Ok(())
+    }
+
+    /// A `PutItem` on the condition that no item has the primary key.
+    fn insert_or_ignore(&self, client: Self::Connection<'_>) -> Result<bool, snafu::Whatever> {
+        let table_name = Self::table_name();
+        let put = block_on(
+            client
+                .put_item()
+                .table_name(table_name)
+                .set_item(Some(to_item(self)?))
+                .condition_expression("attribute_not_exists(#k)")
+                .expression_attribute_names("#k", Self::primary_key_name())
+                .send(),
+        );
+        match put {
+            Ok(_) => Ok(true),
+            Err(err)
+                if err
+                    .as_service_error()
+                    .is_some_and(|err| err.is_conditional_check_failed_exception()) =>
+            {
+                Ok(false)
+            }
+            Err(err) => {
+                Err(err).whatever_context(format!("could not put item into `{table_name}`"))
+            }
+        }
     }
 
+    /// Puts the item, then reads it back with a strongly consistent `GetItem`.
+    ///
+    /// DynamoDB fills in no values, so this only shows how `self` was stored.
+    fn insert_returning(&self, client: Self::Connection<'_>) -> Result<Self, snafu::Whatever> {
+        <Self as Crud<DynamoDb>>::insert(self, client)?;
+        let table_name = Self::table_name();
+        let (key_name, key_value) = self.primary_key();
+        let output = block_on(
+            client
+                .get_item()
+                .table_name(table_name)
+                .key(key_name, AttributeValue::try_from(key_value)?)
+                .consistent_read(true)
+                .send(),
+        )
+        .whatever_context(format!("could not get item from `{table_name}`"))?;
+        from_item(output.item.whatever_context("insert returned no item")?)
+    }
+
+    fn insert_many(client: Self::Connection<'_>, items: &[Self]) -> Result<(), snafu::Whatever> {
+        DynamoDb::insert_many_with(client, items, &BatchWriteConfig::default())
+    }
+
+    /// Items are read with a paginated `Scan`, which has no order, and then
+    /// sorted by primary key.
     fn read_all<'a>(
-        connection: Self::Connection<'a>,
+        client: Self::Connection<'a>,
     ) -> Result<Box<dyn Iterator<Item = Result<Self, snafu::Whatever>> + 'a>, snafu::Whatever> {
-        todo!()
+        let mut items = DynamoDb::scan::<Self>(client, &[])?;
+        items.sort_by(|a, b| {
+            a.primary_key_val()
+                .partial_cmp(&b.primary_key_val())
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        Ok(Box::new(items.into_iter().map(Ok)))
     }
 
+    /// A paginated `Scan` on the calling thread, with the items deserialized
+    /// on `threads` scoped threads.
+    fn read_all_parallel(
+        client: Self::Connection<'_>,
+        threads: usize,
+    ) -> Result<Vec<Self>, snafu::Whatever>
+    where
+        Self: Send,
+    {
+        let rows = DynamoDb::scan_items::<Self>(client, |scan| scan)?
+            .into_iter()
+            .map(item_to_fields::<Self>)
+            .collect::<Result<Vec<_>, _>>()?;
+        try_from_rows_parallel(&rows, threads)
+    }
+
+    /// A paginated `Scan` that only counts the items.
+    fn count(client: Self::Connection<'_>) -> Result<usize, snafu::Whatever> {
+        let mut count = 0;
+        let mut start_key = None;
+        loop {
+            let (counted, next_key) = DynamoDb::count_from::<Self>(client, start_key, None)?;
+            count += counted;
+            start_key = next_key;
+            if start_key.is_none() {
+                return Ok(count);
+            }
+        }
+    }
+
+    /// The first `offset` items are counted rather than read, then the page
+    /// is scanned from where they end.
+    ///
+    /// Pages follow the table's scan order, which is stable while the table
+    /// doesn't change, rather than the primary key order of
+    /// [`Crud::read_all`].
+    fn read_page_typed(
+        client: Self::Connection<'_>,
+        limit: usize,
+        offset: usize,
+    ) -> Result<Page<Self>, snafu::Whatever> {
+        let table_name = Self::table_name();
+        let total = <Self as Crud<DynamoDb>>::count(client)?;
+        let mut skipped = 0;
+        let mut start_key = None;
+        while skipped < offset {
+            let (counted, next_key) =
+                DynamoDb::count_from::<Self>(client, start_key, Some(offset - skipped))?;
+            skipped += counted;
+            start_key = next_key;
+            if start_key.is_none() {
+                return Ok(Page::new(vec![], total, limit, offset));
+            }
+        }
+        let mut items = vec![];
+        while items.len() < limit {
+            let output = block_on(
+                client
+                    .scan()
+                    .table_name(table_name)
+                    .limit(scan_limit(limit - items.len()))
+                    .set_exclusive_start_key(start_key)
+                    .send(),
+            )
+            .whatever_context(format!("could not scan `{table_name}`"))?;
+            for item in output.items.unwrap_or_default() {
+                items.push(from_item(item)?);
+            }
+            start_key = output.last_evaluated_key;
+            if start_key.is_none() {
+                break;
+            }
+        }
+        Ok(Page::new(items, total, limit, offset))
+    }
+
+    /// Only the primary key can be read this way, with `BatchGetItem`.
+    /// Results are sorted by primary key.
+    fn read_in<'a, Key: crate::IsCrudField>(
+        client: Self::Connection<'a>,
+        column: &str,
+        values: Vec<Key>,
+    ) -> Result<Box<dyn Iterator<Item = Result<Self, snafu::Whatever>> + 'a>, snafu::Whatever> {
+        let table_name = Self::table_name();
+        let key_name = Self::primary_key_name();
+        snafu::ensure_whatever!(
+            column == key_name,
+            "can't read `{table_name}` where `{column}` is in a list, \
+             DynamoDB can only do that for the primary key `{key_name}`"
+        );
+        let keys = values.iter().map(crate::IsCrudField::into_value).collect();
+        let items = DynamoDb::batch_get::<Self>(client, keys)?;
+        Ok(Box::new(items.into_iter().map(Ok)))
+    }
+
+    /// A `Scan` with a `ProjectionExpression`, so only `columns` are read,
+    /// sorted by primary key like [`Crud::read_all`].
+    fn read_projected<'a>(
+        client: Self::Connection<'_>,
+        columns: &[&'a str],
+    ) -> Result<Vec<FieldMap<'a>>, snafu::Whatever> {
+        let table_name = Self::table_name();
+        snafu::ensure_whatever!(
+            !columns.is_empty(),
+            "no columns to read from `{table_name}`"
+        );
+        let column_names = Self::field_names();
+        for column in columns {
+            snafu::ensure_whatever!(
+                column_names.contains(column),
+                "table `{table_name}` has no column `{column}`"
+            );
+        }
+        // the primary key is read too, to sort by
+        let key_name = Self::primary_key_name();
+        let mut attributes = columns.to_vec();
+        if !attributes.contains(&key_name) {
+            attributes.push(key_name);
+        }
+        let names = attributes
+            .iter()
+            .enumerate()
+            .map(|(i, name)| (format!("#c{i}"), name.to_string()))
+            .collect::<HashMap<_, _>>();
+        let projection = (0..attributes.len())
+            .map(|i| format!("#c{i}"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let items = DynamoDb::scan_items::<Self>(client, |scan| {
+            scan.projection_expression(projection.clone())
+                .set_expression_attribute_names(Some(names.clone()))
+        })?;
+        let mut rows = items
+            .into_iter()
+            .map(|mut item| {
+                let key = item.get(key_name).cloned().map(Value::from);
+                let row = columns
+                    .iter()
+                    .map(|column| {
+                        let value = item.remove(*column).map(Value::from).unwrap_or(Value::None);
+                        (*column, value)
+                    })
+                    .collect::<FieldMap>();
+                (key, row)
+            })
+            .collect::<Vec<_>>();
+        rows.sort_by(|(a, _), (b, _)| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+        Ok(rows.into_iter().map(|(_, row)| row).collect())
+    }
+
+    /// Equality on the primary key uses `Query`, anything else is a `Scan`
+    /// with a filter. `LIKE` isn't supported.
     fn read_where<'a>(
-        connection: Self::Connection<'a>,
+        client: Self::Connection<'a>,
         key_name: &'a str,
         comparison: &'a str,
         key_value: impl crate::IsCrudField,
     ) -> Result<Box<dyn Iterator<Item = Result<Self, snafu::Whatever>> + 'a>, snafu::Whatever> {
-        todo!()
+        let comparison = Comparison::from_sql(comparison)
+            .whatever_context(format!("unknown comparison `{comparison}`"))?;
+        let value = key_value.into_value();
+        let items = if key_name == Self::primary_key_name() && comparison == Comparison::Eq {
+            DynamoDb::query_key::<Self>(client, value)?
+        } else {
+            let condition = Condition {
+                column: key_name.to_string(),
+                comparison,
+                value,
+            };
+            DynamoDb::scan::<Self>(client, &[condition])?
+        };
+        Ok(Box::new(items.into_iter().map(Ok)))
     }
 
-    fn read<'a, Key: crate::IsCrudField>(
-        connection: Self::Connection<'a>,
-        key: Key,
+    fn read<'a>(
+        client: Self::Connection<'a>,
+        key: Self::PrimaryKey,
     ) -> Result<Box<dyn Iterator<Item = Result<Self, snafu::Whatever>> + 'a>, snafu::Whatever> {
-        todo!()
+        let items = DynamoDb::query_key::<Self>(client, crate::IsCrudField::into_value(&key))?;
+        Ok(Box::new(items.into_iter().map(Ok)))
     }
 
-    fn update(&self, connection: Self::Connection<'_>) -> Result<(), snafu::Whatever> {
-        todo!()
+    /// Puts the whole item, replacing the stored one.
+    fn update(&self, client: Self::Connection<'_>) -> Result<(), snafu::Whatever> {
+        ensure_mutable::<Self>("update")?;
+        let table_name = Self::table_name();
+        block_on(
+            client
+                .put_item()
+                .table_name(table_name)
+                .set_item(Some(to_item(self)?))
+                .send(),
+        )
+        .whatever_context(format!("could not update item in `{table_name}`"))?;
+        Ok(())
     }
 
-    fn delete(self, connection: Self::Connection<'_>) -> Result<(), snafu::Whatever> {
-        todo!()
+    /// A `BatchGetItem` in chunks of [`DynamoDb::BATCH_GET_LIMIT`] keys,
+    /// sorted by primary key.
+    fn read_by_keys(
+        client: Self::Connection<'_>,
+        keys: Vec<Self::PrimaryKey>,
+    ) -> Result<Vec<Self>, snafu::Whatever> {
+        let keys = keys.iter().map(crate::IsCrudField::into_value).collect();
+        DynamoDb::batch_get::<Self>(client, keys)
     }
 
+    /// An `UpdateItem` that sets only the changed attributes, on the
+    /// condition that the item exists. Returns zero without writing if there
+    /// is no item with this primary key.
+    fn update_diff_count(
+        &self,
+        old: &Self,
+        client: Self::Connection<'_>,
+    ) -> Result<usize, snafu::Whatever> {
+        ensure_mutable::<Self>("update")?;
+        let table_name = Self::table_name();
+        let (key_name, key_value) = self.primary_key();
+        snafu::ensure_whatever!(
+            key_value == old.primary_key_val(),
+            "can't diff rows with different primary keys"
+        );
+        let old_fields = old.as_crud_fields();
+        let changed = self
+            .as_crud_fields()
+            .into_iter()
+            .filter(|(name, value)| *name != key_name && old_fields.get(name) != Some(value))
+            .collect::<Vec<_>>();
+        if changed.is_empty() {
+            return Ok(0);
+        }
+        let set = (0..changed.len())
+            .map(|i| format!("#f{i} = :v{i}"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let mut update = client
+            .update_item()
+            .table_name(table_name)
+            .key(key_name, AttributeValue::try_from(key_value)?)
+            .update_expression(format!("SET {set}"))
+            .condition_expression("attribute_exists(#k)")
+            .expression_attribute_names("#k", key_name);
+        let count = changed.len();
+        for (i, (name, value)) in changed.into_iter().enumerate() {
+            ensure_finite::<Self>("DynamoDB", name, &value)?;
+            update = update
+                .expression_attribute_names(format!("#f{i}"), name)
+                .expression_attribute_values(format!(":v{i}"), AttributeValue::try_from(value)?);
+        }
+        match block_on(update.send()) {
+            Ok(_) => Ok(count),
+            Err(err)
+                if err
+                    .as_service_error()
+                    .is_some_and(|err| err.is_conditional_check_failed_exception()) =>
+            {
+                Ok(0)
+            }
+            Err(err) => {
+                Err(err).whatever_context(format!("could not update item in `{table_name}`"))
+            }
+        }
+    }
+
+    /// Matching keys are found with a `Scan` and then each item gets a
+    /// conditional `UpdateItem`, so this isn't atomic. Items that stop
+    /// matching in between are left alone and not counted.
+    fn update_where(
+        client: Self::Connection<'_>,
+        set_col: &str,
+        set_val: impl crate::IsCrudField,
+        key_name: &str,
+        comparison: Comparison,
+        key_value: impl crate::IsCrudField,
+    ) -> Result<u64, snafu::Whatever> {
+        ensure_mutable::<Self>("update")?;
+        let table_name = Self::table_name();
+        let column_names = Self::field_names();
+        for column in [set_col, key_name] {
+            snafu::ensure_whatever!(
+                column_names.contains(&column),
+                "table `{table_name}` has no column `{column}`"
+            );
+        }
+        let primary_key = Self::primary_key_name();
+        snafu::ensure_whatever!(
+            set_col != primary_key,
+            "can't set the primary key `{primary_key}` of `{table_name}`, DynamoDB keys are immutable"
+        );
+        let op = condition_operator(comparison)?;
+        let names = HashMap::from([
+            ("#k".to_string(), primary_key.to_string()),
+            ("#c".to_string(), key_name.to_string()),
+        ]);
+        let key_value = AttributeValue::try_from(key_value.into_value())?;
+        let filter = format!("#c {op} :c");
+        let keys = DynamoDb::scan_items::<Self>(client, |scan| {
+            scan.projection_expression("#k")
+                .filter_expression(filter.clone())
+                .set_expression_attribute_names(Some(names.clone()))
+                .expression_attribute_values(":c", key_value.clone())
+        })?
+        .into_iter()
+        .filter_map(|mut item| item.remove(primary_key))
+        .collect::<Vec<_>>();
+        let set_val = set_val.into_value();
+        ensure_finite::<Self>("DynamoDB", set_col, &set_val)?;
+        let set_val = AttributeValue::try_from(set_val)?;
+        let mut updated = 0;
+        for key in keys {
+            let update = block_on(
+                client
+                    .update_item()
+                    .table_name(table_name)
+                    .key(primary_key, key)
+                    .update_expression("SET #s = :s")
+                    .condition_expression(format!("attribute_exists(#k) AND {filter}"))
+                    .set_expression_attribute_names(Some(names.clone()))
+                    .expression_attribute_names("#s", set_col)
+                    .expression_attribute_values(":s", set_val.clone())
+                    .expression_attribute_values(":c", key_value.clone())
+                    .send(),
+            );
+            match update {
+                Ok(_) => updated += 1,
+                Err(err)
+                    if err
+                        .as_service_error()
+                        .is_some_and(|err| err.is_conditional_check_failed_exception()) => {}
+                Err(err) => {
+                    return Err(err)
+                        .whatever_context(format!("could not update item in `{table_name}`"));
+                }
+            }
+        }
+        Ok(updated)
+    }
+
+    fn delete(self, client: Self::Connection<'_>) -> Result<bool, snafu::Whatever> {
+        ensure_mutable::<Self>("delete from")?;
+        let table_name = Self::table_name();
+        let (key_name, key_value) = self.primary_key();
+        // the old item only comes back if there was one
+        let output = block_on(
+            client
+                .delete_item()
+                .table_name(table_name)
+                .key(key_name, AttributeValue::try_from(key_value)?)
+                .return_values(ReturnValue::AllOld)
+                .send(),
+        )
+        .whatever_context(format!("could not delete item from `{table_name}`"))?;
+        Ok(output.attributes().is_some_and(|item| !item.is_empty()))
+    }
+
+    /// Matching items are found with a `Scan` and then deleted in batches,
+    /// so this isn't atomic.
+    fn delete_where(
+        client: Self::Connection<'_>,
+        query: &Query<Self>,
+    ) -> Result<usize, snafu::Whatever> {
+        ensure_mutable::<Self>("delete from")?;
+        let table_name = Self::table_name();
+        snafu::ensure_whatever!(
+            !query.conditions().is_empty(),
+            "refusing to delete from `{table_name}` without conditions, use `clear` to delete every row"
+        );
+        let keys = DynamoDb::scan::<Self>(client, query.conditions())?
+            .iter()
+            .map(HasCrudFields::primary_key_val)
+            .collect::<Vec<_>>();
+        let deleted = keys.len();
+        DynamoDb::delete_many_with::<Self>(client, keys, &BatchWriteConfig::default())?;
+        Ok(deleted)
+    }
+
+    /// Keys are found with a `Scan` and then deleted in batches, so this
+    /// isn't atomic.
+    fn clear(client: Self::Connection<'_>) -> Result<usize, snafu::Whatever> {
+        ensure_mutable::<Self>("clear")?;
+        let key_name = Self::primary_key_name();
+        let keys = DynamoDb::scan_items::<Self>(client, |scan| {
+            scan.projection_expression("#k")
+                .expression_attribute_names("#k", key_name)
+        })?
+        .into_iter()
+        .filter_map(|mut item| item.remove(key_name).map(Value::from))
+        .collect::<Vec<_>>();
+        let deleted = keys.len();
+        DynamoDb::delete_many_with::<Self>(client, keys, &BatchWriteConfig::default())?;
+        Ok(deleted)
+    }
 }
@@ -0,0 +1,951 @@
+//! MySQL / MariaDB impl.
+use mysql::prelude::Queryable;
+use snafu::{OptionExt, ResultExt};
+
+use crate::{
+    columns_to_add, ensure_all_finite, ensure_finite, ensure_mutable, history_table_name,
+    try_from_rows_parallel, AuditOp, Comparison, Condition, Crud, CrudField, FieldMap,
+    HasCrudFields, History, IsCrudField, LikePattern, MigrateEntireTable, Page, Query, SqlDialect,
+    Value, ValueType,
+};
+
+/// The character set MySQL reports for binary (as opposed to text) columns.
+const BINARY_CHARSET: u16 = 63;
+
+/// The dialect of the MySQL backend.
+pub struct MySqlDialect;
+
+impl SqlDialect for MySqlDialect {
+    fn column_type(ty: &ValueType) -> &'static str {
+        match ty {
+            ValueType::Integer => "BIGINT",
+            ValueType::Float => "DOUBLE",
+            ValueType::String => "TEXT",
+            ValueType::Bytes => "BLOB",
+        }
+    }
+
+    /// MySQL binds values by position, so every placeholder is `?`.
+    fn placeholder(_index: usize) -> String {
+        "?".to_string()
+    }
+
+    fn quote_ident(name: &str) -> String {
+        format!("`{}`", name.replace('`', "``"))
+    }
+}
+
+impl ValueType {
+    /// The declared column type of this value type in MySQL.
+    pub fn mysql_type(&self) -> &'static str {
+        MySqlDialect::column_type(self)
+    }
+
+    /// The declared column type of this value type in MySQL, when used as a
+    /// primary key.
+    ///
+    /// MySQL can't index `TEXT` or `BLOB` without a length.
+    pub fn mysql_key_type(&self) -> &'static str {
+        match self {
+            ValueType::String => "VARCHAR(255)",
+            ValueType::Bytes => "VARBINARY(255)",
+            ty => ty.mysql_type(),
+        }
+    }
+}
+
+impl CrudField {
+    pub fn mysql_create_field(&self) -> String {
+        let Self {
+            name,
+            ty,
+            nullable,
+            primary_key,
+            auto_increment,
+            check,
+            // a table-level clause, see `CrudField::foreign_key_clause`
+            references: _,
+            collation,
+        } = self;
+        let name = MySqlDialect::quote_ident(name);
+        let mut parts = vec![name.as_str()];
+        if *primary_key {
+            parts.extend([ty.mysql_key_type(), "PRIMARY KEY"]);
+        } else {
+            parts.push(ty.mysql_type());
+        }
+        if *auto_increment {
+            parts.push("AUTO_INCREMENT");
+        }
+        if !nullable {
+            parts.push("NOT NULL");
+        }
+        let mut sql = parts.join(" ");
+        if let Some(collation) = collation {
+            sql.push_str(&format!(" COLLATE {collation}"));
+        }
+        if let Some(check) = check {
+            sql.push_str(&format!(" CHECK ({check})"));
+        }
+        sql
+    }
+}
+
+impl From<Value> for mysql::Value {
+    fn from(value: Value) -> Self {
+        match value {
+            Value::Integer(i) => mysql::Value::Int(i),
+            Value::Float(i) => mysql::Value::Double(i),
+            Value::String(i) => mysql::Value::Bytes(i.into_bytes()),
+            Value::Bytes(i) => mysql::Value::Bytes(i),
+            Value::Boolean(i) => mysql::Value::Int(i64::from(i)),
+            Value::None => mysql::Value::NULL,
+        }
+    }
+}
+
+/// Convert a value read from a column.
+///
+/// MySQL sends both text and binary columns as bytes, so `binary` tells them
+/// apart.
+fn value_from_mysql(value: &mysql::Value, binary: bool) -> Result<Value, snafu::Whatever> {
+    Ok(match value {
+        mysql::Value::NULL => Value::None,
+        mysql::Value::Bytes(b) if binary => Value::Bytes(b.clone()),
+        mysql::Value::Bytes(b) => {
+            Value::String(String::from_utf8(b.clone()).whatever_context("text column is not utf8")?)
+        }
+        mysql::Value::Int(i) => Value::Integer(*i),
+        mysql::Value::UInt(u) => {
+            Value::Integer(i64::try_from(*u).whatever_context("unsigned value too large")?)
+        }
+        mysql::Value::Float(f) => Value::Float(f64::from(*f)),
+        mysql::Value::Double(f) => Value::Float(*f),
+        other => snafu::whatever!("unsupported MySQL value {other:?}"),
+    })
+}
+
+/// Collect the named columns of a row.
+fn row_to_fields<'a>(
+    row: &mysql::Row,
+    column_names: &[&'a str],
+) -> Result<FieldMap<'a>, snafu::Whatever> {
+    let columns = row.columns_ref();
+    column_names
+        .iter()
+        .map(|name| {
+            let index = columns
+                .iter()
+                .position(|column| column.name_str() == *name)
+                .whatever_context(format!("row has no column `{name}`"))?;
+            let value = row.as_ref(index).whatever_context("row value was taken")?;
+            let binary = columns[index].character_set() == BINARY_CHARSET;
+            Ok((*name, value_from_mysql(value, binary)?))
+        })
+        .collect()
+}
+
+/// Check out a connection from the pool.
+fn get_conn(pool: &mysql::Pool) -> Result<mysql::PooledConn, snafu::Whatever> {
+    pool.get_conn()
+        .whatever_context("could not get a MySQL connection")
+}
+
+/// Run a `SELECT` statement and decode every row into a `T`.
+fn select<T: HasCrudFields>(
+    pool: &mysql::Pool,
+    statement: String,
+    params: Vec<mysql::Value>,
+) -> Result<Vec<T>, snafu::Whatever> {
+    let column_names = T::field_names();
+    let rows: Vec<mysql::Row> = get_conn(pool)?
+        .exec(&statement, params)
+        .whatever_context(format!("select: {statement}"))?;
+    rows.iter()
+        .map(|row| T::try_from_crud_fields(&row_to_fields(row, &column_names)?))
+        .collect()
+}
+
+/// The statement that inserts `fields` into `table_name`, ending with
+/// `suffix`.
+fn insert_sql(table_name: &str, fields: &FieldMap<'_>, suffix: &str) -> String {
+    let columns = fields
+        .keys()
+        .map(|name| MySqlDialect::quote_ident(name))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let binds = (0..fields.len())
+        .map(MySqlDialect::placeholder)
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!(
+        "INSERT INTO {} ({columns}) VALUES ({binds}){suffix};",
+        MySqlDialect::quote_ident(table_name)
+    )
+}
+
+/// The statement that sets `columns` of the row of `table_name` whose
+/// `primary_key` is bound to the last placeholder.
+fn update_sql(table_name: &str, columns: &[&str], primary_key: &str) -> String {
+    let values = columns
+        .iter()
+        .enumerate()
+        .map(|(i, name)| {
+            format!(
+                "{} = {}",
+                MySqlDialect::quote_ident(name),
+                MySqlDialect::placeholder(i)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!(
+        "UPDATE {} SET {values} WHERE {} = {};",
+        MySqlDialect::quote_ident(table_name),
+        MySqlDialect::quote_ident(primary_key),
+        MySqlDialect::placeholder(columns.len())
+    )
+}
+
+/// The statement that deletes the rows of `table_name` matching `clause`, as
+/// made by [`where_clause`].
+fn delete_sql(table_name: &str, clause: &str) -> String {
+    format!(
+        "DELETE FROM {}{clause};",
+        MySqlDialect::quote_ident(table_name)
+    )
+}
+
+/// Insert `fields` into `table_name` using any connection or transaction.
+fn insert_fields_with(
+    conn: &mut impl Queryable,
+    table_name: &str,
+    fields: &FieldMap<'_>,
+) -> Result<(), snafu::Whatever> {
+    let statement = insert_sql(table_name, fields, "");
+    let params = fields
+        .values()
+        .cloned()
+        .map(mysql::Value::from)
+        .collect::<Vec<_>>();
+    conn.exec_drop(&statement, params)
+        .whatever_context(format!("insert: {statement}"))
+}
+
+impl MigrateEntireTable for MySql {
+    type Connection<'a> = &'a mysql::Pool;
+
+    fn read_all_values<'a>(
+        connection: &'a mysql::Pool,
+        table_name: &'a str,
+        column_names: Vec<&'a str>,
+    ) -> Result<Vec<Result<FieldMap<'a>, snafu::Whatever>>, snafu::Whatever> {
+        let statement = format!("SELECT * FROM {};", MySqlDialect::quote_ident(table_name));
+        let rows: Vec<mysql::Row> = get_conn(connection)?
+            .exec(statement, ())
+            .whatever_context("read all")?;
+        Ok(rows
+            .iter()
+            .map(|row| row_to_fields(row, &column_names))
+            .collect())
+    }
+
+    fn insert_fields(
+        connection: &mysql::Pool,
+        table_name: &str,
+        fields: &FieldMap<'_>,
+    ) -> Result<(), snafu::Whatever> {
+        insert_fields_with(&mut get_conn(connection)?, table_name, fields)
+    }
+
+    fn delete_all(connection: &mysql::Pool, table_name: &str) -> Result<(), snafu::Whatever> {
+        get_conn(connection)?
+            .exec_drop(delete_sql(table_name, ""), ())
+            .whatever_context("clear table")
+    }
+
+    fn create_table(
+        connection: &mysql::Pool,
+        table_name: &str,
+        fields: &[CrudField],
+    ) -> Result<(), snafu::Whatever> {
+        let statement = MySql::create_table_sql_from(table_name, fields);
+        get_conn(connection)?
+            .query_drop(&statement)
+            .whatever_context(format!("could not create `{table_name}`: {statement}"))
+    }
+
+    fn table_exists(connection: &mysql::Pool, table_name: &str) -> Result<bool, snafu::Whatever> {
+        let statement = "SELECT COUNT(*) FROM information_schema.tables \
+                         WHERE table_schema = DATABASE() AND table_name = ?;";
+        let count: Option<i64> = get_conn(connection)?
+            .exec_first(statement, (table_name,))
+            .whatever_context("table exists")?;
+        Ok(count.unwrap_or_default() > 0)
+    }
+}
+
+pub struct MySql;
+
+impl MySql {
+    /// The most placeholders one MySQL statement may bind.
+    pub const MAX_PLACEHOLDERS: usize = 65_535;
+
+    /// The statement [`Crud::create`] runs to create `T`'s table.
+    pub fn create_table_sql<T: HasCrudFields>() -> String {
+        Self::create_table_sql_from(T::table_name(), T::crud_fields())
+    }
+
+    /// The statement that creates `table_name` with the columns of `fields`.
+    fn create_table_sql_from(table_name: &str, fields: &[CrudField]) -> String {
+        let fields: String = fields
+            .iter()
+            .map(CrudField::mysql_create_field)
+            .chain(
+                fields
+                    .iter()
+                    .filter_map(CrudField::foreign_key_clause::<MySqlDialect>),
+            )
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!(
+            "CREATE TABLE IF NOT EXISTS {} ({fields});",
+            MySqlDialect::quote_ident(table_name)
+        )
+    }
+
+    /// The statements [`Crud::create`] runs to create the history table of
+    /// an `#[audited]` `T`, and the triggers that fill it.
+    ///
+    /// MySQL tables have no `rowid`, so the history table has an extra
+    /// `_seq` column to order versions by.
+    pub fn create_history_sql<T: HasCrudFields>() -> Vec<String> {
+        let table_name = MySqlDialect::quote_ident(T::table_name());
+        let history_name = history_table_name::<T>();
+        let history = MySqlDialect::quote_ident(&history_name);
+        let fields = T::crud_fields();
+        let names = fields
+            .iter()
+            .map(|field| MySqlDialect::quote_ident(field.name))
+            .collect::<Vec<_>>();
+        let columns = fields
+            .iter()
+            .zip(&names)
+            .map(|(field, name)| format!("{name} {}", field.ty.mysql_type()))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let mut statements = vec![format!(
+            "CREATE TABLE IF NOT EXISTS {history} (_seq BIGINT AUTO_INCREMENT PRIMARY KEY, \
+             {columns}, _op VARCHAR(6) NOT NULL, _at VARCHAR(32) NOT NULL);"
+        )];
+        for (op, row) in [
+            (AuditOp::Insert, "NEW"),
+            (AuditOp::Update, "OLD"),
+            (AuditOp::Delete, "OLD"),
+        ] {
+            let op = op.as_str();
+            let values = names
+                .iter()
+                .map(|name| format!("{row}.{name}"))
+                .collect::<Vec<_>>()
+                .join(", ");
+            statements.push(format!(
+                "CREATE TRIGGER IF NOT EXISTS {} AFTER {} ON {table_name} \
+                 FOR EACH ROW INSERT INTO {history} ({}, _op, _at) \
+                 VALUES ({values}, '{op}', DATE_FORMAT(UTC_TIMESTAMP(6), '%Y-%m-%dT%H:%i:%s.%fZ'));",
+                MySqlDialect::quote_ident(&format!("{history_name}_{op}")),
+                op.to_ascii_uppercase(),
+                names.join(", "),
+            ));
+        }
+        statements
+    }
+}
+
+impl<T: HasCrudFields + Clone + Sized + 'static> Crud<MySql> for T {
+    type Connection<'a> = &'a mysql::Pool;
+
+    /// Create a table for `Self`.
+    fn create(connection: &mysql::Pool) -> Result<(), snafu::Whatever> {
+        let statement = MySql::create_table_sql::<Self>();
+        let mut conn = get_conn(connection)?;
+        conn.query_drop(statement)
+            .whatever_context("could not create")?;
+        if Self::audited() {
+            for statement in MySql::create_history_sql::<Self>() {
+                conn.query_drop(statement)
+                    .whatever_context("could not create history")?;
+            }
+        }
+        Ok(())
+    }
+
+    fn validate_schema(connection: &mysql::Pool) -> Result<(), snafu::Whatever> {
+        let table_name = Self::table_name();
+        let statement = "SELECT column_name, data_type FROM information_schema.columns \
+                         WHERE table_schema = DATABASE() AND table_name = ?;";
+        let columns: Vec<(String, String)> = get_conn(connection)?
+            .exec_map(statement, (table_name,), |(name, ty): (String, String)| {
+                (name, ty.to_ascii_uppercase())
+            })
+            .whatever_context("validate schema")?;
+        snafu::ensure_whatever!(!columns.is_empty(), "table `{table_name}` does not exist");
+
+        let fields = Self::crud_fields();
+        let mut problems = vec![];
+        for field in fields.iter() {
+            let expected = if field.primary_key {
+                field.ty.mysql_key_type()
+            } else {
+                field.ty.mysql_type()
+            };
+            // `data_type` doesn't include a length, eg. `VARCHAR`
+            let expected = expected.split('(').next().unwrap_or(expected);
+            match columns.iter().find(|(name, _)| name == field.name) {
+                None => problems.push(format!("missing column `{}`", field.name)),
+                Some((_, ty)) if ty != expected => problems.push(format!(
+                    "column `{}` has type `{ty}`, expected `{expected}`",
+                    field.name
+                )),
+                Some(_) => {}
+            }
+        }
+        for (name, _) in columns.iter() {
+            if !fields.iter().any(|field| field.name == name) {
+                problems.push(format!("unexpected column `{name}`"));
+            }
+        }
+        snafu::ensure_whatever!(
+            problems.is_empty(),
+            "table `{table_name}` does not match its fields: {}",
+            problems.join(", ")
+        );
+        Ok(())
+    }
+
+    fn sync_schema(connection: &mysql::Pool) -> Result<Vec<&'static str>, snafu::Whatever> {
+        let table_name = Self::table_name();
+        if !MySql::table_exists(connection, table_name)? {
+            <Self as Crud<MySql>>::create(connection)?;
+            return Ok(vec![]);
+        }
+        let statement = "SELECT column_name FROM information_schema.columns \
+                         WHERE table_schema = DATABASE() AND table_name = ?;";
+        let columns: Vec<String> = get_conn(connection)?
+            .exec(statement, (table_name,))
+            .whatever_context("sync schema columns")?;
+        let mut added = vec![];
+        for field in columns_to_add::<Self>(&columns)? {
+            let mut statement = format!(
+                "ALTER TABLE {} ADD COLUMN {}",
+                MySqlDialect::quote_ident(table_name),
+                field.mysql_create_field()
+            );
+            if let Some(foreign_key) = field.foreign_key_clause::<MySqlDialect>() {
+                statement.push_str(&format!(", ADD {foreign_key}"));
+            }
+            get_conn(connection)?
+                .query_drop(&statement)
+                .whatever_context(format!("sync schema: {statement}"))?;
+            added.push(field.name);
+        }
+        Ok(added)
+    }
+
+    fn healthcheck(connection: &mysql::Pool) -> Result<(), snafu::Whatever> {
+        get_conn(connection)?
+            .query_drop("SELECT 1;")
+            .whatever_context("healthcheck query")?;
+        let table_name = Self::table_name();
+        snafu::ensure_whatever!(
+            MySql::table_exists(connection, table_name)?,
+            "table `{table_name}` does not exist"
+        );
+        Ok(())
+    }
+
+    fn insert(&self, connection: &mysql::Pool) -> Result<(), snafu::Whatever> {
+        let table_name = Self::table_name();
+        let fields = self.as_crud_fields();
+        ensure_all_finite::<Self>("MySQL", &fields)?;
+        MySql::insert_fields(connection, table_name, &fields)?;
+        Ok(())
+    }
+
+    /// A taken primary key turns the insert into an update that changes
+    /// nothing, which MySQL reports as no affected rows. Unlike `INSERT
+    /// IGNORE` this doesn't hide other errors.
+    fn insert_or_ignore(&self, connection: &mysql::Pool) -> Result<bool, snafu::Whatever> {
+        let table_name = Self::table_name();
+        let primary_key = Self::primary_key_name();
+        let fields = self.as_crud_fields();
+        ensure_all_finite::<Self>("MySQL", &fields)?;
+        let primary_key = MySqlDialect::quote_ident(primary_key);
+        let statement = insert_sql(
+            table_name,
+            &fields,
+            &format!(" ON DUPLICATE KEY UPDATE {primary_key} = {primary_key}"),
+        );
+        let params = fields
+            .into_iter()
+            .map(|(_, value)| mysql::Value::from(value))
+            .collect::<Vec<_>>();
+        let mut conn = get_conn(connection)?;
+        conn.exec_drop(&statement, params)
+            .whatever_context(format!("insert or ignore: {statement}"))?;
+        Ok(conn.affected_rows() > 0)
+    }
+
+    /// MySQL has no `RETURNING`, so the row is read back by its primary key
+    /// after the insert. Columns whose value is `Value::None` are left out of
+    /// the insert, and a missing primary key is taken from `LAST_INSERT_ID()`.
+    fn insert_returning(&self, connection: &mysql::Pool) -> Result<Self, snafu::Whatever> {
+        let table_name = Self::table_name();
+        let fields = self
+            .as_crud_fields()
+            .into_iter()
+            .filter(|(_, value)| *value != Value::None)
+            .collect::<FieldMap>();
+        ensure_all_finite::<Self>("MySQL", &fields)?;
+        let mut conn = get_conn(connection)?;
+        if fields.is_empty() {
+            conn.exec_drop(insert_sql(table_name, &fields, ""), ())
+                .whatever_context("insert returning")?;
+        } else {
+            insert_fields_with(&mut conn, table_name, &fields)?;
+        }
+        let key = match self.primary_key_val() {
+            Value::None => {
+                let id = i64::try_from(conn.last_insert_id())
+                    .whatever_context("last insert id too large")?;
+                Value::Integer(id)
+            }
+            key => key,
+        };
+        let primary_key = Self::primary_key_name();
+        let [table, primary_key] = [table_name, primary_key].map(MySqlDialect::quote_ident);
+        let statement = format!("SELECT * FROM {table} WHERE {primary_key} = ?;");
+        select(connection, statement, vec![mysql::Value::from(key)])?
+            .into_iter()
+            .next()
+            .whatever_context("inserted row was not found")
+    }
+
+    /// All rows are inserted within a transaction, so either all of them are
+    /// inserted or none are.
+    fn insert_many(connection: &mysql::Pool, items: &[Self]) -> Result<(), snafu::Whatever> {
+        let table_name = Self::table_name();
+        let rows = items
+            .iter()
+            .map(|item| {
+                let fields = item.as_crud_fields();
+                ensure_all_finite::<Self>("MySQL", &fields)?;
+                Ok(fields)
+            })
+            .collect::<Result<Vec<_>, snafu::Whatever>>()?;
+        let mut conn = get_conn(connection)?;
+        let mut tx = conn
+            .start_transaction(mysql::TxOpts::default())
+            .whatever_context("insert many transaction")?;
+        for fields in &rows {
+            // dropping `tx` on error rolls back
+            insert_fields_with(&mut tx, table_name, fields)?;
+        }
+        tx.commit().whatever_context("insert many commit")
+    }
+
+    fn read_all<'a>(
+        connection: Self::Connection<'a>,
+    ) -> Result<Box<dyn Iterator<Item = Result<Self, snafu::Whatever>> + 'a>, snafu::Whatever> {
+        let [table, primary_key] =
+            [Self::table_name(), Self::primary_key_name()].map(MySqlDialect::quote_ident);
+        let statement = format!("SELECT * FROM {table} ORDER BY {primary_key};");
+        let rows = select(connection, statement, vec![])?;
+        Ok(Box::new(rows.into_iter().map(Ok)))
+    }
+
+    fn read_all_parallel(
+        connection: &mysql::Pool,
+        threads: usize,
+    ) -> Result<Vec<Self>, snafu::Whatever>
+    where
+        Self: Send,
+    {
+        let table_name = Self::table_name();
+        let column_names = Self::field_names();
+        let rows = MySql::read_all_values(connection, table_name, column_names)?
+            .into_iter()
+            .collect::<Result<Vec<_>, _>>()?;
+        try_from_rows_parallel(&rows, threads)
+    }
+
+    fn count(connection: &mysql::Pool) -> Result<usize, snafu::Whatever> {
+        let table = MySqlDialect::quote_ident(Self::table_name());
+        let count: Option<i64> = get_conn(connection)?
+            .exec_first(format!("SELECT COUNT(*) FROM {table};"), ())
+            .whatever_context("count")?;
+        let count = count.whatever_context("count returned no row")?;
+        usize::try_from(count).whatever_context("count is negative")
+    }
+
+    /// Rows are ordered by primary key.
+    fn read_page_typed(
+        connection: &mysql::Pool,
+        limit: usize,
+        offset: usize,
+    ) -> Result<Page<Self>, snafu::Whatever> {
+        let total = <Self as Crud<MySql>>::count(connection)?;
+        let [table, primary_key] =
+            [Self::table_name(), Self::primary_key_name()].map(MySqlDialect::quote_ident);
+        let statement = format!("SELECT * FROM {table} ORDER BY {primary_key} LIMIT ? OFFSET ?;");
+        let mut params = vec![];
+        for n in [limit, offset] {
+            let n = u64::try_from(n).whatever_context("page bounds too large")?;
+            params.push(mysql::Value::UInt(n));
+        }
+        let items = select(connection, statement, params)?;
+        Ok(Page::new(items, total, limit, offset))
+    }
+
+    /// MySQL tables have no `rowid`, so "newest" means "largest primary key".
+    fn read_all_rev<'a>(
+        connection: Self::Connection<'a>,
+    ) -> Result<Box<dyn Iterator<Item = Result<Self, snafu::Whatever>> + 'a>, snafu::Whatever> {
+        let [table, primary_key] =
+            [Self::table_name(), Self::primary_key_name()].map(MySqlDialect::quote_ident);
+        let statement = format!("SELECT * FROM {table} ORDER BY {primary_key} DESC;");
+        let rows = select(connection, statement, vec![])?;
+        Ok(Box::new(rows.into_iter().map(Ok)))
+    }
+
+    fn read_where<'a>(
+        connection: &'a mysql::Pool,
+        key_name: &'a str,
+        comparison: &'a str,
+        key_value: impl IsCrudField,
+    ) -> Result<Box<dyn Iterator<Item = Result<Self, snafu::Whatever>> + 'a>, snafu::Whatever> {
+        let [table, key_name] = [Self::table_name(), key_name].map(MySqlDialect::quote_ident);
+        let statement = format!("SELECT * FROM {table} WHERE {key_name} {comparison} ?;");
+        let params = vec![mysql::Value::from(key_value.into_value())];
+        let rows = select(connection, statement, params)?;
+        Ok(Box::new(rows.into_iter().map(Ok)))
+    }
+
+    /// Assumes the default SQL mode, where `'\\'` is a single backslash.
+    fn read_like<'a>(
+        connection: Self::Connection<'a>,
+        column: &str,
+        pattern: LikePattern,
+    ) -> Result<Box<dyn Iterator<Item = Result<Self, snafu::Whatever>> + 'a>, snafu::Whatever> {
+        let table_name = Self::table_name();
+        snafu::ensure_whatever!(
+            Self::field_names().contains(&column),
+            "table `{table_name}` has no column `{column}`"
+        );
+        let [table, column] = [table_name, column].map(MySqlDialect::quote_ident);
+        let statement = format!("SELECT * FROM {table} WHERE {column} LIKE ? ESCAPE '\\\\';");
+        let params = vec![mysql::Value::from(pattern.as_str())];
+        let rows = select(connection, statement, params)?;
+        Ok(Box::new(rows.into_iter().map(Ok)))
+    }
+
+    fn read_column_cmp<'a>(
+        connection: Self::Connection<'a>,
+        left: &str,
+        comparison: &str,
+        right: &str,
+    ) -> Result<Box<dyn Iterator<Item = Result<Self, snafu::Whatever>> + 'a>, snafu::Whatever> {
+        let table_name = Self::table_name();
+        let fields = Self::crud_fields();
+        for column in [left, right] {
+            snafu::ensure_whatever!(
+                fields.iter().any(|field| field.name == column),
+                "table `{table_name}` has no column `{column}`"
+            );
+        }
+        let op = Comparison::from_sql(comparison)
+            .whatever_context(format!("unknown comparison `{comparison}`"))?
+            .as_sql();
+        let [table, left, right] = [table_name, left, right].map(MySqlDialect::quote_ident);
+        let statement = format!("SELECT * FROM {table} WHERE {left} {op} {right};");
+        let rows = select(connection, statement, vec![])?;
+        Ok(Box::new(rows.into_iter().map(Ok)))
+    }
+
+    /// Results are ordered by `column`.
+    fn aggregate(
+        connection: &mysql::Pool,
+        column: &str,
+    ) -> Result<Vec<(Value, i64)>, snafu::Whatever> {
+        let table_name = Self::table_name();
+        snafu::ensure_whatever!(
+            Self::crud_fields().iter().any(|field| field.name == column),
+            "table `{table_name}` has no column `{column}`"
+        );
+        let [table, column] = [table_name, column].map(MySqlDialect::quote_ident);
+        let statement =
+            format!("SELECT {column}, COUNT(*) FROM {table} GROUP BY {column} ORDER BY {column};");
+        let rows: Vec<mysql::Row> = get_conn(connection)?
+            .exec(statement, ())
+            .whatever_context("aggregate")?;
+        let mut groups = vec![];
+        for row in rows.iter() {
+            let value = row.as_ref(0).whatever_context("group value")?;
+            let binary = row.columns_ref()[0].character_set() == BINARY_CHARSET;
+            let count = row.get::<i64, _>(1).whatever_context("count")?;
+            groups.push((value_from_mysql(value, binary)?, count));
+        }
+        Ok(groups)
+    }
+
+    fn read_projected<'a>(
+        connection: &mysql::Pool,
+        columns: &[&'a str],
+    ) -> Result<Vec<FieldMap<'a>>, snafu::Whatever> {
+        let table_name = Self::table_name();
+        let primary_key = Self::primary_key_name();
+        snafu::ensure_whatever!(
+            !columns.is_empty(),
+            "no columns to read from `{table_name}`"
+        );
+        let column_names = Self::field_names();
+        for column in columns {
+            snafu::ensure_whatever!(
+                column_names.contains(column),
+                "table `{table_name}` has no column `{column}`"
+            );
+        }
+        let projection = columns
+            .iter()
+            .map(|column| MySqlDialect::quote_ident(column))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let [table, primary_key] = [table_name, primary_key].map(MySqlDialect::quote_ident);
+        let statement = format!("SELECT {projection} FROM {table} ORDER BY {primary_key};");
+        let rows: Vec<mysql::Row> = get_conn(connection)?
+            .exec(&statement, ())
+            .whatever_context(format!("read projected: {statement}"))?;
+        rows.iter().map(|row| row_to_fields(row, columns)).collect()
+    }
+
+    fn read<'a>(
+        connection: Self::Connection<'a>,
+        key: Self::PrimaryKey,
+    ) -> Result<Box<dyn Iterator<Item = Result<Self, snafu::Whatever>> + 'a>, snafu::Whatever> {
+        <Self as Crud<MySql>>::read_where(connection, Self::primary_key_name(), "=", key)
+    }
+
+    /// Values are read in chunks of [`MySql::MAX_PLACEHOLDERS`], one query
+    /// per chunk.
+    fn read_in<'a, Key: IsCrudField>(
+        connection: Self::Connection<'a>,
+        column: &str,
+        values: Vec<Key>,
+    ) -> Result<Box<dyn Iterator<Item = Result<Self, snafu::Whatever>> + 'a>, snafu::Whatever> {
+        let table_name = Self::table_name();
+        snafu::ensure_whatever!(
+            Self::field_names().contains(&column),
+            "table `{table_name}` has no column `{column}`"
+        );
+        let [table, column] = [table_name, column].map(MySqlDialect::quote_ident);
+        let mut rows = vec![];
+        for chunk in values.chunks(MySql::MAX_PLACEHOLDERS) {
+            let binds = (0..chunk.len())
+                .map(MySqlDialect::placeholder)
+                .collect::<Vec<_>>()
+                .join(", ");
+            let statement = format!("SELECT * FROM {table} WHERE {column} IN ({binds});");
+            let params = chunk
+                .iter()
+                .map(|value| mysql::Value::from(value.into_value()))
+                .collect();
+            rows.extend(select(connection, statement, params)?);
+        }
+        Ok(Box::new(rows.into_iter().map(Ok)))
+    }
+
+    fn read_by_keys(
+        connection: &mysql::Pool,
+        keys: Vec<Self::PrimaryKey>,
+    ) -> Result<Vec<Self>, snafu::Whatever> {
+        <Self as Crud<MySql>>::read_in(connection, Self::primary_key_name(), keys)?.collect()
+    }
+
+    fn update(&self, connection: &mysql::Pool) -> Result<(), snafu::Whatever> {
+        ensure_mutable::<Self>("update")?;
+        let table_name = Self::table_name();
+        let primary_key = Self::primary_key_name();
+        let mut fields = self.as_crud_fields();
+        ensure_all_finite::<Self>("MySQL", &fields)?;
+        let key_value = fields
+            .shift_remove(primary_key)
+            .whatever_context("no key value")?;
+        let (columns, mut params): (Vec<_>, Vec<_>) = fields
+            .into_iter()
+            .map(|(name, value)| (name, mysql::Value::from(value)))
+            .unzip();
+        params.push(mysql::Value::from(key_value));
+        let statement = update_sql(table_name, &columns, primary_key);
+        get_conn(connection)?
+            .exec_drop(statement, params)
+            .whatever_context("update")
+    }
+
+    fn update_diff_count(
+        &self,
+        old: &Self,
+        connection: &mysql::Pool,
+    ) -> Result<usize, snafu::Whatever> {
+        ensure_mutable::<Self>("update")?;
+        let primary_key = Self::primary_key_name();
+        let key_value = self.primary_key_val();
+        snafu::ensure_whatever!(
+            key_value == old.primary_key_val(),
+            "can't diff rows with different primary keys"
+        );
+        let old_fields = old.as_crud_fields();
+        let changed = self
+            .as_crud_fields()
+            .into_iter()
+            .filter(|(name, value)| *name != primary_key && old_fields.get(name) != Some(value))
+            .collect::<Vec<_>>();
+        if changed.is_empty() {
+            return Ok(0);
+        }
+        for (name, value) in &changed {
+            ensure_finite::<Self>("MySQL", name, value)?;
+        }
+        let columns = changed.iter().map(|(name, _)| *name).collect::<Vec<_>>();
+        let mut params = changed
+            .iter()
+            .map(|(_, value)| mysql::Value::from(value.clone()))
+            .collect::<Vec<_>>();
+        params.push(mysql::Value::from(key_value));
+        let statement = update_sql(Self::table_name(), &columns, primary_key);
+        get_conn(connection)?
+            .exec_drop(statement, params)
+            .whatever_context("update diff")?;
+        Ok(changed.len())
+    }
+
+    fn delete(self, connection: &mysql::Pool) -> Result<bool, snafu::Whatever> {
+        ensure_mutable::<Self>("delete from")?;
+        let table_name = Self::table_name();
+        let (key_name, key_value) = self.primary_key();
+        let clause = format!(" WHERE {} = ?", MySqlDialect::quote_ident(key_name));
+        let statement = delete_sql(table_name, &clause);
+        let mut conn = get_conn(connection)?;
+        conn.exec_drop(statement, vec![mysql::Value::from(key_value)])
+            .whatever_context("delete")?;
+        Ok(conn.affected_rows() > 0)
+    }
+
+    fn update_where(
+        connection: &mysql::Pool,
+        set_col: &str,
+        set_val: impl IsCrudField,
+        key_name: &str,
+        comparison: Comparison,
+        key_value: impl IsCrudField,
+    ) -> Result<u64, snafu::Whatever> {
+        ensure_mutable::<Self>("update")?;
+        let table_name = Self::table_name();
+        let column_names = Self::field_names();
+        for column in [set_col, key_name] {
+            snafu::ensure_whatever!(
+                column_names.contains(&column),
+                "table `{table_name}` has no column `{column}`"
+            );
+        }
+        let set_val = set_val.into_value();
+        ensure_finite::<Self>("MySQL", set_col, &set_val)?;
+        let [table, set_col, key_name] =
+            [table_name, set_col, key_name].map(MySqlDialect::quote_ident);
+        let op = comparison.as_sql();
+        let statement = format!("UPDATE {table} SET {set_col} = ? WHERE {key_name} {op} ?;");
+        let params = vec![
+            mysql::Value::from(set_val),
+            mysql::Value::from(key_value.into_value()),
+        ];
+        let mut conn = get_conn(connection)?;
+        conn.exec_drop(statement, params)
+            .whatever_context("update where")?;
+        Ok(conn.affected_rows())
+    }
+
+    fn delete_where(
+        connection: &mysql::Pool,
+        query: &Query<Self>,
+    ) -> Result<usize, snafu::Whatever> {
+        ensure_mutable::<Self>("delete from")?;
+        let table_name = Self::table_name();
+        snafu::ensure_whatever!(
+            !query.conditions().is_empty(),
+            "refusing to delete from `{table_name}` without conditions, use `clear` to delete every row"
+        );
+        let (clause, params) = where_clause::<Self>(query.conditions())?;
+        let mut conn = get_conn(connection)?;
+        conn.exec_drop(delete_sql(table_name, &clause), params)
+            .whatever_context("delete all where")?;
+        usize::try_from(conn.affected_rows()).whatever_context("too many rows deleted")
+    }
+
+    fn clear(connection: &mysql::Pool) -> Result<usize, snafu::Whatever> {
+        ensure_mutable::<Self>("clear")?;
+        let table_name = Self::table_name();
+        let mut conn = get_conn(connection)?;
+        conn.exec_drop(delete_sql(table_name, ""), ())
+            .whatever_context(format!("could not clear `{table_name}`"))?;
+        usize::try_from(conn.affected_rows()).whatever_context("too many rows deleted")
+    }
+
+    fn read_history(connection: &mysql::Pool) -> Result<Vec<History<Self>>, snafu::Whatever> {
+        let table_name = Self::table_name();
+        snafu::ensure_whatever!(Self::audited(), "table `{table_name}` is not audited");
+        let history = MySqlDialect::quote_ident(&history_table_name::<Self>());
+        let column_names = Self::field_names();
+        let rows: Vec<mysql::Row> = get_conn(connection)?
+            .exec(format!("SELECT * FROM {history} ORDER BY _seq;"), ())
+            .whatever_context("read history")?;
+        rows.iter()
+            .map(|row| {
+                let audit = row_to_fields(row, &["_op", "_at"])?;
+                let op = audit["_op"].as_string().whatever_context("_op")?;
+                let op = AuditOp::from_column(op).whatever_context(format!("unknown op `{op}`"))?;
+                let at = audit["_at"].as_string().whatever_context("_at")?.clone();
+                let row = Self::try_from_crud_fields(&row_to_fields(row, &column_names)?)?;
+                Ok(History { op, at, row })
+            })
+            .collect()
+    }
+}
+
+/// The ` WHERE ...` clause matching all `conditions` on `T`'s table, and the
+/// values to bind to its `?` placeholders, in order.
+///
+/// The clause is empty when there are no conditions.
+fn where_clause<T: HasCrudFields>(
+    conditions: &[Condition],
+) -> Result<(String, Vec<mysql::Value>), snafu::Whatever> {
+    let table_name = T::table_name();
+    let fields = T::crud_fields();
+    let mut clauses = vec![];
+    let mut params = vec![];
+    for condition in conditions.iter() {
+        let column = condition.column.as_str();
+        snafu::ensure_whatever!(
+            fields.iter().any(|field| field.name == column),
+            "table `{table_name}` has no column `{column}`"
+        );
+        let column = MySqlDialect::quote_ident(column);
+        let param = MySqlDialect::placeholder(clauses.len());
+        let op = condition.comparison.as_sql();
+        clauses.push(format!("{column} {op} {param}"));
+        params.push(mysql::Value::from(condition.value.clone()));
+    }
+    if clauses.is_empty() {
+        Ok((String::new(), params))
+    } else {
+        Ok((format!(" WHERE {}", clauses.join(" AND ")), params))
+    }
+}
@@ -0,0 +1,1040 @@
+//! Rusqlite impl.
+//!
+//! This is also the sqlite backend, see [`Sqlite`](crate::Sqlite), so only one
+//! SQLite C library is linked in.
+//!
+//! Statements are prepared with `prepare_cached`, so repeated operations reuse
+//! the statements compiled by earlier ones. The cache belongs to the
+//! `rusqlite::Connection`, which is `Send` but not `Sync`, so it's never
+//! shared between threads.
+use snafu::{OptionExt, ResultExt};
+
+use crate::{
+    columns_to_add, ensure_mutable, history_table_name,
+    sqlite_common::{self, where_clause},
+    try_from_rows_parallel, AuditOp, Comparison, Crud, CrudField, FieldMap, HasCrudFields, History,
+    IsCrudField, LikePattern, MigrateEntireTable, OrderBy, Page, Query, Select, SqlDialect,
+    SqliteDialect, Value, ValueRef,
+};
+
+impl From<Value> for rusqlite::types::Value {
+    fn from(value: Value) -> Self {
+        match value {
+            Value::Integer(i) => rusqlite::types::Value::Integer(i),
+            Value::Float(i) => rusqlite::types::Value::Real(i),
+            Value::String(i) => rusqlite::types::Value::Text(i),
+            Value::Bytes(i) => rusqlite::types::Value::Blob(i),
+            Value::Boolean(i) => rusqlite::types::Value::Integer(i64::from(i)),
+            Value::None => rusqlite::types::Value::Null,
+        }
+    }
+}
+
+impl From<rusqlite::types::Value> for Value {
+    fn from(value: rusqlite::types::Value) -> Self {
+        match value {
+            rusqlite::types::Value::Integer(i) => Value::Integer(i),
+            rusqlite::types::Value::Real(i) => Value::Float(i),
+            rusqlite::types::Value::Text(i) => Value::String(i),
+            rusqlite::types::Value::Blob(i) => Value::Bytes(i),
+            rusqlite::types::Value::Null => Value::None,
+        }
+    }
+}
+
+impl<'a> From<rusqlite::types::ValueRef<'a>> for ValueRef<'a> {
+    fn from(value: rusqlite::types::ValueRef<'a>) -> Self {
+        match value {
+            rusqlite::types::ValueRef::Integer(i) => ValueRef::Integer(i),
+            rusqlite::types::ValueRef::Real(i) => ValueRef::Float(i),
+            // text that isn't UTF-8 is read as bytes rather than panicking
+            rusqlite::types::ValueRef::Text(i) => match std::str::from_utf8(i) {
+                Ok(s) => ValueRef::String(s),
+                Err(_) => ValueRef::Bytes(i),
+            },
+            rusqlite::types::ValueRef::Blob(i) => ValueRef::Bytes(i),
+            rusqlite::types::ValueRef::Null => ValueRef::None,
+        }
+    }
+}
+
+impl rusqlite::types::ToSql for ValueRef<'_> {
+    fn to_sql(&self) -> rusqlite::Result<rusqlite::types::ToSqlOutput<'_>> {
+        let value = match *self {
+            ValueRef::Integer(i) => rusqlite::types::ValueRef::Integer(i),
+            ValueRef::Float(f) => rusqlite::types::ValueRef::Real(f),
+            ValueRef::String(s) => rusqlite::types::ValueRef::Text(s.as_bytes()),
+            ValueRef::Bytes(b) => rusqlite::types::ValueRef::Blob(b),
+            ValueRef::Boolean(b) => rusqlite::types::ValueRef::Integer(i64::from(b)),
+            ValueRef::None => rusqlite::types::ValueRef::Null,
+        };
+        Ok(rusqlite::types::ToSqlOutput::Borrowed(value))
+    }
+}
+
+/// Collect the named columns of a row.
+fn row_to_fields<'a>(
+    row: &rusqlite::Row,
+    column_names: &[&'a str],
+) -> Result<FieldMap<'a>, snafu::Whatever> {
+    column_names
+        .iter()
+        .map(|name| {
+            let value = row
+                .get::<_, rusqlite::types::Value>(*name)
+                .whatever_context(format!("row has no column `{name}`"))?;
+            Ok((*name, Value::from(value)))
+        })
+        .collect()
+}
+
+/// Bind `binds` by name, eg. `(":p0", value)`.
+fn named_params(
+    binds: &[(String, rusqlite::types::Value)],
+) -> Vec<(&str, &dyn rusqlite::types::ToSql)> {
+    binds
+        .iter()
+        .map(|(name, value)| (name.as_str(), value as &dyn rusqlite::types::ToSql))
+        .collect()
+}
+
+/// Convert the values of `binds` for rusqlite.
+fn to_rusqlite_binds(binds: Vec<(String, Value)>) -> Vec<(String, rusqlite::types::Value)> {
+    binds
+        .into_iter()
+        .map(|(name, value)| (name, rusqlite::types::Value::from(value)))
+        .collect()
+}
+
+/// Prepare `statement`, with `operation` naming what it's for in errors.
+///
+/// A missing table gets an error saying so, as `Crud::create` was likely not
+/// called, instead of hiding sqlite's message in the error's source.
+fn prepare<'c>(
+    connection: &'c rusqlite::Connection,
+    statement: &str,
+    operation: &str,
+) -> Result<rusqlite::CachedStatement<'c>, snafu::Whatever> {
+    connection
+        .prepare_cached(statement)
+        .with_whatever_context(|e| {
+            let missing_table = match e {
+                rusqlite::Error::SqliteFailure(_, Some(message)) => {
+                    message.strip_prefix("no such table: ")
+                }
+                _ => None,
+            };
+            match missing_table {
+                Some(table_name) => {
+                    format!("table `{table_name}` does not exist; call create() first")
+                }
+                None => format!("{operation} prepare: {statement}"),
+            }
+        })
+}
+
+/// Run a `SELECT` statement and collect the named columns of every row.
+fn select_fields<'a>(
+    connection: &rusqlite::Connection,
+    statement: &str,
+    binds: Vec<(String, Value)>,
+    column_names: &[&'a str],
+) -> Result<Vec<FieldMap<'a>>, snafu::Whatever> {
+    let binds = to_rusqlite_binds(binds);
+    let mut query = prepare(connection, statement, "select")?;
+    let mut rows = query
+        .query(named_params(&binds).as_slice())
+        .whatever_context(format!("select: {statement}"))?;
+    let mut fields = vec![];
+    while let Some(row) = rows
+        .next()
+        .whatever_context(format!("row of: {statement}"))?
+    {
+        fields.push(row_to_fields(row, column_names)?);
+    }
+    Ok(fields)
+}
+
+/// Build a `T` straight from a row, borrowing each cell until its field
+/// copies it.
+fn row_to_typed<T: HasCrudFields>(row: &rusqlite::Row) -> Result<T, snafu::Whatever> {
+    let mut missing = None;
+    let item = T::try_from_row_ref(|name| match row.get_ref(name) {
+        Ok(value) => ValueRef::from(value),
+        Err(_) => {
+            missing.get_or_insert(name);
+            ValueRef::None
+        }
+    });
+    if let Some(name) = missing {
+        snafu::whatever!("row has no column `{name}`");
+    }
+    item
+}
+
+/// Run a `SELECT` statement and decode every row into a `T`.
+///
+/// A row that can't be decoded is an error in its place, the rows after it
+/// are still read.
+///
+/// Cells are borrowed from sqlite until the fields of `T` copy them.
+fn select<T: HasCrudFields>(
+    connection: &rusqlite::Connection,
+    statement: &str,
+    binds: Vec<(String, Value)>,
+) -> Result<Vec<Result<T, snafu::Whatever>>, snafu::Whatever> {
+    let binds = to_rusqlite_binds(binds);
+    let mut query = prepare(connection, statement, "select")?;
+    let mut rows = query
+        .query(named_params(&binds).as_slice())
+        .whatever_context(format!("select: {statement}"))?;
+    let mut items = vec![];
+    while let Some(row) = rows
+        .next()
+        .whatever_context(format!("row of: {statement}"))?
+    {
+        items.push(row_to_typed(row));
+    }
+    Ok(items)
+}
+
+/// Whether `error` is sqlite's `SQLITE_BUSY` or `SQLITE_LOCKED`.
+fn is_busy(error: &(dyn std::error::Error + 'static)) -> bool {
+    error
+        .downcast_ref::<rusqlite::Error>()
+        .and_then(rusqlite::Error::sqlite_error_code)
+        .is_some_and(|code| {
+            matches!(
+                code,
+                rusqlite::ErrorCode::DatabaseBusy | rusqlite::ErrorCode::DatabaseLocked
+            )
+        })
+}
+
+/// Run a statement, returning the number of rows it changed.
+fn execute(
+    connection: &rusqlite::Connection,
+    statement: &str,
+    binds: Vec<(String, Value)>,
+) -> Result<usize, snafu::Whatever> {
+    let binds = to_rusqlite_binds(binds);
+    prepare(connection, statement, "execute")?
+        .execute(named_params(&binds).as_slice())
+        .whatever_context(format!("execute: {statement}"))
+}
+
+/// Run `f` inside the savepoint `name`, rolling back its writes if it errors.
+///
+/// Savepoints nest, so this also works inside a [`Transaction`].
+fn savepoint<R>(
+    connection: &rusqlite::Connection,
+    name: &str,
+    f: impl FnOnce() -> Result<R, snafu::Whatever>,
+) -> Result<R, snafu::Whatever> {
+    connection
+        .execute_batch(&format!("SAVEPOINT {name};"))
+        .whatever_context(format!("{name} savepoint"))?;
+    let result = f();
+    if result.is_err() {
+        connection
+            .execute_batch(&format!("ROLLBACK TO {name};"))
+            .whatever_context(format!("{name} rollback"))?;
+    }
+    connection
+        .execute_batch(&format!("RELEASE {name};"))
+        .whatever_context(format!("{name} release"))?;
+    result
+}
+
+impl MigrateEntireTable for Rusqlite {
+    type Connection<'a> = &'a rusqlite::Connection;
+
+    fn read_all_values<'a>(
+        connection: &'a rusqlite::Connection,
+        table_name: &'a str,
+        column_names: Vec<&'a str>,
+    ) -> Result<Vec<Result<FieldMap<'a>, snafu::Whatever>>, snafu::Whatever> {
+        // without a typed primary key, order by `rowid` for a stable order
+        let table = SqliteDialect::quote_ident(table_name);
+        let statement = format!("SELECT * FROM {table} ORDER BY rowid;");
+        let rows = select_fields(connection, &statement, vec![], &column_names)?;
+        Ok(rows.into_iter().map(Ok).collect())
+    }
+
+    fn insert_fields(
+        connection: &rusqlite::Connection,
+        table_name: &str,
+        fields: &FieldMap<'_>,
+    ) -> Result<(), snafu::Whatever> {
+        let statement = sqlite_common::insert_sql(table_name, fields);
+        // bound as borrows, so the values aren't cloned
+        let names = (0..fields.len())
+            .map(SqliteDialect::placeholder)
+            .collect::<Vec<_>>();
+        let values = fields.values().map(Value::as_value_ref).collect::<Vec<_>>();
+        let params = names
+            .iter()
+            .zip(values.iter())
+            .map(|(name, value)| (name.as_str(), value as &dyn rusqlite::types::ToSql))
+            .collect::<Vec<_>>();
+        prepare(connection, &statement, "insert")?
+            .execute(params.as_slice())
+            .whatever_context(format!("insert: {statement}"))?;
+        Ok(())
+    }
+
+    fn delete_all(
+        connection: &rusqlite::Connection,
+        table_name: &str,
+    ) -> Result<(), snafu::Whatever> {
+        execute(
+            connection,
+            &sqlite_common::delete_sql(table_name, ""),
+            vec![],
+        )?;
+        Ok(())
+    }
+
+    fn create_table(
+        connection: &rusqlite::Connection,
+        table_name: &str,
+        fields: &[CrudField],
+    ) -> Result<(), snafu::Whatever> {
+        let statement = sqlite_common::create_table_sql_from(table_name, fields);
+        connection
+            .execute_batch(&statement)
+            .whatever_context(format!("could not create `{table_name}`: {statement}"))
+    }
+
+    fn table_exists(
+        connection: &rusqlite::Connection,
+        table_name: &str,
+    ) -> Result<bool, snafu::Whatever> {
+        let statement = "SELECT name FROM sqlite_master WHERE type = 'table' AND name = :name;";
+        let binds = vec![(":name".to_string(), Value::from(table_name.to_string()))];
+        let rows = select_fields(connection, statement, binds, &["name"])?;
+        Ok(!rows.is_empty())
+    }
+}
+
+pub struct Rusqlite;
+
+impl Rusqlite {
+    /// The most variables one statement may bind in older versions of sqlite.
+    pub const MAX_VARIABLES: usize = 999;
+
+    /// The statement [`Crud::create`] runs to create `T`'s table.
+    pub fn create_table_sql<T: HasCrudFields>() -> String {
+        sqlite_common::create_table_sql::<T>()
+    }
+
+    /// The statement [`Crud::create_temp`] runs to create `T`'s temporary
+    /// table.
+    pub fn create_temp_table_sql<T: HasCrudFields>() -> Result<String, snafu::Whatever> {
+        sqlite_common::create_temp_table_sql::<T>()
+    }
+
+    /// The statements [`Crud::create`] runs to create the history table of
+    /// an `#[audited]` `T`, and the triggers that fill it.
+    pub fn create_history_sql<T: HasCrudFields>() -> Vec<String> {
+        sqlite_common::create_history_sql::<T>()
+    }
+}
+
+/// Run `f` on a fresh in-memory database that has `T`'s table.
+///
+/// Handy in tests and scripts. The database is gone once `f` returns.
+pub fn scoped<T: HasCrudFields + Clone + 'static, R>(
+    f: impl FnOnce(&rusqlite::Connection) -> R,
+) -> Result<R, snafu::Whatever> {
+    let connection = rusqlite::Connection::open_in_memory()
+        .whatever_context("could not open in-memory database")?;
+    <T as Crud<Rusqlite>>::create(&connection)?;
+    Ok(f(&connection))
+}
+
+/// A transaction on a sqlite connection.
+///
+/// `BEGIN`s when created and `ROLLBACK`s when dropped unless
+/// [`Transaction::commit`] was called. Pass [`Transaction::connection`] to
+/// `Crud` methods to run them inside the transaction.
+///
+/// Unlike `rusqlite::Transaction` this only needs a shared borrow of the
+/// connection, which is what `Crud` methods take.
+pub struct Transaction<'a> {
+    connection: &'a rusqlite::Connection,
+    finished: bool,
+}
+
+impl<'a> Transaction<'a> {
+    pub fn new(connection: &'a rusqlite::Connection) -> Result<Self, snafu::Whatever> {
+        connection
+            .execute_batch("BEGIN;")
+            .whatever_context("could not begin transaction")?;
+        Ok(Transaction {
+            connection,
+            finished: false,
+        })
+    }
+
+    /// The connection the transaction is on.
+    pub fn connection(&self) -> &'a rusqlite::Connection {
+        self.connection
+    }
+
+    pub fn commit(mut self) -> Result<(), snafu::Whatever> {
+        // if this fails the transaction is still open, and is rolled back on
+        // drop
+        self.connection
+            .execute_batch("COMMIT;")
+            .whatever_context("could not commit transaction")?;
+        self.finished = true;
+        Ok(())
+    }
+
+    pub fn rollback(mut self) -> Result<(), snafu::Whatever> {
+        self.finished = true;
+        self.connection
+            .execute_batch("ROLLBACK;")
+            .whatever_context("could not roll back transaction")
+    }
+}
+
+impl Drop for Transaction<'_> {
+    fn drop(&mut self) {
+        if !self.finished {
+            if let Err(e) = self.connection.execute_batch("ROLLBACK;") {
+                log::error!("could not roll back transaction: {e}");
+            }
+        }
+    }
+}
+
+impl<T: HasCrudFields + Clone + Sized + 'static> Crud<Rusqlite> for T {
+    type Connection<'a> = &'a rusqlite::Connection;
+
+    /// Create a table for `Self`.
+    fn create(connection: &rusqlite::Connection) -> Result<(), snafu::Whatever> {
+        if Self::crud_fields().iter().any(|f| f.references.is_some()) {
+            connection
+                .execute_batch("PRAGMA foreign_keys = ON;")
+                .whatever_context("could not enable foreign keys")?;
+        }
+        let table_name = Self::table_name();
+        let statement = Rusqlite::create_table_sql::<Self>();
+        connection
+            .execute_batch(&statement)
+            .whatever_context(format!("could not create `{table_name}`: {statement}"))?;
+        if Self::audited() {
+            for statement in Rusqlite::create_history_sql::<Self>() {
+                connection
+                    .execute_batch(&statement)
+                    .whatever_context(format!(
+                        "could not create history of `{table_name}`: {statement}"
+                    ))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn create_temp(connection: &rusqlite::Connection) -> Result<(), snafu::Whatever> {
+        let statement = Rusqlite::create_temp_table_sql::<Self>()?;
+        connection
+            .execute_batch(&statement)
+            .whatever_context("could not create temporary table")
+    }
+
+    fn validate_schema(connection: &rusqlite::Connection) -> Result<(), snafu::Whatever> {
+        let table_name = Self::table_name();
+        let statement = format!(
+            "PRAGMA table_info({});",
+            SqliteDialect::quote_ident(table_name)
+        );
+        let columns = select_fields(connection, &statement, vec![], &["name", "type"])?
+            .into_iter()
+            .map(|row| {
+                let name = row["name"].as_string().whatever_context("column name")?;
+                let ty = row["type"].as_string().whatever_context("column type")?;
+                Ok((name.clone(), ty.to_ascii_uppercase()))
+            })
+            .collect::<Result<Vec<_>, snafu::Whatever>>()?;
+        snafu::ensure_whatever!(!columns.is_empty(), "table `{table_name}` does not exist");
+
+        let fields = Self::crud_fields();
+        let mut problems = vec![];
+        for field in fields.iter() {
+            let expected = field.ty.sqlite_type();
+            match columns.iter().find(|(name, _)| name == field.name) {
+                None => problems.push(format!("missing column `{}`", field.name)),
+                Some((_, ty)) if ty != expected => problems.push(format!(
+                    "column `{}` has type `{ty}`, expected `{expected}`",
+                    field.name
+                )),
+                Some(_) => {}
+            }
+        }
+        for (name, _) in columns.iter() {
+            if !fields.iter().any(|field| field.name == name) {
+                problems.push(format!("unexpected column `{name}`"));
+            }
+        }
+        snafu::ensure_whatever!(
+            problems.is_empty(),
+            "table `{table_name}` does not match its fields: {}",
+            problems.join(", ")
+        );
+        Ok(())
+    }
+
+    fn sync_schema(
+        connection: &rusqlite::Connection,
+    ) -> Result<Vec<&'static str>, snafu::Whatever> {
+        let table_name = Self::table_name();
+        if !Rusqlite::table_exists(connection, table_name)? {
+            <Self as Crud<Rusqlite>>::create(connection)?;
+            return Ok(vec![]);
+        }
+        let statement = format!(
+            "PRAGMA table_info({});",
+            SqliteDialect::quote_ident(table_name)
+        );
+        let columns = select_fields(connection, &statement, vec![], &["name"])?
+            .into_iter()
+            .map(|row| {
+                let name = row["name"].as_string().whatever_context("column name")?;
+                Ok(name.clone())
+            })
+            .collect::<Result<Vec<_>, snafu::Whatever>>()?;
+        let mut added = vec![];
+        for field in columns_to_add::<Self>(&columns)? {
+            let statement = sqlite_common::add_column_sql(table_name, &field);
+            execute(connection, &statement, vec![])?;
+            added.push(field.name);
+        }
+        Ok(added)
+    }
+
+    fn healthcheck(connection: &rusqlite::Connection) -> Result<(), snafu::Whatever> {
+        connection
+            .execute_batch("SELECT 1;")
+            .whatever_context("healthcheck query")?;
+        let table_name = Self::table_name();
+        snafu::ensure_whatever!(
+            Rusqlite::table_exists(connection, table_name)?,
+            "table `{table_name}` does not exist"
+        );
+        Ok(())
+    }
+
+    /// Retried while the database is busy, see [`BusyRetry`](crate::BusyRetry).
+    fn insert(&self, connection: &rusqlite::Connection) -> Result<(), snafu::Whatever> {
+        let table_name = Self::table_name();
+        let fields = self.as_crud_fields();
+        sqlite_common::retry_busy("insert into", table_name, is_busy, || {
+            Rusqlite::insert_fields(connection, table_name, &fields)
+        })
+    }
+
+    /// Uses `INSERT OR IGNORE`, which also skips rows that break a `NOT NULL`
+    /// or `CHECK` constraint.
+    fn insert_or_ignore(&self, connection: &rusqlite::Connection) -> Result<bool, snafu::Whatever> {
+        let fields = self.as_crud_fields();
+        let statement =
+            sqlite_common::insert_sql_with("INSERT OR IGNORE", Self::table_name(), &fields, "");
+        let binds = sqlite_common::insert_binds(fields);
+        Ok(execute(connection, &statement, binds)? > 0)
+    }
+
+    /// Columns whose value is `Value::None` are left out of the insert so
+    /// sqlite can fill them in, eg. with a column default or a generated
+    /// `INTEGER PRIMARY KEY`.
+    fn insert_returning(&self, connection: &rusqlite::Connection) -> Result<Self, snafu::Whatever> {
+        let fields = self
+            .as_crud_fields()
+            .into_iter()
+            .filter(|(_, value)| *value != Value::None)
+            .collect::<FieldMap>();
+        let statement =
+            sqlite_common::insert_sql_with("INSERT", Self::table_name(), &fields, " RETURNING *");
+        let binds = sqlite_common::insert_binds(fields);
+        select(connection, &statement, binds)?
+            .into_iter()
+            .next()
+            .whatever_context("insert returned no row")?
+    }
+
+    /// All rows are inserted within a savepoint, so either all of them are
+    /// inserted or none are.
+    fn insert_many(
+        connection: &rusqlite::Connection,
+        items: &[Self],
+    ) -> Result<(), snafu::Whatever> {
+        savepoint(connection, "insert_many", || {
+            items
+                .iter()
+                .try_for_each(|item| <Self as Crud<Rusqlite>>::insert(item, connection))
+        })
+    }
+
+    fn read_all<'a>(
+        connection: Self::Connection<'a>,
+    ) -> Result<Box<dyn Iterator<Item = Result<Self, snafu::Whatever>> + 'a>, snafu::Whatever> {
+        let [table, primary_key] =
+            [Self::table_name(), Self::primary_key_name()].map(SqliteDialect::quote_ident);
+        let statement = format!("SELECT * FROM {table} ORDER BY {primary_key};");
+        let items = select(connection, &statement, vec![])?;
+        Ok(Box::new(items.into_iter()))
+    }
+
+    /// Rows are read on the calling thread, then split into `threads` chunks
+    /// that are deserialized on scoped threads.
+    fn read_all_parallel(
+        connection: &rusqlite::Connection,
+        threads: usize,
+    ) -> Result<Vec<Self>, snafu::Whatever>
+    where
+        Self: Send,
+    {
+        let table = SqliteDialect::quote_ident(Self::table_name());
+        let column_names = Self::field_names();
+        let statement = format!("SELECT * FROM {table};");
+        let rows = select_fields(connection, &statement, vec![], &column_names)?;
+        try_from_rows_parallel(&rows, threads)
+    }
+
+    fn count(connection: &rusqlite::Connection) -> Result<usize, snafu::Whatever> {
+        let table = SqliteDialect::quote_ident(Self::table_name());
+        let statement = format!("SELECT COUNT(*) FROM {table};");
+        let count = connection
+            .query_row(&statement, [], |row| row.get::<_, i64>(0))
+            .whatever_context(format!("count: {statement}"))?;
+        usize::try_from(count).whatever_context("count is negative")
+    }
+
+    /// Rows are ordered by `rowid`.
+    fn read_page_typed(
+        connection: &rusqlite::Connection,
+        limit: usize,
+        offset: usize,
+    ) -> Result<Page<Self>, snafu::Whatever> {
+        let total = <Self as Crud<Rusqlite>>::count(connection)?;
+        let table = SqliteDialect::quote_ident(Self::table_name());
+        let statement =
+            format!("SELECT * FROM {table} ORDER BY rowid LIMIT :limit OFFSET :offset;");
+        let mut binds = vec![];
+        for (param, n) in [(":limit", limit), (":offset", offset)] {
+            let n = i64::try_from(n).whatever_context("page bounds too large")?;
+            binds.push((param.to_string(), Value::from(n)));
+        }
+        let items = select(connection, &statement, binds)?
+            .into_iter()
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Page::new(items, total, limit, offset))
+    }
+
+    /// Tables made by [`Crud::create`] always have a `rowid`, so this orders
+    /// by `rowid` descending.
+    ///
+    /// Note that an `INTEGER PRIMARY KEY` column is an alias for `rowid`, so
+    /// for those tables "newest" means "largest key".
+    fn read_all_rev<'a>(
+        connection: Self::Connection<'a>,
+    ) -> Result<Box<dyn Iterator<Item = Result<Self, snafu::Whatever>> + 'a>, snafu::Whatever> {
+        let table = SqliteDialect::quote_ident(Self::table_name());
+        let statement = format!("SELECT * FROM {table} ORDER BY rowid DESC;");
+        let items = select(connection, &statement, vec![])?;
+        Ok(Box::new(items.into_iter()))
+    }
+
+    fn read_where<'a>(
+        connection: &'a rusqlite::Connection,
+        key_name: &'a str,
+        comparison: &'a str,
+        key_value: impl IsCrudField,
+    ) -> Result<Box<dyn Iterator<Item = Result<Self, snafu::Whatever>> + 'a>, snafu::Whatever> {
+        let table_name = Self::table_name();
+        // sqlite treats a quoted identifier that isn't a column as a string
+        // literal, so check the column exists up front
+        snafu::ensure_whatever!(
+            Self::field_names().contains(&key_name),
+            "table `{table_name}` has no column `{key_name}`"
+        );
+        let [table, key_name] = [table_name, key_name].map(SqliteDialect::quote_ident);
+        let statement = format!("SELECT * FROM {table} WHERE {key_name} {comparison} :key_value");
+        let binds = vec![(":key_value".to_string(), key_value.into_value())];
+        let items = select(connection, &statement, binds)?;
+        Ok(Box::new(items.into_iter()))
+    }
+
+    fn read_like<'a>(
+        connection: Self::Connection<'a>,
+        column: &str,
+        pattern: LikePattern,
+    ) -> Result<Box<dyn Iterator<Item = Result<Self, snafu::Whatever>> + 'a>, snafu::Whatever> {
+        let table_name = Self::table_name();
+        snafu::ensure_whatever!(
+            Self::field_names().contains(&column),
+            "table `{table_name}` has no column `{column}`"
+        );
+        let [table, column] = [table_name, column].map(SqliteDialect::quote_ident);
+        let statement = format!("SELECT * FROM {table} WHERE {column} LIKE :pattern ESCAPE '\\';");
+        let binds = vec![(
+            ":pattern".to_string(),
+            Value::from(pattern.as_str().to_string()),
+        )];
+        let items = select(connection, &statement, binds)?;
+        Ok(Box::new(items.into_iter()))
+    }
+
+    fn read_column_cmp<'a>(
+        connection: Self::Connection<'a>,
+        left: &str,
+        comparison: &str,
+        right: &str,
+    ) -> Result<Box<dyn Iterator<Item = Result<Self, snafu::Whatever>> + 'a>, snafu::Whatever> {
+        let table_name = Self::table_name();
+        let fields = Self::crud_fields();
+        for column in [left, right] {
+            snafu::ensure_whatever!(
+                fields.iter().any(|field| field.name == column),
+                "table `{table_name}` has no column `{column}`"
+            );
+        }
+        let op = Comparison::from_sql(comparison)
+            .whatever_context(format!("unknown comparison `{comparison}`"))?
+            .as_sql();
+        let [table, left, right] = [table_name, left, right].map(SqliteDialect::quote_ident);
+        let statement = format!("SELECT * FROM {table} WHERE {left} {op} {right};");
+        let items = select(connection, &statement, vec![])?;
+        Ok(Box::new(items.into_iter()))
+    }
+
+    /// Results are ordered by `column`.
+    fn aggregate(
+        connection: &rusqlite::Connection,
+        column: &str,
+    ) -> Result<Vec<(Value, i64)>, snafu::Whatever> {
+        let table_name = Self::table_name();
+        // sqlite treats a quoted identifier that isn't a column as a string
+        // literal, so check the column exists up front
+        snafu::ensure_whatever!(
+            Self::crud_fields().iter().any(|field| field.name == column),
+            "table `{table_name}` has no column `{column}`"
+        );
+        let [table, column] = [table_name, column].map(SqliteDialect::quote_ident);
+        let statement = format!(
+            "SELECT {column} AS value, COUNT(*) AS count FROM {table} \
+             GROUP BY {column} ORDER BY {column};"
+        );
+        select_fields(connection, &statement, vec![], &["value", "count"])?
+            .into_iter()
+            .map(|mut row| {
+                let count = row["count"].as_i64().whatever_context("count")?;
+                let value = row.shift_remove("value").unwrap_or(Value::None);
+                Ok((value, count))
+            })
+            .collect()
+    }
+
+    fn read_projected<'a>(
+        connection: &rusqlite::Connection,
+        columns: &[&'a str],
+    ) -> Result<Vec<FieldMap<'a>>, snafu::Whatever> {
+        let table_name = Self::table_name();
+        let primary_key = Self::primary_key_name();
+        snafu::ensure_whatever!(
+            !columns.is_empty(),
+            "no columns to read from `{table_name}`"
+        );
+        let column_names = Self::field_names();
+        for column in columns {
+            snafu::ensure_whatever!(
+                column_names.contains(column),
+                "table `{table_name}` has no column `{column}`"
+            );
+        }
+        let projection = columns
+            .iter()
+            .map(|column| SqliteDialect::quote_ident(column))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let [table, primary_key] = [table_name, primary_key].map(SqliteDialect::quote_ident);
+        let statement = format!("SELECT {projection} FROM {table} ORDER BY {primary_key};");
+        select_fields(connection, &statement, vec![], columns)
+    }
+
+    fn read<'a>(
+        connection: Self::Connection<'a>,
+        key: Self::PrimaryKey,
+    ) -> Result<Box<dyn Iterator<Item = Result<Self, snafu::Whatever>> + 'a>, snafu::Whatever> {
+        <Self as Crud<Rusqlite>>::read_where(connection, Self::primary_key_name(), "=", key)
+    }
+
+    /// Keys are read in chunks of [`Rusqlite::MAX_VARIABLES`], one query per
+    /// chunk. No query is made when `keys` is empty.
+    fn read_in<'a, Key: IsCrudField>(
+        connection: Self::Connection<'a>,
+        column: &str,
+        values: Vec<Key>,
+    ) -> Result<Box<dyn Iterator<Item = Result<Self, snafu::Whatever>> + 'a>, snafu::Whatever> {
+        let table_name = Self::table_name();
+        snafu::ensure_whatever!(
+            Self::field_names().contains(&column),
+            "table `{table_name}` has no column `{column}`"
+        );
+        let [table, column] = [table_name, column].map(SqliteDialect::quote_ident);
+        let mut rows = vec![];
+        for chunk in values.chunks(Rusqlite::MAX_VARIABLES) {
+            let binds = chunk
+                .iter()
+                .enumerate()
+                .map(|(i, value)| (SqliteDialect::placeholder(i), value.into_value()))
+                .collect::<Vec<_>>();
+            let params = binds
+                .iter()
+                .map(|(param, _)| param.as_str())
+                .collect::<Vec<_>>()
+                .join(", ");
+            let statement = format!("SELECT * FROM {table} WHERE {column} IN ({params});");
+            rows.extend(select(connection, &statement, binds)?);
+        }
+        Ok(Box::new(rows.into_iter()))
+    }
+
+    fn read_by_keys(
+        connection: &rusqlite::Connection,
+        keys: Vec<Self::PrimaryKey>,
+    ) -> Result<Vec<Self>, snafu::Whatever> {
+        <Self as Crud<Rusqlite>>::read_in(connection, Self::primary_key_name(), keys)?.collect()
+    }
+
+    /// Retried while the database is busy, see [`BusyRetry`](crate::BusyRetry).
+    fn update(&self, connection: &rusqlite::Connection) -> Result<(), snafu::Whatever> {
+        ensure_mutable::<Self>("update")?;
+        let primary_key = Self::primary_key_name();
+        let mut fields = self.as_crud_fields();
+        let key_value = fields
+            .shift_remove(primary_key)
+            .whatever_context("no key value")?;
+        let columns = fields.keys().copied().collect::<Vec<_>>();
+        let mut binds = sqlite_common::insert_binds(fields);
+        binds.push((":key_value".to_string(), key_value));
+        let table_name = Self::table_name();
+        let statement = sqlite_common::update_sql(table_name, &columns, primary_key);
+        sqlite_common::retry_busy("update", table_name, is_busy, || {
+            execute(connection, &statement, binds.clone())
+        })?;
+        Ok(())
+    }
+
+    fn update_diff_count(
+        &self,
+        old: &Self,
+        connection: &rusqlite::Connection,
+    ) -> Result<usize, snafu::Whatever> {
+        ensure_mutable::<Self>("update")?;
+        let primary_key = Self::primary_key_name();
+        let key_value = self.primary_key_val();
+        snafu::ensure_whatever!(
+            key_value == old.primary_key_val(),
+            "can't diff rows with different primary keys"
+        );
+        let old_fields = old.as_crud_fields();
+        let changed = self
+            .as_crud_fields()
+            .into_iter()
+            .filter(|(name, value)| *name != primary_key && old_fields.get(name) != Some(value))
+            .collect::<Vec<_>>();
+        if changed.is_empty() {
+            return Ok(0);
+        }
+        let columns = changed.iter().map(|(name, _)| *name).collect::<Vec<_>>();
+        let statement = sqlite_common::update_sql(Self::table_name(), &columns, primary_key);
+        let count = changed.len();
+        let binds = changed
+            .into_iter()
+            .enumerate()
+            .map(|(i, (_, value))| (SqliteDialect::placeholder(i), value))
+            .chain([(":key_value".to_string(), key_value)])
+            .collect();
+        execute(connection, &statement, binds)?;
+        Ok(count)
+    }
+
+    /// Retried while the database is busy, see [`BusyRetry`](crate::BusyRetry).
+    fn delete(self, connection: &rusqlite::Connection) -> Result<bool, snafu::Whatever> {
+        ensure_mutable::<Self>("delete from")?;
+        let table_name = Self::table_name();
+        let (key_name, key_value) = self.primary_key();
+        let clause = format!(
+            " WHERE {} = :key_value",
+            SqliteDialect::quote_ident(key_name)
+        );
+        let statement = sqlite_common::delete_sql(table_name, &clause);
+        let binds = vec![(":key_value".to_string(), key_value)];
+        let deleted = sqlite_common::retry_busy("delete from", table_name, is_busy, || {
+            execute(connection, &statement, binds.clone())
+        })?;
+        Ok(deleted > 0)
+    }
+
+    fn update_where(
+        connection: &rusqlite::Connection,
+        set_col: &str,
+        set_val: impl IsCrudField,
+        key_name: &str,
+        comparison: Comparison,
+        key_value: impl IsCrudField,
+    ) -> Result<u64, snafu::Whatever> {
+        ensure_mutable::<Self>("update")?;
+        let table_name = Self::table_name();
+        let column_names = Self::field_names();
+        for column in [set_col, key_name] {
+            snafu::ensure_whatever!(
+                column_names.contains(&column),
+                "table `{table_name}` has no column `{column}`"
+            );
+        }
+        let [table, set_col, key_name] =
+            [table_name, set_col, key_name].map(SqliteDialect::quote_ident);
+        let op = comparison.as_sql();
+        let statement =
+            format!("UPDATE {table} SET {set_col} = :set_val WHERE {key_name} {op} :key_value;");
+        let binds = vec![
+            (":set_val".to_string(), set_val.into_value()),
+            (":key_value".to_string(), key_value.into_value()),
+        ];
+        Ok(execute(connection, &statement, binds)? as u64)
+    }
+
+    fn delete_where(
+        connection: &rusqlite::Connection,
+        query: &Query<Self>,
+    ) -> Result<usize, snafu::Whatever> {
+        ensure_mutable::<Self>("delete from")?;
+        let table_name = Self::table_name();
+        snafu::ensure_whatever!(
+            !query.conditions().is_empty(),
+            "refusing to delete from `{table_name}` without conditions, use `clear` to delete every row"
+        );
+        let (clause, binds) = where_clause::<Self>(query.conditions())?;
+        execute(
+            connection,
+            &sqlite_common::delete_sql(table_name, &clause),
+            binds,
+        )
+    }
+
+    fn clear(connection: &rusqlite::Connection) -> Result<usize, snafu::Whatever> {
+        ensure_mutable::<Self>("clear")?;
+        let table_name = Self::table_name();
+        execute(
+            connection,
+            &sqlite_common::delete_sql(table_name, ""),
+            vec![],
+        )
+        .whatever_context(format!("could not clear `{table_name}`"))
+    }
+
+    fn atomically<R>(
+        connection: &rusqlite::Connection,
+        f: impl FnOnce() -> Result<R, snafu::Whatever>,
+    ) -> Result<R, snafu::Whatever> {
+        savepoint(connection, "atomically", f)
+    }
+
+    fn read_history(
+        connection: &rusqlite::Connection,
+    ) -> Result<Vec<History<Self>>, snafu::Whatever> {
+        let table_name = Self::table_name();
+        snafu::ensure_whatever!(Self::audited(), "table `{table_name}` is not audited");
+        let history = SqliteDialect::quote_ident(&history_table_name::<Self>());
+        let mut column_names = Self::field_names();
+        column_names.extend(["_op", "_at"]);
+        let statement = format!("SELECT * FROM {history} ORDER BY rowid;");
+        select_fields(connection, &statement, vec![], &column_names)?
+            .into_iter()
+            .map(|row| {
+                let op = row["_op"].as_string().whatever_context("_op")?;
+                let op = AuditOp::from_column(op).whatever_context(format!("unknown op `{op}`"))?;
+                let at = row["_at"].as_string().whatever_context("_at")?.clone();
+                let row = Self::try_from_crud_fields(&row)?;
+                Ok(History { op, at, row })
+            })
+            .collect()
+    }
+}
+
+impl<T: HasCrudFields> Query<T> {
+    /// The parameterized sqlite statement for this query and the values to
+    /// bind to it.
+    pub fn sqlite_statement(&self) -> Result<(String, Vec<(String, Value)>), snafu::Whatever> {
+        let table = SqliteDialect::quote_ident(T::table_name());
+        let (clause, binds) = where_clause::<T>(self.conditions())?;
+        Ok((format!("SELECT * FROM {table}{clause};"), binds))
+    }
+
+    /// Read all rows matching this query.
+    pub fn execute<'c>(
+        self,
+        connection: &'c rusqlite::Connection,
+    ) -> Result<Box<dyn Iterator<Item = Result<T, snafu::Whatever>> + 'c>, snafu::Whatever>
+    where
+        T: 'c,
+    {
+        let (statement, binds) = self.sqlite_statement()?;
+        let items = select(connection, &statement, binds)?;
+        Ok(Box::new(items.into_iter()))
+    }
+}
+
+impl<T: HasCrudFields> Select<T> {
+    /// The parameterized sqlite statement for this select and the values to
+    /// bind to it.
+    pub fn sqlite_statement(&self) -> Result<(String, Vec<(String, Value)>), snafu::Whatever> {
+        let table_name = T::table_name();
+        let (clause, binds) = where_clause::<T>(self.conditions())?;
+        let field_names = T::field_names();
+        let mut order = vec![];
+        for OrderBy { column, descending } in self.orderings() {
+            snafu::ensure_whatever!(
+                field_names.contains(&column.as_str()),
+                "table `{table_name}` has no column `{column}`"
+            );
+            let direction = if *descending { "DESC" } else { "ASC" };
+            order.push(format!(
+                "{} {direction}",
+                SqliteDialect::quote_ident(column)
+            ));
+        }
+        let primary_key = T::primary_key_name();
+        if !self.orderings().iter().any(|o| o.column == primary_key) {
+            order.push(format!("{} ASC", SqliteDialect::quote_ident(primary_key)));
+        }
+        let mut statement = format!(
+            "SELECT * FROM {}{clause} ORDER BY {}",
+            SqliteDialect::quote_ident(table_name),
+            order.join(", ")
+        );
+        if let Some(limit) = self.row_limit() {
+            // sqlite's LIMIT is an i64
+            let limit = limit.min(i64::MAX as usize);
+            statement.push_str(&format!(" LIMIT {limit}"));
+        }
+        statement.push(';');
+        Ok((statement, binds))
+    }
+
+    /// Read the rows of this select.
+    pub fn execute<'c>(
+        self,
+        connection: &'c rusqlite::Connection,
+    ) -> Result<Box<dyn Iterator<Item = Result<T, snafu::Whatever>> + 'c>, snafu::Whatever>
+    where
+        T: 'c,
+    {
+        let (statement, binds) = self.sqlite_statement()?;
+        let items = select(connection, &statement, binds)?;
+        Ok(Box::new(items.into_iter()))
+    }
+}
@@ -0,0 +1,62 @@
+//! Compressed columns.
+//!
+//! Fields marked `#[compress]` are gzipped into a bytes column by
+//! [`compress_value`] and restored by [`decompress_value`].
+use std::io::{Read, Write};
+
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use snafu::{OptionExt, ResultExt};
+
+use crate::Value;
+
+/// Prefixes values compressed by [`compress_value`], so that raw values, eg.
+/// ones written before a field was marked `#[compress]`, can still be read.
+pub const COMPRESSED_MAGIC: &[u8; 4] = b"TYZ\x01";
+
+const STRING_TAG: u8 = b's';
+const BYTES_TAG: u8 = b'b';
+
+/// Compress a string or bytes value.
+///
+/// Other values are returned as-is.
+pub fn compress_value(value: Value) -> Value {
+    let (tag, bytes) = match value {
+        Value::String(s) => (STRING_TAG, s.into_bytes()),
+        Value::Bytes(b) => (BYTES_TAG, b),
+        value => return value,
+    };
+    let mut header = COMPRESSED_MAGIC.to_vec();
+    header.push(tag);
+    let mut encoder = GzEncoder::new(header, Compression::default());
+    // writing into a `Vec` can't fail
+    encoder
+        .write_all(&bytes)
+        .expect("could not compress into memory");
+    Value::Bytes(encoder.finish().expect("could not compress into memory"))
+}
+
+/// Decompress a value made by [`compress_value`].
+///
+/// Values without the [`COMPRESSED_MAGIC`] header are returned as-is.
+pub fn decompress_value(value: &Value) -> Result<Value, snafu::Whatever> {
+    let Some(rest) = value
+        .as_bytes()
+        .and_then(|bytes| bytes.strip_prefix(COMPRESSED_MAGIC.as_slice()))
+    else {
+        return Ok(value.clone());
+    };
+    let (tag, compressed) = rest
+        .split_first()
+        .whatever_context("compressed value has no tag")?;
+    let mut bytes = vec![];
+    GzDecoder::new(compressed)
+        .read_to_end(&mut bytes)
+        .whatever_context("could not decompress")?;
+    match *tag {
+        STRING_TAG => Ok(Value::String(
+            String::from_utf8(bytes).whatever_context("decompressed string is not utf8")?,
+        )),
+        BYTES_TAG => Ok(Value::Bytes(bytes)),
+        tag => snafu::whatever!("unknown compressed value tag {tag}"),
+    }
+}
@@ -0,0 +1,37 @@
+//! CSV cells.
+//!
+//! Used by [`Crud::export_csv`](crate::Crud::export_csv) and
+//! [`Crud::import_csv`](crate::Crud::import_csv).
+use base64::Engine;
+
+use crate::{CrudField, Value, ValueType};
+
+/// The CSV cell for `value`.
+///
+/// Bytes are base64 encoded and `Value::None` is an empty cell, which is
+/// what [`Value::from_str_typed`] expects.
+pub(crate) fn value_to_csv(value: &Value) -> String {
+    match value {
+        Value::Integer(i) => i.to_string(),
+        Value::Float(f) => f.to_string(),
+        Value::String(s) => s.clone(),
+        Value::Bytes(b) => base64::engine::general_purpose::STANDARD.encode(b),
+        Value::Boolean(b) => i64::from(*b).to_string(),
+        Value::None => String::new(),
+    }
+}
+
+/// Parse a CSV cell of `field`'s column.
+///
+/// Empty cells are `Value::None`, except in non-nullable string and bytes
+/// columns where they're empty strings and bytes.
+pub(crate) fn value_from_csv(cell: &str, field: &CrudField) -> Result<Value, snafu::Whatever> {
+    if cell.is_empty() && !field.nullable {
+        match field.ty {
+            ValueType::String => return Ok(Value::String(String::new())),
+            ValueType::Bytes => return Ok(Value::Bytes(vec![])),
+            ValueType::Integer | ValueType::Float => {}
+        }
+    }
+    Value::from_str_typed(cell, &field.ty)
+}
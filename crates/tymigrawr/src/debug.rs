@@ -0,0 +1,48 @@
+//! Helpers for debugging.
+use crate::{FieldMap, Value};
+
+/// Render rows as an aligned ASCII table with a header.
+///
+/// Only `columns` are shown, in the order given. Values are formatted with
+/// their `Display` impl and missing values are left blank.
+pub fn format_rows(rows: &[FieldMap<'_>], columns: &[&str]) -> String {
+    let cells = rows
+        .iter()
+        .map(|row| {
+            columns
+                .iter()
+                .map(|column| row.get(column).map(Value::to_string).unwrap_or_default())
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    let widths = columns
+        .iter()
+        .enumerate()
+        .map(|(i, column)| {
+            cells
+                .iter()
+                .map(|row| row[i].chars().count())
+                .fold(column.chars().count(), usize::max)
+        })
+        .collect::<Vec<_>>();
+    let rule = widths
+        .iter()
+        .map(|width| "-".repeat(*width))
+        .collect::<Vec<_>>();
+
+    let mut table = String::new();
+    push_line(&mut table, columns, &widths);
+    push_line(&mut table, &rule, &widths);
+    for row in cells.iter() {
+        push_line(&mut table, row, &widths);
+    }
+    table
+}
+
+fn push_line(table: &mut String, cells: &[impl AsRef<str>], widths: &[usize]) {
+    table.push('|');
+    for (cell, width) in cells.iter().zip(widths) {
+        table.push_str(&format!(" {:width$} |", cell.as_ref()));
+    }
+    table.push('\n');
+}
@@ -0,0 +1,29 @@
+//! Decimal columns.
+//!
+//! [`rust_decimal::Decimal`] is stored as its canonical string so values like
+//! `0.10` keep their exact digits, which a float column would lose.
+use std::str::FromStr;
+
+use snafu::{OptionExt, ResultExt};
+
+use crate::{CrudField, IsCrudField, Value, ValueType};
+
+impl IsCrudField for rust_decimal::Decimal {
+    type MaybeSelf = Result<Self, snafu::Whatever>;
+
+    fn field() -> CrudField {
+        CrudField {
+            ty: ValueType::String,
+            ..Default::default()
+        }
+    }
+
+    fn into_value(&self) -> Value {
+        self.to_string().into()
+    }
+
+    fn maybe_from_value(value: &Value) -> Self::MaybeSelf {
+        let s = value.as_string().whatever_context("not a string")?;
+        rust_decimal::Decimal::from_str(s).whatever_context(format!("'{s}' is not a decimal"))
+    }
+}
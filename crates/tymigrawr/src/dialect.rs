@@ -0,0 +1,17 @@
+//! Differences between SQL databases.
+use crate::ValueType;
+
+/// How one SQL database spells column types, placeholders and identifiers.
+///
+/// Backends build their statements through their dialect instead of
+/// hard-coding these.
+pub trait SqlDialect {
+    /// The declared column type of `ty`.
+    fn column_type(ty: &ValueType) -> &'static str;
+
+    /// The placeholder of the `index`th bound value in a statement.
+    fn placeholder(index: usize) -> String;
+
+    /// `name` quoted as an identifier, eg. a table or column name.
+    fn quote_ident(name: &str) -> String;
+}
@@ -0,0 +1,85 @@
+//! JSON columns, and JSON values of whole rows.
+use base64::Engine;
+use serde::{de::DeserializeOwned, Serialize};
+use snafu::{OptionExt, ResultExt};
+
+use crate::{CrudField, IsCrudField, Value, ValueType};
+
+/// A field stored as a JSON string column.
+///
+/// This is how to store collections like `Vec<String>`. It's a wrapper
+/// because `Vec<u8>` is already stored natively as bytes.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Json<T>(pub T);
+
+impl<T> From<T> for Json<T> {
+    fn from(value: T) -> Self {
+        Json(value)
+    }
+}
+
+impl<T: Serialize + DeserializeOwned> IsCrudField for Json<T> {
+    type MaybeSelf = Result<Self, snafu::Whatever>;
+
+    fn field() -> CrudField {
+        CrudField {
+            ty: ValueType::String,
+            ..Default::default()
+        }
+    }
+
+    fn into_value(&self) -> Value {
+        // serde_json only fails here for maps with non-string keys
+        serde_json::to_string(&self.0)
+            .map(Value::from)
+            .unwrap_or(Value::None)
+    }
+
+    fn maybe_from_value(value: &Value) -> Self::MaybeSelf {
+        let s = value.as_string().whatever_context("not a string")?;
+        let t = serde_json::from_str(s).whatever_context("can't deserialize json")?;
+        Ok(Json(t))
+    }
+}
+
+/// The JSON scalar for `value`.
+///
+/// Bytes are base64 encoded. Floats JSON can't represent (NaN and the
+/// infinities) become `null`.
+pub(crate) fn value_to_json(value: &Value) -> serde_json::Value {
+    match value {
+        Value::Integer(i) => serde_json::Value::from(*i),
+        Value::Float(f) => serde_json::Value::from(*f),
+        Value::String(s) => serde_json::Value::from(s.as_str()),
+        Value::Bytes(b) => {
+            serde_json::Value::from(base64::engine::general_purpose::STANDARD.encode(b))
+        }
+        Value::Boolean(b) => serde_json::Value::from(*b),
+        Value::None => serde_json::Value::Null,
+    }
+}
+
+/// The value of `field`'s column in the JSON scalar `json`.
+pub(crate) fn value_from_json(
+    json: &serde_json::Value,
+    field: &CrudField,
+) -> Result<Value, snafu::Whatever> {
+    Ok(match (json, field.ty) {
+        (serde_json::Value::Null, _) => Value::None,
+        (serde_json::Value::Bool(b), _) => Value::Boolean(*b),
+        (serde_json::Value::Number(n), ValueType::Integer) => Value::Integer(
+            n.as_i64()
+                .whatever_context(format!("{n} is not an integer"))?,
+        ),
+        (serde_json::Value::Number(n), ValueType::Float) => {
+            Value::Float(n.as_f64().whatever_context(format!("{n} is not a float"))?)
+        }
+        (serde_json::Value::String(s), ValueType::String) => Value::String(s.clone()),
+        (serde_json::Value::String(s), ValueType::Bytes) => Value::Bytes(
+            base64::engine::general_purpose::STANDARD
+                .decode(s)
+                .whatever_context(format!("'{s}' is not base64"))?,
+        ),
+        (json, ty) => snafu::whatever!("{json} is not a {ty:?}"),
+    })
+}
@@ -1,24 +1,70 @@
-use std::{
-    collections::{HashMap, VecDeque},
-    marker::PhantomData,
-};
+use std::{collections::VecDeque, future::Future, marker::PhantomData};
 
 use snafu::prelude::*;
 
-pub use tymigrawr_derive::HasCrudFields;
+pub use tymigrawr_derive::{CrudEnum, CrudNewtype, HasCrudFields};
+
+#[cfg(feature = "backend_rusqlite")]
+mod sqlite_common;
+#[cfg(feature = "backend_rusqlite")]
+pub use sqlite_common::{with_busy_retry, BusyRetry, SqliteDialect};
 
 #[cfg(feature = "backend_sqlite")]
 mod backend_sqlite;
 #[cfg(feature = "backend_sqlite")]
 pub use backend_sqlite::*;
 
+#[cfg(feature = "backend_rusqlite")]
+mod backend_rusqlite;
+#[cfg(feature = "backend_rusqlite")]
+pub use backend_rusqlite::*;
+
+#[cfg(feature = "backend_async_sqlite")]
+mod backend_async_sqlite;
+#[cfg(feature = "backend_async_sqlite")]
+pub use backend_async_sqlite::*;
+
 #[cfg(feature = "backend_dynamodb")]
 mod backend_dynamodb;
-#[cfg(feature = "backend_dynamdb")]
+#[cfg(feature = "backend_dynamodb")]
 pub use backend_dynamodb::*;
 
+#[cfg(feature = "backend_mysql")]
+mod backend_mysql;
+#[cfg(feature = "backend_mysql")]
+pub use backend_mysql::*;
 
-#[derive(Default)]
+#[cfg(feature = "json")]
+mod json;
+#[cfg(feature = "json")]
+pub use json::*;
+
+#[cfg(feature = "csv")]
+mod csv;
+
+#[cfg(feature = "serde")]
+mod serde_base64;
+
+#[cfg(feature = "compress")]
+mod compress;
+#[cfg(feature = "compress")]
+pub use compress::*;
+
+#[cfg(feature = "decimal")]
+mod decimal;
+
+mod query;
+pub use query::*;
+
+mod dialect;
+pub use dialect::*;
+
+mod table_prefix;
+pub use table_prefix::*;
+
+pub mod debug;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
 pub enum ValueType {
     #[default]
     Integer,
@@ -27,21 +73,55 @@ pub enum ValueType {
     Bytes,
 }
 
-#[derive(Default)]
+#[derive(Debug, Default, Clone, PartialEq)]
 pub struct CrudField {
     pub name: &'static str,
     pub ty: ValueType,
     pub nullable: bool,
     pub primary_key: bool,
     pub auto_increment: bool,
+    /// A `CHECK` constraint on the column, in the backend's SQL.
+    pub check: Option<&'static str>,
+    /// The table and column this column references as a foreign key.
+    ///
+    /// The table is prefixed by [`with_table_prefix`] where it's used.
+    pub references: Option<(&'static str, &'static str)>,
+    /// The collation of the column, named as the backend names it, eg.
+    /// `NOCASE` in SQLite.
+    pub collation: Option<&'static str>,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+impl CrudField {
+    /// The table-level `FOREIGN KEY` clause of this column, if it references
+    /// another table, with identifiers quoted by `D`.
+    pub fn foreign_key_clause<D: SqlDialect>(&self) -> Option<String> {
+        let (table, column) = self.references?;
+        Some(format!(
+            "FOREIGN KEY ({}) REFERENCES {}({})",
+            D::quote_ident(self.name),
+            D::quote_ident(prefixed_table_name(table)),
+            D::quote_ident(column)
+        ))
+    }
+}
+
+/// A column value.
+///
+/// With the `serde` feature this (de)serializes adjacently tagged, eg.
+/// `{"type":"Integer","value":1}` or `{"type":"None"}`, with bytes as a
+/// base64 string.
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(tag = "type", content = "value")
+)]
 pub enum Value {
     Integer(i64),
     Float(f64),
     String(String),
-    Bytes(Vec<u8>),
+    Bytes(#[cfg_attr(feature = "serde", serde(with = "crate::serde_base64"))] Vec<u8>),
+    Boolean(bool),
     None,
 }
 
@@ -69,6 +149,31 @@ impl From<Vec<u8>> for Value {
     }
 }
 
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Value::Boolean(value)
+    }
+}
+
+impl std::fmt::Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Integer(i) => i.fmt(f),
+            Value::Float(i) => i.fmt(f),
+            Value::String(i) => i.fmt(f),
+            Value::Bytes(i) => {
+                f.write_str("x'")?;
+                for byte in i {
+                    write!(f, "{byte:02x}")?;
+                }
+                f.write_str("'")
+            }
+            Value::Boolean(i) => i.fmt(f),
+            Value::None => f.write_str("NULL"),
+        }
+    }
+}
+
 impl<T> From<Option<T>> for Value
 where
     Value: From<T>,
@@ -79,6 +184,18 @@ where
 }
 
 impl Value {
+    /// Borrow this value, without cloning strings or bytes.
+    pub fn as_value_ref(&self) -> ValueRef<'_> {
+        match self {
+            Value::Integer(i) => ValueRef::Integer(*i),
+            Value::Float(f) => ValueRef::Float(*f),
+            Value::String(s) => ValueRef::String(s),
+            Value::Bytes(b) => ValueRef::Bytes(b),
+            Value::Boolean(b) => ValueRef::Boolean(*b),
+            Value::None => ValueRef::None,
+        }
+    }
+
     pub fn as_i64(&self) -> Option<i64> {
         if let Value::Integer(i) = self {
             Some(*i)
@@ -110,14 +227,165 @@ impl Value {
             None
         }
     }
+
+    /// Backends without a boolean type store booleans as the integers `0` and
+    /// `1`, so those are read as booleans too.
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Boolean(b) => Some(*b),
+            Value::Integer(0) => Some(false),
+            Value::Integer(1) => Some(true),
+            _ => None,
+        }
+    }
+
+    /// Like `==`, but floats are equal when they're within `epsilon` of each
+    /// other.
+    ///
+    /// Two NaNs are equal, as are infinities of the same sign. Values of
+    /// different variants are never equal, eg. `Integer(1)` and `Float(1.0)`.
+    pub fn approx_eq(&self, other: &Value, epsilon: f64) -> bool {
+        match (self, other) {
+            (Value::Float(a), Value::Float(b)) => {
+                a == b || (a.is_nan() && b.is_nan()) || (a - b).abs() <= epsilon
+            }
+            _ => self == other,
+        }
+    }
+
+    /// Parse text, eg. a CSV cell, into a value of the given type.
+    ///
+    /// Bytes are expected to be base64 encoded. An empty string is
+    /// `Value::None` for every type.
+    pub fn from_str_typed(s: &str, ty: &ValueType) -> Result<Value, snafu::Whatever> {
+        use base64::Engine;
+
+        if s.is_empty() {
+            return Ok(Value::None);
+        }
+        Ok(match ty {
+            ValueType::Integer => Value::Integer(
+                s.trim()
+                    .parse()
+                    .whatever_context(format!("'{s}' is not an integer"))?,
+            ),
+            ValueType::Float => Value::Float(
+                s.trim()
+                    .parse()
+                    .whatever_context(format!("'{s}' is not a float"))?,
+            ),
+            ValueType::String => Value::String(s.to_string()),
+            ValueType::Bytes => Value::Bytes(
+                base64::engine::general_purpose::STANDARD
+                    .decode(s.trim())
+                    .whatever_context(format!("'{s}' is not base64"))?,
+            ),
+        })
+    }
+
+    /// Convert to the variant stored in columns of type `target`, for
+    /// backends that read values back as another compatible variant, eg.
+    /// DynamoDB reading the float `600.0` as the integer `600`.
+    ///
+    /// Integers and floats convert both ways as long as no precision is lost,
+    /// strings are parsed into numbers, numbers are formatted as strings and
+    /// booleans are the integers `0` and `1`. Strings and bytes convert as
+    /// UTF-8. `Value::None` stays `Value::None`.
+    pub fn coerce_to(&self, target: ValueType) -> Result<Value, snafu::Whatever> {
+        Ok(match (self, target) {
+            (Value::None, _) => Value::None,
+            (Value::Integer(_), ValueType::Integer)
+            | (Value::Float(_), ValueType::Float)
+            | (Value::String(_), ValueType::String)
+            | (Value::Bytes(_), ValueType::Bytes) => self.clone(),
+            (Value::Integer(i), ValueType::Float) => {
+                let f = *i as f64;
+                // compared as i128, since `as i64` would saturate 2^63 back to i64::MAX
+                snafu::ensure_whatever!(
+                    f as i128 == i128::from(*i),
+                    "{i} can't be a float without rounding"
+                );
+                Value::Float(f)
+            }
+            (Value::Float(f), ValueType::Integer) => {
+                // i64::MAX as f64 rounds up to 2^63, which is out of range
+                snafu::ensure_whatever!(
+                    f.fract() == 0.0 && *f >= i64::MIN as f64 && *f < i64::MAX as f64,
+                    "{f} is not an integer"
+                );
+                Value::Integer(*f as i64)
+            }
+            (Value::Integer(i), ValueType::String) => Value::String(i.to_string()),
+            (Value::Float(f), ValueType::String) => Value::String(f.to_string()),
+            (Value::String(s), ValueType::Integer | ValueType::Float) => {
+                snafu::ensure_whatever!(!s.is_empty(), "an empty string is not a number");
+                Value::from_str_typed(s, &target)?
+            }
+            (Value::String(s), ValueType::Bytes) => Value::Bytes(s.as_bytes().to_vec()),
+            (Value::Bytes(b), ValueType::String) => {
+                Value::String(String::from_utf8(b.clone()).whatever_context("bytes are not UTF-8")?)
+            }
+            (Value::Boolean(b), ValueType::Integer) => Value::Integer(i64::from(*b)),
+            (value, target) => snafu::whatever!("{value:?} can't be coerced to {target:?}"),
+        })
+    }
+}
+
+/// A borrowed [`Value`].
+///
+/// Backends read rows as these where they can, so string and bytes columns
+/// are only copied once, into the field that owns them.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub enum ValueRef<'a> {
+    Integer(i64),
+    Float(f64),
+    String(&'a str),
+    Bytes(&'a [u8]),
+    Boolean(bool),
+    None,
+}
+
+impl<'a> From<&'a Value> for ValueRef<'a> {
+    fn from(value: &'a Value) -> Self {
+        value.as_value_ref()
+    }
+}
+
+impl From<ValueRef<'_>> for Value {
+    fn from(value: ValueRef<'_>) -> Self {
+        value.to_value()
+    }
+}
+
+impl ValueRef<'_> {
+    /// Copy into an owned [`Value`].
+    pub fn to_value(self) -> Value {
+        match self {
+            ValueRef::Integer(i) => Value::Integer(i),
+            ValueRef::Float(f) => Value::Float(f),
+            ValueRef::String(s) => Value::String(s.to_owned()),
+            ValueRef::Bytes(b) => Value::Bytes(b.to_vec()),
+            ValueRef::Boolean(b) => Value::Boolean(b),
+            ValueRef::None => Value::None,
+        }
+    }
 }
 
 pub trait IsCrudField: Sized {
     type MaybeSelf;
 
     fn field() -> CrudField;
+    #[allow(clippy::wrong_self_convention)]
     fn into_value(&self) -> Value;
     fn maybe_from_value(value: &Value) -> Self::MaybeSelf;
+
+    /// Like [`IsCrudField::maybe_from_value`], from a borrowed value.
+    ///
+    /// By default this copies `value` into a [`Value`] first. Fields that
+    /// own strings or bytes override it to copy them just once.
+    fn maybe_from_value_ref(value: ValueRef<'_>) -> Self::MaybeSelf {
+        Self::maybe_from_value(&value.to_value())
+    }
 }
 
 impl IsCrudField for i64 {
@@ -182,70 +450,79 @@ impl IsCrudField for u32 {
     }
 }
 
-impl IsCrudField for String {
-    type MaybeSelf = Option<Self>;
+/// A stored zero is an error.
+impl IsCrudField for std::num::NonZeroI64 {
+    type MaybeSelf = Result<Self, snafu::Whatever>;
 
     fn field() -> CrudField {
         CrudField {
-            ty: ValueType::String,
+            ty: ValueType::Integer,
             ..Default::default()
         }
     }
 
     fn into_value(&self) -> Value {
-        self.clone().into()
+        self.get().into()
     }
 
-    fn maybe_from_value(value: &Value) -> Option<Self> {
-        value.as_string().cloned()
+    fn maybe_from_value(value: &Value) -> Self::MaybeSelf {
+        let i = value.as_i64().whatever_context("not an integer")?;
+        Self::new(i).whatever_context("stored zero for a nonzero integer")
     }
 }
 
-impl IsCrudField for f64 {
-    type MaybeSelf = Option<Self>;
+/// A stored zero is an error.
+impl IsCrudField for std::num::NonZeroI32 {
+    type MaybeSelf = Result<Self, snafu::Whatever>;
 
     fn field() -> CrudField {
         CrudField {
-            ty: ValueType::Float,
+            ty: ValueType::Integer,
             ..Default::default()
         }
     }
 
     fn into_value(&self) -> Value {
-        (*self).into()
+        let i = i64::from(self.get());
+        i.into()
     }
 
-    fn maybe_from_value(value: &Value) -> Option<Self> {
-        value.as_f64()
+    fn maybe_from_value(value: &Value) -> Self::MaybeSelf {
+        let i = value.as_i64().whatever_context("not an integer")?;
+        let i = i32::try_from(i).whatever_context("can't i32 from i64")?;
+        Self::new(i).whatever_context("stored zero for a nonzero integer")
     }
 }
 
-impl IsCrudField for f32 {
-    type MaybeSelf = Option<Self>;
+/// A stored zero is an error.
+impl IsCrudField for std::num::NonZeroU32 {
+    type MaybeSelf = Result<Self, snafu::Whatever>;
 
     fn field() -> CrudField {
         CrudField {
-            ty: ValueType::Float,
+            ty: ValueType::Integer,
             ..Default::default()
         }
     }
 
     fn into_value(&self) -> Value {
-        (*self as f64).into()
+        let i = i64::from(self.get());
+        i.into()
     }
 
-    fn maybe_from_value(value: &Value) -> Option<Self> {
-        let f = value.as_f64()?;
-        Some(f as f32)
+    fn maybe_from_value(value: &Value) -> Self::MaybeSelf {
+        let i = value.as_i64().whatever_context("not an integer")?;
+        let i = u32::try_from(i).whatever_context("can't u32 from i64")?;
+        Self::new(i).whatever_context("stored zero for a nonzero integer")
     }
 }
 
-impl IsCrudField for Vec<u8> {
+impl IsCrudField for String {
     type MaybeSelf = Option<Self>;
 
     fn field() -> CrudField {
         CrudField {
-            ty: ValueType::Bytes,
+            ty: ValueType::String,
             ..Default::default()
         }
     }
@@ -255,344 +532,4392 @@ impl IsCrudField for Vec<u8> {
     }
 
     fn maybe_from_value(value: &Value) -> Option<Self> {
-        let bytes = value.as_bytes()?;
-        Some(bytes.to_vec())
+        value.as_string().cloned()
+    }
+
+    fn maybe_from_value_ref(value: ValueRef<'_>) -> Option<Self> {
+        match value {
+            ValueRef::String(s) => Some(s.to_owned()),
+            _ => None,
+        }
     }
 }
 
-impl<T: IsCrudField> IsCrudField for Option<T> {
-    type MaybeSelf = T::MaybeSelf;
+/// Stored as a one-character string.
+impl IsCrudField for char {
+    type MaybeSelf = Result<Self, snafu::Whatever>;
 
     fn field() -> CrudField {
-        let mut cf = T::field();
-        cf.nullable = true;
-        cf
+        CrudField {
+            ty: ValueType::String,
+            ..Default::default()
+        }
     }
 
     fn into_value(&self) -> Value {
-        self.as_ref().map(T::into_value).unwrap_or(Value::None)
+        self.to_string().into()
     }
 
     fn maybe_from_value(value: &Value) -> Self::MaybeSelf {
-        T::maybe_from_value(value)
+        let s = value.as_string().whatever_context("not a string")?;
+        let mut chars = s.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Ok(c),
+            _ => snafu::whatever!("'{s}' is not a single character"),
+        }
     }
 }
 
-pub trait HasCrudFields: Sized {
-    fn table_name() -> &'static str;
-    fn crud_fields() -> Vec<CrudField>;
-    fn as_crud_fields(&self) -> HashMap<&str, Value>;
-    fn primary_key_name() -> &'static str;
-    fn primary_key_val(&self) -> Value;
-    fn try_from_crud_fields(fields: &HashMap<&str, Value>) -> Result<Self, snafu::Whatever>;
+/// Parse a string value with `FromStr`, naming `what` it should be on error.
+fn parse_string_value<T: std::str::FromStr>(value: &Value, what: &str) -> Result<T, snafu::Whatever>
+where
+    T::Err: std::error::Error + 'static,
+{
+    let s = value.as_string().whatever_context("not a string")?;
+    s.parse().whatever_context(format!("'{s}' is not {what}"))
 }
 
-pub struct Migration {
-    table_name: Box<dyn Fn() -> &'static str>,
-    crud_fields: Box<dyn Fn() -> Vec<CrudField>>,
-    from_prev: Box<dyn Fn(Box<dyn core::any::Any>) -> Box<dyn core::any::Any>>,
-    as_crud_fields: Box<dyn Fn(&Box<dyn core::any::Any>) -> HashMap<&str, Value>>,
-    try_from_crud_fields:
-        Box<dyn Fn(&HashMap<&str, Value>) -> Result<Box<dyn core::any::Any>, snafu::Whatever>>,
-}
+/// Stored as a string, eg. `"127.0.0.1"` or `"::1"`.
+impl IsCrudField for std::net::IpAddr {
+    type MaybeSelf = Result<Self, snafu::Whatever>;
 
-pub trait Crud<Backend>: HasCrudFields + Clone + Sized + 'static {
-    type Connection<'a>;
+    fn field() -> CrudField {
+        CrudField {
+            ty: ValueType::String,
+            ..Default::default()
+        }
+    }
 
-    /// Create a table for `Self`.
-    fn create(connection: Self::Connection<'_>) -> Result<(), snafu::Whatever>;
+    fn into_value(&self) -> Value {
+        self.to_string().into()
+    }
 
-    fn insert(&self, connection: Self::Connection<'_>) -> Result<(), snafu::Whatever>;
+    fn maybe_from_value(value: &Value) -> Self::MaybeSelf {
+        parse_string_value(value, "an IP address")
+    }
+}
 
-    fn read_all<'a>(
-        connection: Self::Connection<'a>,
-    ) -> Result<Box<dyn Iterator<Item = Result<Self, snafu::Whatever>> + 'a>, snafu::Whatever>;
+/// Stored as a string, eg. `"127.0.0.1:8080"` or `"[::1]:8080"`.
+impl IsCrudField for std::net::SocketAddr {
+    type MaybeSelf = Result<Self, snafu::Whatever>;
 
-    fn read_where<'a>(
-        connection: Self::Connection<'a>,
-        key_name: &'a str,
-        comparison: &'a str,
-        key_value: impl IsCrudField,
-    ) -> Result<Box<dyn Iterator<Item = Result<Self, snafu::Whatever>> + 'a>, snafu::Whatever>;
+    fn field() -> CrudField {
+        CrudField {
+            ty: ValueType::String,
+            ..Default::default()
+        }
+    }
 
-    fn read<'a, Key: IsCrudField>(
-        connection: Self::Connection<'a>,
-        key: Key,
-    ) -> Result<Box<dyn Iterator<Item = Result<Self, snafu::Whatever>> + 'a>, snafu::Whatever>;
+    fn into_value(&self) -> Value {
+        self.to_string().into()
+    }
 
-    fn update(&self, connection: Self::Connection<'_>) -> Result<(), snafu::Whatever>;
+    fn maybe_from_value(value: &Value) -> Self::MaybeSelf {
+        parse_string_value(value, "a socket address")
+    }
+}
 
-    fn delete(self, connection: Self::Connection<'_>) -> Result<(), snafu::Whatever>;
+/// Stored as whole seconds since the Unix epoch, negative before it. Any
+/// fraction of a second is truncated.
+impl IsCrudField for std::time::SystemTime {
+    type MaybeSelf = Result<Self, snafu::Whatever>;
 
-    fn migration<T: 'static>() -> Migration
-    where
-        Self: From<T>,
-    {
-        Migration {
-            table_name: Box::new(Self::table_name),
-            crud_fields: Box::new(Self::crud_fields),
-            from_prev: Box::new(|any: Box<dyn core::any::Any>| {
-                // SAFETY: we know we can downcast because of the Self: From<T> constraint
-                let t: Box<T> = any.downcast().unwrap();
-                let s = Self::from(*t);
-                Box::new(s)
-            }),
-            as_crud_fields: Box::new(|any: &Box<dyn core::any::Any>| {
-                if let Some(t) = any.downcast_ref::<Self>() {
-                    t.as_crud_fields()
-                } else {
-                    Default::default()
-                }
-            }),
-            try_from_crud_fields: Box::new(|fields| {
-                let t = Self::try_from_crud_fields(fields)?;
-                Ok(Box::new(t))
-            }),
+    fn field() -> CrudField {
+        CrudField {
+            ty: ValueType::Integer,
+            ..Default::default()
         }
     }
+
+    fn into_value(&self) -> Value {
+        let secs = match self.duration_since(std::time::UNIX_EPOCH) {
+            Ok(since) => i64::try_from(since.as_secs()).unwrap_or(i64::MAX),
+            Err(before) => -i64::try_from(before.duration().as_secs()).unwrap_or(i64::MAX),
+        };
+        secs.into()
+    }
+
+    fn maybe_from_value(value: &Value) -> Self::MaybeSelf {
+        let secs = value.as_i64().whatever_context("not an integer")?;
+        let duration = std::time::Duration::from_secs(secs.unsigned_abs());
+        let time = if secs >= 0 {
+            std::time::UNIX_EPOCH.checked_add(duration)
+        } else {
+            std::time::UNIX_EPOCH.checked_sub(duration)
+        };
+        time.whatever_context(format!("{secs} seconds from the epoch is out of range"))
+    }
 }
 
-pub trait MigrateEntireTable {
-    type Connection<'a>: Copy;
+/// Stored as whole milliseconds. Any fraction of a millisecond is truncated
+/// and durations longer than `i64::MAX` milliseconds are stored as that.
+impl IsCrudField for std::time::Duration {
+    type MaybeSelf = Result<Self, snafu::Whatever>;
 
-    fn read_all_values<'a>(
-        connection: Self::Connection<'a>,
-        table_name: &'a str,
-        column_names: Vec<&'a str>,
-    ) -> Result<Vec<Result<HashMap<&'a str, Value>, snafu::Whatever>>, snafu::Whatever>;
+    fn field() -> CrudField {
+        CrudField {
+            ty: ValueType::Integer,
+            ..Default::default()
+        }
+    }
 
-    fn insert_fields(
-        connection: Self::Connection<'_>,
-        table_name: &str,
-        fields: &HashMap<&str, Value>,
-    ) -> Result<(), snafu::Whatever>;
+    fn into_value(&self) -> Value {
+        i64::try_from(self.as_millis()).unwrap_or(i64::MAX).into()
+    }
 
-    fn delete_all(
-        connection: Self::Connection<'_>,
-        table_name: &str,
-    ) -> Result<(), snafu::Whatever>;
+    fn maybe_from_value(value: &Value) -> Self::MaybeSelf {
+        let millis = value.as_i64().whatever_context("not an integer")?;
+        let millis = u64::try_from(millis)
+            .ok()
+            .whatever_context(format!("{millis}ms is a negative duration"))?;
+        Ok(std::time::Duration::from_millis(millis))
+    }
 }
 
-pub struct Migrations<T, Backend> {
-    _current: PhantomData<(T, Backend)>,
-    all: VecDeque<Migration>,
-}
+/// How NaN floats are stored.
+///
+/// sqlite can't store NaN as a number (it turns it into NULL), so it's stored
+/// as this string instead. Infinities are stored as numbers. MySQL and
+/// DynamoDB have neither, so their backends reject them.
+///
+/// The sentinel is text in a `REAL` column, which sqlite doesn't treat like
+/// any number:
+///
+/// - `ORDER BY` sorts it after every number, as text sorts after numbers.
+/// - Comparisons with a number treat it as the greater, eg. `WHERE x > 1.0`
+///   matches a NaN row and `WHERE x < 1.0` doesn't.
+/// - `SUM` and `AVG` count it as `0.0`, and `MAX` returns it.
+///
+/// Leave NaN rows out, eg. with `typeof(x) = 'real'`, where that matters.
+pub const NAN_SENTINEL: &str = "NaN";
 
-impl<T: HasCrudFields + Clone + Sized + 'static, Backend: MigrateEntireTable>
-    Migrations<T, Backend>
-{
-    pub fn default() -> Self {
-        Self {
-            _current: PhantomData,
-            all: Default::default(),
+impl IsCrudField for f64 {
+    type MaybeSelf = Option<Self>;
+
+    fn field() -> CrudField {
+        CrudField {
+            ty: ValueType::Float,
+            ..Default::default()
         }
-        .with_version::<T>()
     }
-}
 
-impl<T: HasCrudFields + Clone + Sized + 'static, Backend: MigrateEntireTable>
-    Migrations<T, Backend>
-{
-    pub fn with_version<Next>(self) -> Migrations<Next, Backend>
-    where
-        Next: From<T> + HasCrudFields + Clone + Sized + 'static,
-    {
-        let Self {
-            _current: _,
-            mut all,
-        } = self;
-        all.push_back(Next::migration());
-        Migrations {
-            _current: PhantomData,
-            all,
+    fn into_value(&self) -> Value {
+        if self.is_nan() {
+            Value::String(NAN_SENTINEL.to_string())
+        } else {
+            (*self).into()
         }
     }
 
-    pub fn run<'a>(self, connection: Backend::Connection<'a>) -> Result<(), snafu::Whatever> {
-        self.run_with(|_| connection)
+    fn maybe_from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::Float(f) => Some(*f),
+            Value::String(s) if s == NAN_SENTINEL => Some(f64::NAN),
+            _ => None,
+        }
     }
+}
 
-    pub fn run_with<'a>(
-        self,
-        mk_connection: impl Fn(&str) -> Backend::Connection<'a>,
-    ) -> Result<(), snafu::Whatever> {
-        let Self { _current, mut all } = self;
-        log::info!(
-            "migrating {} versions of {:?}",
-            all.len(),
-            core::any::type_name::<T>()
+impl IsCrudField for f32 {
+    type MaybeSelf = Option<Self>;
+
+    fn field() -> CrudField {
+        CrudField {
+            ty: ValueType::Float,
+            ..Default::default()
+        }
+    }
+
+    fn into_value(&self) -> Value {
+        f64::from(*self).into_value()
+    }
+
+    fn maybe_from_value(value: &Value) -> Option<Self> {
+        let f = f64::maybe_from_value(value)?;
+        Some(f as f32)
+    }
+}
+
+impl IsCrudField for Vec<u8> {
+    type MaybeSelf = Option<Self>;
+
+    fn field() -> CrudField {
+        CrudField {
+            ty: ValueType::Bytes,
+            ..Default::default()
+        }
+    }
+
+    fn into_value(&self) -> Value {
+        self.clone().into()
+    }
+
+    fn maybe_from_value(value: &Value) -> Option<Self> {
+        let bytes = value.as_bytes()?;
+        Some(bytes.to_vec())
+    }
+
+    fn maybe_from_value_ref(value: ValueRef<'_>) -> Option<Self> {
+        match value {
+            ValueRef::Bytes(b) => Some(b.to_vec()),
+            _ => None,
+        }
+    }
+}
+
+/// Bytes stored as a lowercase hex string, for stores without bytes columns.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct HexBytes(pub Vec<u8>);
+
+impl From<Vec<u8>> for HexBytes {
+    fn from(bytes: Vec<u8>) -> Self {
+        HexBytes(bytes)
+    }
+}
+
+impl IsCrudField for HexBytes {
+    type MaybeSelf = Result<Self, snafu::Whatever>;
+
+    fn field() -> CrudField {
+        CrudField {
+            ty: ValueType::String,
+            ..Default::default()
+        }
+    }
+
+    fn into_value(&self) -> Value {
+        self.0
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect::<String>()
+            .into()
+    }
+
+    fn maybe_from_value(value: &Value) -> Self::MaybeSelf {
+        let s = value.as_string().whatever_context("not a string")?;
+        snafu::ensure_whatever!(s.bytes().all(|b| b.is_ascii_hexdigit()), "'{s}' is not hex");
+        snafu::ensure_whatever!(s.len() % 2 == 0, "'{s}' has an odd number of hex digits");
+        let bytes = s
+            .as_bytes()
+            .chunks(2)
+            .map(|pair| {
+                // both digits are ascii, checked above
+                let pair = std::str::from_utf8(pair).unwrap();
+                u8::from_str_radix(pair, 16).unwrap()
+            })
+            .collect();
+        Ok(HexBytes(bytes))
+    }
+}
+
+/// Converts the `MaybeSelf` of an [`IsCrudField`] into a `Result`.
+pub trait IntoCrudResult<T> {
+    fn into_crud_result(self) -> Result<T, snafu::Whatever>;
+}
+
+impl<T> IntoCrudResult<T> for Option<T> {
+    fn into_crud_result(self) -> Result<T, snafu::Whatever> {
+        self.whatever_context("unexpected value")
+    }
+}
+
+impl<T> IntoCrudResult<T> for Result<T, snafu::Whatever> {
+    fn into_crud_result(self) -> Result<T, snafu::Whatever> {
+        self
+    }
+}
+
+/// `None` is stored as NULL.
+///
+/// Nested options would flatten, `Some(None)` reading back as `None`, so the
+/// `HasCrudFields` derive rejects `Option<Option<_>>` fields.
+impl<T: IsCrudField> IsCrudField for Option<T>
+where
+    T::MaybeSelf: IntoCrudResult<T>,
+{
+    type MaybeSelf = Result<Self, snafu::Whatever>;
+
+    fn field() -> CrudField {
+        let mut cf = T::field();
+        cf.nullable = true;
+        cf
+    }
+
+    fn into_value(&self) -> Value {
+        self.as_ref().map(T::into_value).unwrap_or(Value::None)
+    }
+
+    fn maybe_from_value(value: &Value) -> Self::MaybeSelf {
+        if *value == Value::None {
+            return Ok(None);
+        }
+        T::maybe_from_value(value).into_crud_result().map(Some)
+    }
+
+    fn maybe_from_value_ref(value: ValueRef<'_>) -> Self::MaybeSelf {
+        if value == ValueRef::None {
+            return Ok(None);
+        }
+        T::maybe_from_value_ref(value).into_crud_result().map(Some)
+    }
+}
+
+/// The fields of `T`, built by `build` the first time and kept for the life
+/// of the program.
+///
+/// Used by the `HasCrudFields` derive for generic types, where a `static`
+/// would be shared by every instantiation.
+pub fn cached_crud_fields<T: 'static>(
+    build: impl FnOnce() -> Vec<CrudField>,
+) -> &'static [CrudField] {
+    type Cache =
+        std::sync::RwLock<std::collections::HashMap<core::any::TypeId, &'static [CrudField]>>;
+    static CACHE: std::sync::OnceLock<Cache> = std::sync::OnceLock::new();
+    let cache = CACHE.get_or_init(Default::default);
+    let id = core::any::TypeId::of::<T>();
+    if let Some(fields) = cache.read().unwrap_or_else(|e| e.into_inner()).get(&id) {
+        return fields;
+    }
+    cache
+        .write()
+        .unwrap_or_else(|e| e.into_inner())
+        .entry(id)
+        .or_insert_with(|| Box::leak(build().into_boxed_slice()))
+}
+
+/// The values of a row by column name.
+///
+/// Columns are kept in the order they're inserted in, which for
+/// [`HasCrudFields::as_crud_fields`] is the order of
+/// [`HasCrudFields::crud_fields`], so statements built from it are stable.
+pub type FieldMap<'a> = indexmap::IndexMap<&'a str, Value>;
+
+pub trait HasCrudFields: Sized {
+    /// The type of the primary key field, which [`Crud::read`] takes.
+    type PrimaryKey: IsCrudField;
+
+    fn table_name() -> &'static str;
+    /// The fields of each column.
+    ///
+    /// Derived impls build these once per type and keep them for the life of
+    /// the program.
+    fn crud_fields() -> &'static [CrudField];
+    fn as_crud_fields(&self) -> FieldMap<'_>;
+    fn primary_key_name() -> &'static str;
+    fn primary_key_val(&self) -> Value;
+    fn try_from_crud_fields(fields: &FieldMap<'_>) -> Result<Self, snafu::Whatever>;
+
+    /// Whether rows may only be inserted, never updated or deleted.
+    fn append_only() -> bool {
+        false
+    }
+
+    /// Whether every version of each row is kept in a history table, see
+    /// [`Crud::read_history`].
+    fn audited() -> bool {
+        false
+    }
+
+    /// The names of the fields, in declaration order.
+    fn field_names() -> Vec<&'static str> {
+        Self::crud_fields().iter().map(|field| field.name).collect()
+    }
+
+    /// The name and value of the primary key.
+    fn primary_key(&self) -> (&'static str, Value) {
+        (Self::primary_key_name(), self.primary_key_val())
+    }
+
+    /// Build `Self` from the value of each column, as returned by `column`.
+    ///
+    /// Backends use this to read rows without collecting them into a map
+    /// first. The derive implements it directly, this default goes through
+    /// [`HasCrudFields::try_from_crud_fields`].
+    fn try_from_row(
+        mut column: impl FnMut(&'static str) -> Value,
+    ) -> Result<Self, snafu::Whatever> {
+        let fields = Self::field_names()
+            .into_iter()
+            .map(|name| (name, column(name)))
+            .collect();
+        Self::try_from_crud_fields(&fields)
+    }
+
+    /// Like [`HasCrudFields::try_from_row`], with borrowed column values.
+    ///
+    /// The derive implements it directly, copying strings and bytes only into
+    /// the fields that own them. This default copies each column first.
+    fn try_from_row_ref<'r>(
+        mut column: impl FnMut(&'static str) -> ValueRef<'r>,
+    ) -> Result<Self, snafu::Whatever> {
+        Self::try_from_row(|name| column(name).to_value())
+    }
+
+    /// Each field paired with its value, in declaration order.
+    fn field_values(&self) -> Vec<(CrudField, Value)> {
+        let mut values = self.as_crud_fields();
+        Self::crud_fields()
+            .iter()
+            .map(|field| {
+                let value = values.shift_remove(field.name).unwrap_or(Value::None);
+                (field.clone(), value)
+            })
+            .collect()
+    }
+}
+
+/// Errors if `T` is append-only, naming the rejected `operation`.
+#[cfg(any(
+    feature = "backend_rusqlite",
+    feature = "backend_mysql",
+    feature = "backend_dynamodb"
+))]
+pub(crate) fn ensure_mutable<T: HasCrudFields>(operation: &str) -> Result<(), snafu::Whatever> {
+    let table_name = T::table_name();
+    snafu::ensure_whatever!(
+        !T::append_only(),
+        "can't {operation} `{table_name}`, it is an append-only table"
+    );
+    Ok(())
+}
+
+/// Errors if `value` is a NaN or infinite float of `T`'s `column`, on a
+/// `backend` whose numbers are all finite.
+///
+/// NaN is stored as [`NAN_SENTINEL`], so it's rejected here rather than by a
+/// less clear error from the database, or not at all.
+#[cfg(any(feature = "backend_mysql", feature = "backend_dynamodb"))]
+pub(crate) fn ensure_finite<T: HasCrudFields>(
+    backend: &str,
+    column: &str,
+    value: &Value,
+) -> Result<(), snafu::Whatever> {
+    let finite = match value {
+        Value::Float(f) => f.is_finite(),
+        Value::String(s) if s == NAN_SENTINEL => !T::crud_fields()
+            .iter()
+            .any(|field| field.name == column && field.ty == ValueType::Float),
+        _ => true,
+    };
+    snafu::ensure_whatever!(
+        finite,
+        "can't store a NaN or infinite float in `{}`.`{column}`, {backend} doesn't support them",
+        T::table_name()
+    );
+    Ok(())
+}
+
+/// [`ensure_finite`] for every field of a row.
+#[cfg(any(feature = "backend_mysql", feature = "backend_dynamodb"))]
+pub(crate) fn ensure_all_finite<T: HasCrudFields>(
+    backend: &str,
+    fields: &FieldMap<'_>,
+) -> Result<(), snafu::Whatever> {
+    fields
+        .iter()
+        .try_for_each(|(column, value)| ensure_finite::<T>(backend, column, value))
+}
+
+/// The fields of `T` that its table's `existing` columns lack, for
+/// [`Crud::sync_schema`].
+///
+/// Errors if a field can't be added or a column isn't a field of `T`.
+#[cfg(any(
+    feature = "backend_sqlite",
+    feature = "backend_rusqlite",
+    feature = "backend_mysql"
+))]
+pub(crate) fn columns_to_add<T: HasCrudFields>(
+    existing: &[String],
+) -> Result<Vec<CrudField>, snafu::Whatever> {
+    let table_name = T::table_name();
+    let fields = T::crud_fields();
+    let unknown = existing
+        .iter()
+        .filter(|name| !fields.iter().any(|field| field.name == name.as_str()))
+        .map(|name| format!("`{name}`"))
+        .collect::<Vec<_>>();
+    snafu::ensure_whatever!(
+        unknown.is_empty(),
+        "table `{table_name}` has columns {} that aren't fields, \
+         dropping or renaming columns needs a migration",
+        unknown.join(", ")
+    );
+    let added = fields
+        .iter()
+        .filter(|field| !existing.iter().any(|name| name == field.name))
+        .cloned()
+        .collect::<Vec<_>>();
+    for field in added.iter() {
+        snafu::ensure_whatever!(
+            field.nullable,
+            "can't add column `{}` to `{table_name}`, existing rows would be NULL, \
+             make it an `Option` or add it with a migration",
+            field.name
         );
-        while let Some(migration) = all.pop_front() {
-            if all.is_empty() {
-                break;
-            }
-            let prev_table_name = (migration.table_name)();
-            log::info!("  checking {prev_table_name}");
-            let fields = (migration.crud_fields)();
-            let column_names = fields.iter().map(|f| f.name).collect::<Vec<_>>();
-            // Get a cursor of each value in the prev table
-            let cursor = Backend::read_all_values(
-                (mk_connection)(prev_table_name),
-                prev_table_name,
-                column_names,
-            )?;
-            let mut current_table_name = prev_table_name;
-            let mut entries = 0;
-            for res_prev in cursor {
-                entries += 1;
-                let values = res_prev?;
-                // Serialize to the prev type
-                let mut prev = (migration.try_from_crud_fields)(&values)?;
-                let mut last_migration = &migration;
-                // Move the type forward with From, from the prev to the most
-                // current
-                for target in all.iter() {
-                    prev = (target.from_prev)(prev);
-                    last_migration = target;
-                }
-                // Now prev is the most current type.
-                let current = prev;
-                current_table_name = (last_migration.table_name)();
-                // Save it in the most current table, if need be.
-                if current_table_name != prev_table_name {
-                    let fields = (last_migration.as_crud_fields)(&current);
-                    Backend::insert_fields(
-                        (mk_connection)(current_table_name),
-                        current_table_name,
-                        &fields,
-                    )?;
+    }
+    Ok(added)
+}
+
+/// Deserialize `rows` across `threads` scoped threads, preserving their order.
+#[cfg(any(
+    feature = "backend_rusqlite",
+    feature = "backend_mysql",
+    feature = "backend_dynamodb"
+))]
+pub(crate) fn try_from_rows_parallel<T: HasCrudFields + Send>(
+    rows: &[FieldMap<'_>],
+    threads: usize,
+) -> Result<Vec<T>, snafu::Whatever> {
+    if rows.is_empty() {
+        return Ok(vec![]);
+    }
+    let chunk_size = rows.len().div_ceil(threads.max(1));
+    // `Whatever` isn't `Send`, so a thread reports the index of the row it
+    // failed on and the error is rebuilt here.
+    let decoded = std::thread::scope(|scope| {
+        let handles = rows
+            .chunks(chunk_size)
+            .enumerate()
+            .map(|(i, chunk)| {
+                scope.spawn(move || {
+                    chunk
+                        .iter()
+                        .enumerate()
+                        .map(|(j, row)| {
+                            T::try_from_crud_fields(row).map_err(|_| i * chunk_size + j)
+                        })
+                        .collect::<Result<Vec<_>, usize>>()
+                })
+            })
+            .collect::<Vec<_>>();
+        handles
+            .into_iter()
+            .map(|handle| {
+                handle
+                    .join()
+                    .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+            })
+            .collect::<Result<Vec<_>, usize>>()
+    });
+    match decoded {
+        Ok(chunks) => Ok(chunks.into_iter().flatten().collect()),
+        Err(index) => match T::try_from_crud_fields(&rows[index]) {
+            Err(e) => Err(e),
+            Ok(_) => snafu::whatever!("could not deserialize row {index} of `{}`", T::table_name()),
+        },
+    }
+}
+
+/// Converts a row of one version into a row of another, both boxed as `Any`.
+type Conversion = fn(Box<dyn core::any::Any>) -> Box<dyn core::any::Any>;
+
+/// A fallible [`Conversion`] that may capture state, eg. a lookup table.
+type ConversionFn = std::sync::Arc<
+    dyn Fn(Box<dyn core::any::Any>) -> Result<Box<dyn core::any::Any>, snafu::Whatever>
+        + Send
+        + Sync,
+>;
+
+/// One version in a chain of [`Migrations`].
+///
+/// Steps are function pointers or shared closures, so migrations are cheap
+/// to clone.
+#[derive(Clone)]
+#[allow(clippy::type_complexity)]
+pub struct Migration {
+    type_id: core::any::TypeId,
+    table_name: fn() -> &'static str,
+    crud_fields: fn() -> &'static [CrudField],
+    from_prev: ConversionFn,
+    as_crud_fields: fn(&Box<dyn core::any::Any>) -> FieldMap<'_>,
+    try_from_crud_fields: fn(&FieldMap<'_>) -> Result<Box<dyn core::any::Any>, snafu::Whatever>,
+    dropped_columns: Vec<&'static str>,
+    into_prev: Option<Conversion>,
+}
+
+impl core::fmt::Debug for Migration {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Migration")
+            .field("table_name", &(self.table_name)())
+            .field("crud_fields", &(self.crud_fields)())
+            .field("dropped_columns", &self.dropped_columns)
+            .field("reversible", &self.into_prev.is_some())
+            .finish()
+    }
+}
+
+impl Migration {
+    /// Create a migration step from `Prev` to `Next`.
+    pub fn new<Prev, Next>() -> Self
+    where
+        Prev: HasCrudFields + 'static,
+        Next: HasCrudFields + From<Prev> + 'static,
+    {
+        Self::from_fn::<Prev, Next>(|any| {
+            // SAFETY: we know we can downcast because of the Next: From<Prev> constraint
+            let prev: Box<Prev> = any.downcast().unwrap();
+            let next = Next::from(*prev);
+            Ok(Box::new(next))
+        })
+    }
+
+    /// Create a migration step from `Prev` to `Next` that converts rows with
+    /// `f` instead of `From`.
+    ///
+    /// `f` is given a boxed `Prev` and must return a boxed `Next`.
+    pub fn from_fn<Prev, Next>(
+        f: impl Fn(Box<dyn core::any::Any>) -> Result<Box<dyn core::any::Any>, snafu::Whatever>
+            + Send
+            + Sync
+            + 'static,
+    ) -> Self
+    where
+        Prev: HasCrudFields + 'static,
+        Next: HasCrudFields + 'static,
+    {
+        let next_fields = Next::crud_fields();
+        let dropped_columns = Prev::crud_fields()
+            .iter()
+            .map(|f| f.name)
+            .filter(|name| !next_fields.iter().any(|f| f.name == *name))
+            .collect();
+        Migration {
+            dropped_columns,
+            type_id: core::any::TypeId::of::<Next>(),
+            table_name: Next::table_name,
+            crud_fields: Next::crud_fields,
+            from_prev: std::sync::Arc::new(f),
+            as_crud_fields: |any: &Box<dyn core::any::Any>| {
+                if let Some(next) = any.downcast_ref::<Next>() {
+                    next.as_crud_fields()
+                } else {
+                    Default::default()
                 }
-            }
-            log::info!("    migrated {entries} entries from {prev_table_name}",);
-            // Remove the old entries if need be
-            if current_table_name != prev_table_name {
-                log::info!("    clearing out previous table {prev_table_name}");
-                let conn = (mk_connection)(prev_table_name);
-                Backend::delete_all(conn, prev_table_name)?;
-            }
+            },
+            try_from_crud_fields: |fields| {
+                let next = Next::try_from_crud_fields(fields)?;
+                Ok(Box::new(next))
+            },
+            into_prev: None,
+        }
+    }
+
+    /// Create a migration step from `Prev` to `Next` that can be rolled back.
+    pub fn reversible<Prev, Next>() -> Self
+    where
+        Prev: HasCrudFields + From<Next> + 'static,
+        Next: HasCrudFields + From<Prev> + 'static,
+    {
+        let mut migration = Self::new::<Prev, Next>();
+        migration.into_prev = Some(|any: Box<dyn core::any::Any>| {
+            // SAFETY: we know we can downcast because this step's rows are `Next`
+            let next: Box<Next> = any.downcast().unwrap();
+            let prev = Prev::from(*next);
+            Box::new(prev)
+        });
+        migration
+    }
+
+    /// Columns of the previous version that don't exist in this one.
+    ///
+    /// Data in these columns is discarded by this step and won't round-trip.
+    pub fn dropped_columns(&self) -> &[&'static str] {
+        &self.dropped_columns
+    }
+
+    /// Whether this step discards columns of the previous version.
+    pub fn is_lossy(&self) -> bool {
+        !self.dropped_columns.is_empty()
+    }
+}
+
+/// One page of rows, as returned by [`Crud::read_page_typed`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// Number of rows in the whole table.
+    pub total: usize,
+    pub limit: usize,
+    pub offset: usize,
+    /// Whether there are rows after this page.
+    pub has_more: bool,
+}
+
+impl<T> Page<T> {
+    pub fn new(items: Vec<T>, total: usize, limit: usize, offset: usize) -> Self {
+        let has_more = offset.saturating_add(limit) < total;
+        Page {
+            items,
+            total,
+            limit,
+            offset,
+            has_more,
+        }
+    }
+}
+
+/// The operation that put a row in a history table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditOp {
+    Insert,
+    Update,
+    Delete,
+}
+
+impl AuditOp {
+    pub const ALL: [AuditOp; 3] = [AuditOp::Insert, AuditOp::Update, AuditOp::Delete];
+
+    /// How this operation is stored in the `_op` column.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditOp::Insert => "insert",
+            AuditOp::Update => "update",
+            AuditOp::Delete => "delete",
+        }
+    }
+
+    /// Find the operation stored in an `_op` column.
+    pub fn from_column(s: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|op| op.as_str() == s)
+    }
+}
+
+/// One version of a row of an `#[audited]` table, as returned by
+/// [`Crud::read_history`].
+///
+/// Inserts record the new row, updates and deletes record the old one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct History<T> {
+    pub op: AuditOp,
+    /// When the operation happened, as written by the database.
+    pub at: String,
+    pub row: T,
+}
+
+/// The name of the history table of `T`.
+pub fn history_table_name<T: HasCrudFields>() -> String {
+    format!("{}_history", T::table_name())
+}
+
+pub trait Crud<Backend>: HasCrudFields + Clone + Sized + 'static {
+    type Connection<'a>;
+
+    /// Create a table for `Self`.
+    ///
+    /// For `#[audited]` types this also creates the history table and the
+    /// triggers that fill it.
+    ///
+    /// If `Self` has `#[references(..)]` columns, the sqlite backends turn on
+    /// `PRAGMA foreign_keys` for `connection`, since sqlite doesn't enforce
+    /// foreign keys otherwise. It's a setting of the connection, so other
+    /// connections to the same database need to turn it on themselves.
+    fn create(connection: Self::Connection<'_>) -> Result<(), snafu::Whatever>;
+
+    /// Create a temporary table for `Self`, which is dropped when
+    /// `connection` is closed.
+    ///
+    /// Other operations on `connection` then use it in place of any table of
+    /// the same name. Only the sqlite backends have temporary tables, and
+    /// they can't be `#[audited]` or have `#[references(..)]` columns.
+    fn create_temp(connection: Self::Connection<'_>) -> Result<(), snafu::Whatever> {
+        let _ = connection;
+        snafu::whatever!(
+            "can't create a temporary `{}`, this backend has no temporary tables",
+            Self::table_name()
+        )
+    }
+
+    /// Verify that the existing table for `Self` has the columns and types
+    /// described by `Self::crud_fields`.
+    ///
+    /// Only the SQL backends have a schema to check, others error.
+    fn validate_schema(connection: Self::Connection<'_>) -> Result<(), snafu::Whatever> {
+        let _ = connection;
+        snafu::whatever!(
+            "can't validate the schema of `{}`, this backend has no schema",
+            Self::table_name()
+        )
+    }
+
+    /// Add the columns of `Self` that its existing table lacks with
+    /// `ALTER TABLE ... ADD COLUMN`, or create the table if there is none.
+    ///
+    /// Only nullable columns can be added, since existing rows have no value
+    /// for them. Columns of the table that `Self` doesn't have are an error,
+    /// as dropping or renaming columns needs [`Migrations`].
+    ///
+    /// Returns the names of the added columns. Only the SQL backends have
+    /// columns to add, others error.
+    fn sync_schema(connection: Self::Connection<'_>) -> Result<Vec<&'static str>, snafu::Whatever> {
+        let _ = connection;
+        snafu::whatever!(
+            "can't sync the schema of `{}`, this backend has no schema",
+            Self::table_name()
+        )
+    }
+
+    /// Like [`Crud::create`], but errors if the table already exists with a
+    /// schema that doesn't match [`Crud::validate_schema`].
+    ///
+    /// `create` keeps an existing table as it is, so a stale schema only shows
+    /// up later when inserts fail.
+    fn create_strict<'a>(connection: Self::Connection<'a>) -> Result<(), snafu::Whatever>
+    where
+        Self::Connection<'a>: Copy,
+    {
+        Self::create(connection)?;
+        Self::validate_schema(connection)
+    }
+
+    /// Cheaply check that the backend is reachable and `Self`'s table exists.
+    fn healthcheck(connection: Self::Connection<'_>) -> Result<(), snafu::Whatever>;
+
+    fn insert(&self, connection: Self::Connection<'_>) -> Result<(), snafu::Whatever>;
+
+    /// Insert `self` unless its primary key is already taken, in which case
+    /// the existing row is left unchanged.
+    ///
+    /// Returns whether the row was inserted.
+    fn insert_or_ignore(&self, connection: Self::Connection<'_>) -> Result<bool, snafu::Whatever>;
+
+    /// Insert `self` and read back the row as it was stored, including any
+    /// values the database filled in.
+    fn insert_returning(&self, connection: Self::Connection<'_>) -> Result<Self, snafu::Whatever>;
+
+    /// Insert many rows at once.
+    fn insert_many(connection: Self::Connection<'_>, items: &[Self])
+        -> Result<(), snafu::Whatever>;
+
+    /// Read all rows of `Self`'s table, ordered by primary key.
+    fn read_all<'a>(
+        connection: Self::Connection<'a>,
+    ) -> Result<Box<dyn Iterator<Item = Result<Self, snafu::Whatever>> + 'a>, snafu::Whatever>;
+
+    /// Read all rows of `Self`'s table into a `Vec`, ordered by primary key.
+    ///
+    /// Fails on the first row that can't be converted.
+    fn read_all_vec(connection: Self::Connection<'_>) -> Result<Vec<Self>, snafu::Whatever> {
+        Self::read_all(connection)?.collect()
+    }
+
+    /// Read the rows of `Self`'s table for which `predicate` is true, in the
+    /// order of [`Crud::read_all`].
+    ///
+    /// For filters that can't be written as a query. Rows are read and
+    /// tested lazily as the iterator is advanced, and rows that fail to
+    /// convert are yielded as errors without calling `predicate`.
+    fn read_filtered<'a>(
+        connection: Self::Connection<'a>,
+        predicate: impl Fn(&Self) -> bool + 'a,
+    ) -> Result<Box<dyn Iterator<Item = Result<Self, snafu::Whatever>> + 'a>, snafu::Whatever> {
+        let rows = Self::read_all(connection)?;
+        Ok(Box::new(rows.filter(move |row| match row {
+            Ok(row) => predicate(row),
+            Err(_) => true,
+        })))
+    }
+
+    /// Read all rows of `Self`'s table, deserializing them across `threads`
+    /// threads.
+    ///
+    /// Useful when `try_from_crud_fields` is expensive. Rows are returned in
+    /// the same order as [`Crud::read_all`].
+    fn read_all_parallel(
+        connection: Self::Connection<'_>,
+        threads: usize,
+    ) -> Result<Vec<Self>, snafu::Whatever>
+    where
+        Self: Send;
+
+    /// Count the rows of `Self`'s table.
+    fn count(connection: Self::Connection<'_>) -> Result<usize, snafu::Whatever>;
+
+    /// Read at most `limit` rows, skipping the first `offset`.
+    fn read_page_typed(
+        connection: Self::Connection<'_>,
+        limit: usize,
+        offset: usize,
+    ) -> Result<Page<Self>, snafu::Whatever>;
+
+    /// Read all rows of `Self`'s table, newest first.
+    ///
+    /// Errors on backends with no order to reverse, like DynamoDB.
+    fn read_all_rev<'a>(
+        connection: Self::Connection<'a>,
+    ) -> Result<Box<dyn Iterator<Item = Result<Self, snafu::Whatever>> + 'a>, snafu::Whatever> {
+        let _ = connection;
+        snafu::whatever!(
+            "can't read `{}` newest first, this backend doesn't keep the insertion order",
+            Self::table_name()
+        )
+    }
+
+    fn read_where<'a>(
+        connection: Self::Connection<'a>,
+        key_name: &'a str,
+        comparison: &'a str,
+        key_value: impl IsCrudField,
+    ) -> Result<Box<dyn Iterator<Item = Result<Self, snafu::Whatever>> + 'a>, snafu::Whatever>;
+
+    /// Read the rows where `column` is `LIKE` `pattern`.
+    ///
+    /// Errors on backends without `LIKE`, like DynamoDB.
+    fn read_like<'a>(
+        connection: Self::Connection<'a>,
+        column: &str,
+        pattern: LikePattern,
+    ) -> Result<Box<dyn Iterator<Item = Result<Self, snafu::Whatever>> + 'a>, snafu::Whatever> {
+        let _ = (connection, pattern);
+        snafu::whatever!(
+            "can't match `{column}` of `{}` with LIKE, this backend has no LIKE",
+            Self::table_name()
+        )
+    }
+
+    /// Read the rows where `column` is `IN` `values`.
+    ///
+    /// Each value is bound as a parameter. No values reads no rows, without
+    /// querying.
+    fn read_in<'a, Key: IsCrudField>(
+        connection: Self::Connection<'a>,
+        column: &str,
+        values: Vec<Key>,
+    ) -> Result<Box<dyn Iterator<Item = Result<Self, snafu::Whatever>> + 'a>, snafu::Whatever>;
+
+    /// Read the rows where `left` compares to `right`, both columns of
+    /// `Self`'s table, eg. rows where `low <= high`.
+    ///
+    /// Errors on backends that can't compare two columns, like DynamoDB.
+    fn read_column_cmp<'a>(
+        connection: Self::Connection<'a>,
+        left: &str,
+        comparison: &str,
+        right: &str,
+    ) -> Result<Box<dyn Iterator<Item = Result<Self, snafu::Whatever>> + 'a>, snafu::Whatever> {
+        let _ = connection;
+        snafu::whatever!(
+            "can't compare `{left}` {comparison} `{right}` of `{}`, this backend can't compare columns",
+            Self::table_name()
+        )
+    }
+
+    /// Count the rows of `Self`'s table grouped by the values of `column`.
+    ///
+    /// Returns each distinct value of `column` along with its number of rows.
+    /// Errors on backends without `GROUP BY`, like DynamoDB.
+    fn aggregate(
+        connection: Self::Connection<'_>,
+        column: &str,
+    ) -> Result<Vec<(Value, i64)>, snafu::Whatever> {
+        let _ = connection;
+        snafu::whatever!(
+            "can't group `{}` by `{column}`, this backend has no GROUP BY",
+            Self::table_name()
+        )
+    }
+
+    /// Read only `columns` of every row of `Self`'s table, ordered by primary
+    /// key.
+    ///
+    /// Rows are returned as raw values, since a partial row can't be turned
+    /// back into `Self`.
+    fn read_projected<'a>(
+        connection: Self::Connection<'_>,
+        columns: &[&'a str],
+    ) -> Result<Vec<FieldMap<'a>>, snafu::Whatever>;
+
+    /// Read the row with the primary key `key`.
+    ///
+    /// `key` has the type of the primary key field, so keys of another type
+    /// don't compile rather than matching nothing.
+    fn read<'a>(
+        connection: Self::Connection<'a>,
+        key: Self::PrimaryKey,
+    ) -> Result<Box<dyn Iterator<Item = Result<Self, snafu::Whatever>> + 'a>, snafu::Whatever>;
+
+    /// Read the rows with any of the given primary keys.
+    ///
+    /// Like [`Crud::read`], keys of another type don't compile.
+    fn read_by_keys(
+        connection: Self::Connection<'_>,
+        keys: Vec<Self::PrimaryKey>,
+    ) -> Result<Vec<Self>, snafu::Whatever>;
+
+    fn update(&self, connection: Self::Connection<'_>) -> Result<(), snafu::Whatever>;
+
+    /// Update only the columns that differ from `old`, the previously stored
+    /// version of this row.
+    ///
+    /// Returns the number of columns updated. Zero means nothing was written.
+    fn update_diff_count(
+        &self,
+        old: &Self,
+        connection: Self::Connection<'_>,
+    ) -> Result<usize, snafu::Whatever>;
+
+    /// Update only the columns that differ from `old`, writing nothing if
+    /// they're equal.
+    ///
+    /// Unlike [`Crud::update`] this leaves unchanged columns alone, so
+    /// triggers on them don't fire. The primary key is never written.
+    fn update_diff(
+        &self,
+        old: &Self,
+        connection: Self::Connection<'_>,
+    ) -> Result<(), snafu::Whatever> {
+        self.update_diff_count(old, connection).map(|_| ())
+    }
+
+    /// Set `set_col` to `set_val` in the rows where `key_name` compares to
+    /// `key_value`, without reading them first.
+    ///
+    /// Returns the number of rows updated.
+    fn update_where(
+        connection: Self::Connection<'_>,
+        set_col: &str,
+        set_val: impl IsCrudField,
+        key_name: &str,
+        comparison: Comparison,
+        key_value: impl IsCrudField,
+    ) -> Result<u64, snafu::Whatever>;
+
+    /// Delete the row with this row's primary key.
+    ///
+    /// Returns whether there was a row to delete.
+    fn delete(self, connection: Self::Connection<'_>) -> Result<bool, snafu::Whatever>;
+
+    /// Delete the rows matching `query`, returning how many were deleted.
+    ///
+    /// Errors if `query` has no conditions, use [`Crud::clear`] to delete
+    /// every row.
+    fn delete_where(
+        connection: Self::Connection<'_>,
+        query: &Query<Self>,
+    ) -> Result<usize, snafu::Whatever>;
+
+    /// Delete the rows matching all `conditions`, returning how many were
+    /// deleted.
+    ///
+    /// See [`Crud::delete_where`].
+    fn delete_all_where(
+        connection: Self::Connection<'_>,
+        conditions: &[Condition],
+    ) -> Result<usize, snafu::Whatever> {
+        let query = conditions.iter().cloned().collect::<Query<Self>>();
+        Self::delete_where(connection, &query)
+    }
+
+    /// Delete every row of `Self`'s table, returning how many were deleted.
+    fn clear(connection: Self::Connection<'_>) -> Result<usize, snafu::Whatever>;
+
+    /// Run `f`, which writes through `connection`, as one unit: if it errors
+    /// none of its writes are kept.
+    ///
+    /// Backends without transactions, like DynamoDB, just run `f`.
+    fn atomically<R>(
+        connection: Self::Connection<'_>,
+        f: impl FnOnce() -> Result<R, snafu::Whatever>,
+    ) -> Result<R, snafu::Whatever> {
+        let _ = connection;
+        f()
+    }
+
+    /// Read the history of an `#[audited]` table, oldest first.
+    ///
+    /// Errors on backends without audit triggers, like DynamoDB.
+    fn read_history(
+        connection: Self::Connection<'_>,
+    ) -> Result<Vec<History<Self>>, snafu::Whatever> {
+        let _ = connection;
+        snafu::whatever!(
+            "table `{}` is not audited on this backend",
+            Self::table_name()
+        )
+    }
+
+    fn migration<T: HasCrudFields + 'static>() -> Migration
+    where
+        Self: From<T>,
+    {
+        Migration::new::<T, Self>()
+    }
+
+    /// Write every row of `Self`'s table to `writer` as CSV, under a header of
+    /// [`HasCrudFields::field_names`].
+    ///
+    /// Bytes are base64 encoded and NULLs are empty cells. Returns the number
+    /// of rows written.
+    #[cfg(feature = "csv")]
+    fn export_csv<W: std::io::Write>(
+        connection: Self::Connection<'_>,
+        writer: W,
+    ) -> Result<usize, snafu::Whatever> {
+        let mut writer = ::csv::Writer::from_writer(writer);
+        writer
+            .write_record(Self::field_names())
+            .whatever_context("could not write csv header")?;
+        let mut rows = 0;
+        for row in Self::read_all(connection)? {
+            let cells = row?
+                .field_values()
+                .iter()
+                .map(|(_, value)| crate::csv::value_to_csv(value))
+                .collect::<Vec<_>>();
+            writer
+                .write_record(cells)
+                .whatever_context("could not write csv row")?;
+            rows += 1;
+        }
+        writer.flush().whatever_context("could not flush csv")?;
+        Ok(rows)
+    }
+
+    /// Read CSV written by [`Crud::export_csv`] and insert its rows with
+    /// [`Crud::insert_many`].
+    ///
+    /// Columns are found by the header, so they may be in any order. Empty
+    /// cells are NULL, except in non-nullable string and bytes columns where
+    /// they're empty. Returns the number of rows inserted.
+    #[cfg(feature = "csv")]
+    fn import_csv<R: std::io::Read>(
+        connection: Self::Connection<'_>,
+        reader: R,
+    ) -> Result<usize, snafu::Whatever> {
+        let mut reader = ::csv::Reader::from_reader(reader);
+        let headers = reader
+            .headers()
+            .whatever_context("could not read csv header")?
+            .clone();
+        let fields = Self::crud_fields();
+        let columns = fields
+            .iter()
+            .map(|field| {
+                let index = headers
+                    .iter()
+                    .position(|header| header == field.name)
+                    .whatever_context(format!("csv has no column `{}`", field.name))?;
+                Ok((field, index))
+            })
+            .collect::<Result<Vec<_>, snafu::Whatever>>()?;
+        let mut items = vec![];
+        for (i, record) in reader.records().enumerate() {
+            let record = record.whatever_context(format!("could not read csv row {i}"))?;
+            let values = columns
+                .iter()
+                .map(|(field, index)| {
+                    let cell = record.get(*index).unwrap_or_default();
+                    let value = crate::csv::value_from_csv(cell, field)
+                        .whatever_context(format!("csv row {i}, column `{}`", field.name))?;
+                    Ok((field.name, value))
+                })
+                .collect::<Result<FieldMap, snafu::Whatever>>()?;
+            items.push(Self::try_from_crud_fields(&values)?);
+        }
+        Self::insert_many(connection, &items)?;
+        Ok(items.len())
+    }
+
+    /// Every row of `Self`'s table as a JSON array of objects keyed by column
+    /// name.
+    ///
+    /// Bytes are base64 encoded and NULLs are `null`.
+    #[cfg(feature = "json")]
+    fn export_json(connection: Self::Connection<'_>) -> Result<serde_json::Value, snafu::Whatever> {
+        let mut rows = vec![];
+        for row in Self::read_all(connection)? {
+            let object = row?
+                .field_values()
+                .iter()
+                .map(|(field, value)| (field.name.to_string(), crate::json::value_to_json(value)))
+                .collect::<serde_json::Map<_, _>>();
+            rows.push(serde_json::Value::Object(object));
+        }
+        Ok(serde_json::Value::Array(rows))
+    }
+
+    /// Insert the rows of a JSON array written by [`Crud::export_json`] with
+    /// [`Crud::insert_many`].
+    ///
+    /// Missing keys are NULL. Returns the number of rows inserted.
+    #[cfg(feature = "json")]
+    fn import_json(
+        connection: Self::Connection<'_>,
+        json: &serde_json::Value,
+    ) -> Result<usize, snafu::Whatever> {
+        let rows = json.as_array().whatever_context("json is not an array")?;
+        let fields = Self::crud_fields();
+        let mut items = vec![];
+        for (i, row) in rows.iter().enumerate() {
+            let object = row
+                .as_object()
+                .whatever_context(format!("json row {i} is not an object"))?;
+            let values = fields
+                .iter()
+                .map(|field| {
+                    let json = object.get(field.name).unwrap_or(&serde_json::Value::Null);
+                    let value = crate::json::value_from_json(json, field)
+                        .whatever_context(format!("json row {i}, column `{}`", field.name))?;
+                    Ok((field.name, value))
+                })
+                .collect::<Result<FieldMap, snafu::Whatever>>()?;
+            items.push(Self::try_from_crud_fields(&values)?);
+        }
+        Self::insert_many(connection, &items)?;
+        Ok(items.len())
+    }
+}
+
+/// The async version of [`Crud`], for backends that shouldn't block the
+/// executor.
+///
+/// Reads return `Vec`s instead of iterators, as rows can't borrow from the
+/// connection across an `.await`.
+pub trait AsyncCrud<Backend>: HasCrudFields + Clone + Sized + 'static {
+    type Connection;
+
+    /// See [`Crud::create`].
+    fn create(
+        connection: &Self::Connection,
+    ) -> impl Future<Output = Result<(), snafu::Whatever>> + Send;
+
+    /// See [`Crud::validate_schema`].
+    fn validate_schema(
+        connection: &Self::Connection,
+    ) -> impl Future<Output = Result<(), snafu::Whatever>> + Send;
+
+    fn insert(
+        &self,
+        connection: &Self::Connection,
+    ) -> impl Future<Output = Result<(), snafu::Whatever>> + Send;
+
+    /// See [`Crud::insert_or_ignore`].
+    fn insert_or_ignore(
+        &self,
+        connection: &Self::Connection,
+    ) -> impl Future<Output = Result<bool, snafu::Whatever>> + Send;
+
+    /// See [`Crud::insert_many`].
+    fn insert_many(
+        connection: &Self::Connection,
+        items: Vec<Self>,
+    ) -> impl Future<Output = Result<(), snafu::Whatever>> + Send;
+
+    /// See [`Crud::read_all`].
+    fn read_all(
+        connection: &Self::Connection,
+    ) -> impl Future<Output = Result<Vec<Self>, snafu::Whatever>> + Send;
+
+    /// See [`Crud::count`].
+    fn count(
+        connection: &Self::Connection,
+    ) -> impl Future<Output = Result<usize, snafu::Whatever>> + Send;
+
+    /// See [`Crud::read_page_typed`].
+    fn read_page_typed(
+        connection: &Self::Connection,
+        limit: usize,
+        offset: usize,
+    ) -> impl Future<Output = Result<Page<Self>, snafu::Whatever>> + Send;
+
+    /// See [`Crud::read_where`].
+    fn read_where<Key: IsCrudField + Send + 'static>(
+        connection: &Self::Connection,
+        key_name: &str,
+        comparison: &str,
+        key_value: Key,
+    ) -> impl Future<Output = Result<Vec<Self>, snafu::Whatever>> + Send;
+
+    /// See [`Crud::read`].
+    fn read(
+        connection: &Self::Connection,
+        key: Self::PrimaryKey,
+    ) -> impl Future<Output = Result<Vec<Self>, snafu::Whatever>> + Send;
+
+    /// See [`Crud::read_by_keys`].
+    fn read_by_keys(
+        connection: &Self::Connection,
+        keys: Vec<Self::PrimaryKey>,
+    ) -> impl Future<Output = Result<Vec<Self>, snafu::Whatever>> + Send;
+
+    fn update(
+        &self,
+        connection: &Self::Connection,
+    ) -> impl Future<Output = Result<(), snafu::Whatever>> + Send;
+
+    /// See [`Crud::delete`].
+    fn delete(
+        self,
+        connection: &Self::Connection,
+    ) -> impl Future<Output = Result<bool, snafu::Whatever>> + Send;
+
+    /// See [`Crud::delete_where`].
+    fn delete_where(
+        connection: &Self::Connection,
+        query: &Query<Self>,
+    ) -> impl Future<Output = Result<usize, snafu::Whatever>> + Send;
+
+    /// See [`Crud::clear`].
+    fn clear(
+        connection: &Self::Connection,
+    ) -> impl Future<Output = Result<usize, snafu::Whatever>> + Send;
+}
+
+pub trait MigrateEntireTable {
+    type Connection<'a>: Copy;
+
+    #[allow(clippy::type_complexity)]
+    fn read_all_values<'a>(
+        connection: Self::Connection<'a>,
+        table_name: &'a str,
+        column_names: Vec<&'a str>,
+    ) -> Result<Vec<Result<FieldMap<'a>, snafu::Whatever>>, snafu::Whatever>;
+
+    fn insert_fields(
+        connection: Self::Connection<'_>,
+        table_name: &str,
+        fields: &FieldMap<'_>,
+    ) -> Result<(), snafu::Whatever>;
+
+    fn delete_all(
+        connection: Self::Connection<'_>,
+        table_name: &str,
+    ) -> Result<(), snafu::Whatever>;
+
+    fn table_exists(
+        connection: Self::Connection<'_>,
+        table_name: &str,
+    ) -> Result<bool, snafu::Whatever>;
+
+    /// Create `table_name` with the columns of `fields`, unless it exists.
+    fn create_table(
+        connection: Self::Connection<'_>,
+        table_name: &str,
+        fields: &[CrudField],
+    ) -> Result<(), snafu::Whatever>;
+}
+
+/// Marks `Self` as the version that follows `Prev` in a migration chain.
+///
+/// [`Migrations::with_version`] only accepts declared steps, eg.
+/// `impl MigrationStep<PlayerV1> for PlayerV2 {}`, so a stray `From` impl
+/// can't put versions in the wrong order.
+///
+/// Migrating backwards is a separate chain with its own declared steps.
+pub trait MigrationStep<Prev>: From<Prev> {}
+
+/// A step registered with a [`MigrationRegistry`].
+struct RegisteredStep {
+    prev: core::any::TypeId,
+    next: core::any::TypeId,
+    prev_name: &'static str,
+    first: fn() -> Migration,
+    step: fn() -> Migration,
+}
+
+/// Collects migration steps from many places, eg. plugins that each
+/// contribute their own tables.
+///
+/// Use [`Migrations::from_registry`] to assemble the chain for a type.
+#[derive(Default)]
+pub struct MigrationRegistry {
+    steps: Vec<RegisteredStep>,
+}
+
+impl MigrationRegistry {
+    /// Register the step from `Prev` to `Next`.
+    pub fn register<Prev, Next>(&mut self) -> &mut Self
+    where
+        Prev: HasCrudFields + 'static,
+        Next: MigrationStep<Prev> + HasCrudFields + 'static,
+    {
+        self.steps.push(RegisteredStep {
+            prev: core::any::TypeId::of::<Prev>(),
+            next: core::any::TypeId::of::<Next>(),
+            prev_name: core::any::type_name::<Prev>(),
+            first: Migration::new::<Prev, Prev>,
+            step: Migration::new::<Prev, Next>,
+        });
+        self
+    }
+}
+
+pub struct Migrations<T, Backend> {
+    _current: PhantomData<(T, Backend)>,
+    all: VecDeque<Migration>,
+    deny_lossy: bool,
+}
+
+impl<T, Backend> Clone for Migrations<T, Backend> {
+    fn clone(&self) -> Self {
+        Self {
+            _current: PhantomData,
+            all: self.all.clone(),
+            deny_lossy: self.deny_lossy,
+        }
+    }
+}
+
+impl<T, Backend> core::fmt::Debug for Migrations<T, Backend> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Migrations")
+            .field("current", &core::any::type_name::<T>())
+            .field("all", &self.all)
+            .field("deny_lossy", &self.deny_lossy)
+            .finish()
+    }
+}
+
+impl<T: HasCrudFields + Clone + Sized + 'static, Backend: MigrateEntireTable>
+    Migrations<T, Backend>
+{
+    #[allow(clippy::should_implement_trait)]
+    pub fn default() -> Self {
+        Self {
+            _current: PhantomData,
+            all: VecDeque::from([Migration::new::<T, T>()]),
+            deny_lossy: false,
+        }
+    }
+
+    /// Assemble the chain of registered steps that ends at `T`.
+    ///
+    /// The chain starts at the earliest version that no registered step
+    /// leads to. If no step leads to `T` this is the same as
+    /// [`Migrations::default`].
+    pub fn from_registry(registry: &MigrationRegistry) -> Result<Self, snafu::Whatever> {
+        let mut current = core::any::TypeId::of::<T>();
+        let mut current_name = core::any::type_name::<T>();
+        let mut visited = vec![current];
+        let mut steps = VecDeque::new();
+        loop {
+            let mut leading_here = registry.steps.iter().filter(|step| step.next == current);
+            let Some(step) = leading_here.next() else {
+                break;
+            };
+            snafu::ensure_whatever!(
+                leading_here.next().is_none(),
+                "more than one registered step leads to {current_name}"
+            );
+            snafu::ensure_whatever!(
+                !visited.contains(&step.prev),
+                "registered steps to {current_name} form a cycle"
+            );
+            visited.push(step.prev);
+            steps.push_front(step);
+            current = step.prev;
+            current_name = step.prev_name;
+        }
+        let all = match steps.front() {
+            None => return Ok(Self::default()),
+            Some(first) => std::iter::once((first.first)())
+                .chain(steps.iter().map(|step| (step.step)()))
+                .collect(),
+        };
+        Ok(Self {
+            _current: PhantomData,
+            all,
+            deny_lossy: false,
+        })
+    }
+}
+
+impl<T: HasCrudFields + Clone + Sized + 'static, Backend: MigrateEntireTable>
+    Migrations<T, Backend>
+{
+    pub fn with_version<Next>(self) -> Migrations<Next, Backend>
+    where
+        Next: MigrationStep<T> + HasCrudFields + Clone + Sized + 'static,
+    {
+        let Self {
+            _current: _,
+            mut all,
+            deny_lossy,
+        } = self;
+        all.push_back(Migration::new::<T, Next>());
+        Migrations {
+            _current: PhantomData,
+            all,
+            deny_lossy,
+        }
+    }
+
+    /// Like [`Migrations::with_version`], but rows are converted with `f`
+    /// instead of `From`, for steps that can fail or need more than the row.
+    ///
+    /// `f` is given each row as a boxed `T` and must return a boxed `Next`.
+    /// Its errors stop the migration.
+    pub fn with_version_fn<Next>(
+        self,
+        f: impl Fn(Box<dyn core::any::Any>) -> Result<Box<dyn core::any::Any>, snafu::Whatever>
+            + Send
+            + Sync
+            + 'static,
+    ) -> Migrations<Next, Backend>
+    where
+        Next: HasCrudFields + Clone + Sized + 'static,
+    {
+        let Self {
+            _current: _,
+            mut all,
+            deny_lossy,
+        } = self;
+        all.push_back(Migration::from_fn::<T, Next>(f));
+        Migrations {
+            _current: PhantomData,
+            all,
+            deny_lossy,
+        }
+    }
+
+    /// Like [`Migrations::with_version`], but the step can also be undone by
+    /// [`Migrations::rollback_to`].
+    ///
+    /// This requires the reverse `From` impl, eg. `impl From<PlayerV2> for
+    /// PlayerV1`.
+    pub fn with_reversible_version<Next>(self) -> Migrations<Next, Backend>
+    where
+        Next: MigrationStep<T> + HasCrudFields + Clone + Sized + 'static,
+        T: From<Next>,
+    {
+        let Self {
+            _current: _,
+            mut all,
+            deny_lossy,
+        } = self;
+        all.push_back(Migration::reversible::<T, Next>());
+        Migrations {
+            _current: PhantomData,
+            all,
+            deny_lossy,
+        }
+    }
+
+    /// Number of versions in the chain, including the first.
+    pub fn len(&self) -> usize {
+        self.all.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.all.is_empty()
+    }
+
+    /// Table names of each version in the chain, from first to current.
+    pub fn table_names(&self) -> Vec<&'static str> {
+        self.all
+            .iter()
+            .map(|migration| (migration.table_name)())
+            .collect()
+    }
+
+    /// Refuse to run if any step drops columns, eg. when migrating backwards.
+    ///
+    /// By default lossy steps only log a warning.
+    pub fn deny_lossy(mut self) -> Self {
+        self.deny_lossy = true;
+        self
+    }
+
+    /// Check that the table of every version but the current one exists,
+    /// before [`Migrations::run`] reads them.
+    ///
+    /// The current version's table is created by `run` when it's missing.
+    /// The types of the chain are already checked when it's built. Errors
+    /// name each missing table.
+    pub fn validate(&self, connection: Backend::Connection<'_>) -> Result<(), snafu::Whatever> {
+        self.validate_with(|_| connection)
+    }
+
+    /// Like [`Migrations::validate`], with the connection of each table as
+    /// for [`Migrations::run_with`].
+    pub fn validate_with<'a>(
+        &self,
+        mk_connection: impl Fn(&str) -> Backend::Connection<'a>,
+    ) -> Result<(), snafu::Whatever> {
+        let Some(current) = self.all.back() else {
+            return Ok(());
+        };
+        let current_table_name = (current.table_name)();
+        let mut missing: Vec<&str> = vec![];
+        for migration in self.all.iter() {
+            let table_name = (migration.table_name)();
+            if table_name == current_table_name || missing.contains(&table_name) {
+                continue;
+            }
+            if !Backend::table_exists((mk_connection)(table_name), table_name)? {
+                missing.push(table_name);
+            }
+        }
+        snafu::ensure_whatever!(
+            missing.is_empty(),
+            "can't migrate to `{current_table_name}`, tables of earlier versions are missing: {}",
+            missing
+                .iter()
+                .map(|table_name| format!("`{table_name}`"))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        Ok(())
+    }
+
+    pub fn run<'a>(self, connection: Backend::Connection<'a>) -> Result<(), snafu::Whatever> {
+        self.run_with(|_| connection)
+    }
+
+    pub fn run_with<'a>(
+        self,
+        mk_connection: impl Fn(&str) -> Backend::Connection<'a>,
+    ) -> Result<(), snafu::Whatever> {
+        let Self {
+            _current,
+            mut all,
+            deny_lossy,
+        } = self;
+        log::info!(
+            "migrating {} versions of {:?}",
+            all.len(),
+            core::any::type_name::<T>()
+        );
+        for migration in all.iter().filter(|m| m.is_lossy()) {
+            let table_name = (migration.table_name)();
+            let dropped = migration.dropped_columns.join(", ");
+            if deny_lossy {
+                snafu::whatever!("migrating to {table_name} would discard columns {dropped}");
+            }
+            log::warn!("  migrating to {table_name} discards columns {dropped}");
+        }
+        // Every row ends up in the last version's table, which is never read
+        // or cleared, even when an earlier version shares its name.
+        let Some(current) = all.back() else {
+            return Ok(());
+        };
+        let current_table_name = (current.table_name)();
+        let current_connection = (mk_connection)(current_table_name);
+        if !Backend::table_exists(current_connection, current_table_name)? {
+            log::info!("  creating {current_table_name}");
+            Backend::create_table(
+                current_connection,
+                current_table_name,
+                (current.crud_fields)(),
+            )?;
+        }
+        while all.len() > 1 {
+            let migration = all.pop_front().expect("more than one migration");
+            let prev_table_name = (migration.table_name)();
+            if prev_table_name == current_table_name {
+                log::info!("  skipping {prev_table_name}, it is the current table");
+                continue;
+            }
+            log::info!("  checking {prev_table_name}");
+            #[cfg(feature = "tracing")]
+            let span = tracing::info_span!(
+                "migrate_table",
+                table = prev_table_name,
+                entries = tracing::field::Empty
+            );
+            #[cfg(feature = "tracing")]
+            let _entered = span.enter();
+            let fields = (migration.crud_fields)();
+            let column_names = fields.iter().map(|f| f.name).collect::<Vec<_>>();
+            // Get a cursor of each value in the prev table
+            let cursor = Backend::read_all_values(
+                (mk_connection)(prev_table_name),
+                prev_table_name,
+                column_names,
+            )?;
+            let mut entries = 0;
+            for res_prev in cursor {
+                entries += 1;
+                let values = res_prev?;
+                // Serialize to the prev type
+                let mut prev = (migration.try_from_crud_fields)(&values)?;
+                let mut last_migration = &migration;
+                // Move the type forward with From, from the prev to the most
+                // current
+                for target in all.iter() {
+                    let target_table_name = (target.table_name)();
+                    prev = (target.from_prev)(prev).whatever_context(format!(
+                        "could not convert a row of {prev_table_name} to {target_table_name}"
+                    ))?;
+                    snafu::ensure_whatever!(
+                        (*prev).type_id() == target.type_id,
+                        "the step to {target_table_name} returned a row of another type"
+                    );
+                    last_migration = target;
+                }
+                // Now prev is the most current type, save it in the most
+                // current table.
+                let current = prev;
+                let fields = (last_migration.as_crud_fields)(&current);
+                Backend::insert_fields(
+                    (mk_connection)(current_table_name),
+                    current_table_name,
+                    &fields,
+                )?;
+            }
+            log::info!("    migrated {entries} entries from {prev_table_name}",);
+            #[cfg(feature = "tracing")]
+            {
+                span.record("entries", entries);
+                tracing::info!(entries, "migrated");
+            }
+            // Remove the old entries
+            log::info!("    clearing out previous table {prev_table_name}");
+            let conn = (mk_connection)(prev_table_name);
+            Backend::delete_all(conn, prev_table_name)?;
+        }
+        Ok(())
+    }
+
+    pub fn rollback_to<'a, Target: 'static>(
+        self,
+        connection: Backend::Connection<'a>,
+    ) -> Result<(), snafu::Whatever> {
+        self.rollback_to_with::<Target>(|_| connection)
+    }
+
+    /// Move the rows of the current version's table back into `Target`'s
+    /// table, converting them with the reverse `From` impls of each step in
+    /// between.
+    ///
+    /// Every step after `Target` must have been added with
+    /// [`Migrations::with_reversible_version`]. The tables of `Target` and of
+    /// the versions before it are otherwise left as they are.
+    pub fn rollback_to_with<'a, Target: 'static>(
+        self,
+        mk_connection: impl Fn(&str) -> Backend::Connection<'a>,
+    ) -> Result<(), snafu::Whatever> {
+        let Self {
+            _current,
+            all,
+            deny_lossy,
+        } = self;
+        let target_name = core::any::type_name::<Target>();
+        let target_id = core::any::TypeId::of::<Target>();
+        let target = all
+            .iter()
+            .take(all.len().saturating_sub(1))
+            .rposition(|migration| migration.type_id == target_id)
+            .whatever_context(format!(
+                "{target_name} is not an earlier version of {}",
+                core::any::type_name::<T>()
+            ))?;
+        for (prev, migration) in all.iter().zip(all.iter().skip(1)).skip(target) {
+            let table_name = (migration.table_name)();
+            let prev_table_name = (prev.table_name)();
+            snafu::ensure_whatever!(
+                migration.into_prev.is_some(),
+                "the step from {prev_table_name} to {table_name} can't be rolled back, \
+                 add it with `with_reversible_version`"
+            );
+            let prev_fields = (prev.crud_fields)();
+            let dropped = (migration.crud_fields)()
+                .iter()
+                .map(|f| f.name)
+                .filter(|name| !prev_fields.iter().any(|f| f.name == *name))
+                .collect::<Vec<_>>();
+            if !dropped.is_empty() {
+                let dropped = dropped.join(", ");
+                if deny_lossy {
+                    snafu::whatever!(
+                        "rolling back to {prev_table_name} would discard columns {dropped}"
+                    );
+                }
+                log::warn!("  rolling back to {prev_table_name} discards columns {dropped}");
+            }
+        }
+
+        let current = all
+            .back()
+            .expect("the chain has a version after the target");
+        let current_table_name = (current.table_name)();
+        let target_migration = &all[target];
+        let target_table_name = (target_migration.table_name)();
+        log::info!("rolling back {current_table_name} to {target_table_name}");
+        let column_names = (current.crud_fields)()
+            .iter()
+            .map(|f| f.name)
+            .collect::<Vec<_>>();
+        // Convert every row before writing anything, so a failed conversion
+        // leaves both tables untouched
+        let rows = Backend::read_all_values(
+            (mk_connection)(current_table_name),
+            current_table_name,
+            column_names,
+        )?
+        .into_iter()
+        .map(|values| {
+            let mut row = (current.try_from_crud_fields)(&values?)?;
+            for migration in all.iter().skip(target + 1).rev() {
+                let into_prev = migration.into_prev.expect("checked above");
+                row = into_prev(row);
+            }
+            Ok(row)
+        })
+        .collect::<Result<Vec<_>, snafu::Whatever>>()?;
+        let same_table = current_table_name == target_table_name;
+        if same_table {
+            Backend::delete_all((mk_connection)(current_table_name), current_table_name)?;
+        }
+        for row in rows.iter() {
+            let fields = (target_migration.as_crud_fields)(row);
+            Backend::insert_fields(
+                (mk_connection)(target_table_name),
+                target_table_name,
+                &fields,
+            )?;
+        }
+        if !same_table {
+            Backend::delete_all((mk_connection)(current_table_name), current_table_name)?;
+        }
+        log::info!(
+            "  rolled back {} entries into {target_table_name}",
+            rows.len()
+        );
+        Ok(())
+    }
+}
+
+/// Migrate the row of `Old` with the primary key `key` into `New`'s table.
+///
+/// This migrates lazily, eg. as rows are read, instead of running
+/// [`Migrations`] over the entire table upfront. The row is converted with
+/// `New::from` like a [`Migration`] step, inserted as `New` and then deleted
+/// from `Old`'s table. Other rows are left alone.
+///
+/// Returns the migrated row, or `None` if `Old`'s table has no row with
+/// `key`. Errors if both versions share a table, or if `Old` is
+/// `#[append_only]` and so can't give up its row.
+///
+/// The insert and delete run through [`Crud::atomically`], so on backends
+/// with transactions a failed delete doesn't leave the row in both tables.
+pub fn migrate_row<'a, Backend, Old, New>(
+    connection: <Old as Crud<Backend>>::Connection<'a>,
+    key: Old::PrimaryKey,
+) -> Result<Option<New>, snafu::Whatever>
+where
+    Old: Crud<Backend>,
+    New:
+        Crud<Backend, Connection<'a> = <Old as Crud<Backend>>::Connection<'a>> + MigrationStep<Old>,
+    <Old as Crud<Backend>>::Connection<'a>: Copy,
+{
+    snafu::ensure_whatever!(
+        Old::table_name() != New::table_name(),
+        "can't migrate a row of `{}` into the same table",
+        Old::table_name()
+    );
+    snafu::ensure_whatever!(
+        !Old::append_only(),
+        "can't migrate a row out of `{}`, it is an append-only table",
+        Old::table_name()
+    );
+    let Some(old) = Old::read(connection, key)?.next().transpose()? else {
+        return Ok(None);
+    };
+    let new = New::from(old.clone());
+    Old::atomically(connection, || {
+        new.insert(connection)?;
+        old.delete(connection)?;
+        Ok(())
+    })?;
+    Ok(Some(new))
+}
+
+#[cfg(all(test, feature = "backend_sqlite"))]
+mod test {
+    use snafu::prelude::*;
+
+    use crate::{
+        self as tymigrawr, migrate_row, scoped, AuditOp, Comparison, Condition, Crud, CrudEnum,
+        CrudField, CrudNewtype, FieldMap, HasCrudFields, HexBytes, IsCrudField, LikePattern,
+        MigrateEntireTable, MigrationRegistry, MigrationStep, Migrations, Page, Query, Select,
+        SqlDialect, Sqlite, SqliteDialect, Transaction, Value, ValueRef, ValueType,
+    };
+
+    #[derive(Debug, Clone, PartialEq, HasCrudFields)]
+    pub struct PlayerV1 {
+        #[primary_key]
+        pub id: i64,
+        pub name: String,
+    }
+
+    impl From<PlayerV2> for PlayerV1 {
+        fn from(value: PlayerV2) -> PlayerV1 {
+            PlayerV1 {
+                id: value.id,
+                name: value.name,
+            }
+        }
+    }
+
+    impl From<PlayerV1> for PlayerV2 {
+        fn from(value: PlayerV1) -> PlayerV2 {
+            PlayerV2 {
+                id: value.id,
+                name: value.name,
+                age: 0.0,
+            }
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq, HasCrudFields)]
+    pub struct PlayerV2 {
+        #[primary_key]
+        pub id: i64,
+        pub name: String,
+        pub age: f32,
+    }
+
+    #[test]
+    fn migrate_one_row() {
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+        <PlayerV1 as Crud<Sqlite>>::create(&connection).unwrap();
+        <PlayerV2 as Crud<Sqlite>>::create(&connection).unwrap();
+        let players = (0..3)
+            .map(|id| PlayerV1 {
+                id,
+                name: format!("tymigrawr_{id}"),
+            })
+            .collect::<Vec<_>>();
+        <PlayerV1 as Crud<Sqlite>>::insert_many(&connection, &players).unwrap();
+
+        let migrated = migrate_row::<Sqlite, PlayerV1, PlayerV2>(&connection, 1)
+            .unwrap()
+            .unwrap();
+        assert_eq!(PlayerV2::from(players[1].clone()), migrated);
+        let v2 = <PlayerV2 as Crud<Sqlite>>::read_all(&connection)
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(vec![migrated], v2);
+        let v1 = <PlayerV1 as Crud<Sqlite>>::read_all(&connection)
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(vec![players[0].clone(), players[2].clone()], v1);
+
+        // already migrated
+        assert_eq!(
+            None,
+            migrate_row::<Sqlite, PlayerV1, PlayerV2>(&connection, 1).unwrap()
+        );
+        assert!(migrate_row::<Sqlite, PlayerV2, same_table::PlayerV2>(&connection, 0).is_err());
+    }
+
+    #[derive(Debug, Clone, PartialEq, HasCrudFields)]
+    #[append_only]
+    pub struct LedgerV1 {
+        #[primary_key]
+        pub id: i64,
+        pub amount: i64,
+    }
+
+    #[derive(Debug, Clone, PartialEq, HasCrudFields)]
+    pub struct LedgerV2 {
+        #[primary_key]
+        pub id: i64,
+        pub amount: i64,
+        pub memo: String,
+    }
+
+    impl From<LedgerV1> for LedgerV2 {
+        fn from(LedgerV1 { id, amount }: LedgerV1) -> Self {
+            LedgerV2 {
+                id,
+                amount,
+                memo: String::new(),
+            }
+        }
+    }
+
+    impl MigrationStep<LedgerV1> for LedgerV2 {}
+
+    #[test]
+    fn migrate_row_append_only() {
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+        <LedgerV1 as Crud<Sqlite>>::create(&connection).unwrap();
+        <LedgerV2 as Crud<Sqlite>>::create(&connection).unwrap();
+        let entry = LedgerV1 { id: 0, amount: 10 };
+        Crud::<Sqlite>::insert(&entry, &connection).unwrap();
+
+        let error = migrate_row::<Sqlite, LedgerV1, LedgerV2>(&connection, 0).unwrap_err();
+        assert!(error.to_string().contains("append-only"), "{error}");
+        assert_eq!(
+            vec![entry],
+            <LedgerV1 as Crud<Sqlite>>::read_all_vec(&connection).unwrap()
+        );
+        assert!(<LedgerV2 as Crud<Sqlite>>::read_all_vec(&connection)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn migrate_row_rolls_back() {
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+        <PlayerV1 as Crud<Sqlite>>::create(&connection).unwrap();
+        <PlayerV2 as Crud<Sqlite>>::create(&connection).unwrap();
+        let player = PlayerV1 {
+            id: 0,
+            name: "tymigrawr".to_string(),
+        };
+        Crud::<Sqlite>::insert(&player, &connection).unwrap();
+        connection
+            .execute_batch(
+                "CREATE TRIGGER keep_players BEFORE DELETE ON playerv1 \
+                 BEGIN SELECT RAISE(ABORT, 'kept'); END;",
+            )
+            .unwrap();
+
+        assert!(migrate_row::<Sqlite, PlayerV1, PlayerV2>(&connection, 0).is_err());
+        // the insert into `playerv2` was rolled back with the failed delete
+        assert_eq!(
+            vec![player],
+            <PlayerV1 as Crud<Sqlite>>::read_all_vec(&connection).unwrap()
+        );
+        assert!(<PlayerV2 as Crud<Sqlite>>::read_all_vec(&connection)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn scoped_crud() {
+        let players = scoped::<PlayerV1, _>(|connection| {
+            let mut player = PlayerV1 {
+                id: 0,
+                name: "tymigrawr".to_string(),
+            };
+            Crud::<Sqlite>::insert(&player, connection)?;
+            player.name = "rawr".to_string();
+            Crud::<Sqlite>::update(&player, connection)?;
+            let read =
+                <PlayerV1 as Crud<Sqlite>>::read(connection, 0)?.collect::<Result<Vec<_>, _>>()?;
+            assert_eq!(vec![player.clone()], read);
+            Crud::<Sqlite>::delete(player, connection)?;
+            <PlayerV1 as Crud<Sqlite>>::count(connection)
+        })
+        .unwrap()
+        .unwrap();
+        assert_eq!(0, players);
+    }
+
+    #[test]
+    fn p1_crud() {
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+        <PlayerV1 as Crud<Sqlite>>::create(&connection).unwrap();
+        let first_player = PlayerV1 {
+            id: 0,
+            name: "tymigrawr".to_string(),
+        };
+        Crud::<Sqlite>::insert(&first_player, &connection).unwrap();
+        let player = <PlayerV1 as Crud<Sqlite>>::read(&connection, 0)
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap();
+        assert_eq!(first_player, player);
+        let mut second_player = PlayerV1 {
+            id: 1,
+            name: "developer".to_string(),
+        };
+        Crud::<Sqlite>::insert(&second_player, &connection).unwrap();
+        let player = <PlayerV1 as Crud<Sqlite>>::read(&connection, 1)
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap();
+        assert_eq!(second_player, player);
+
+        let mut p1 = <PlayerV1 as Crud<Sqlite>>::read(&connection, first_player.id).unwrap();
+        assert_eq!(first_player, p1.next().unwrap().unwrap());
+        let mut p2 = <PlayerV1 as Crud<Sqlite>>::read(&connection, second_player.id).unwrap();
+        assert_eq!(second_player, p2.next().unwrap().unwrap());
+
+        second_player.name = "software engineer".to_string();
+        Crud::<Sqlite>::update(&second_player, &connection).unwrap();
+        let p2 = <PlayerV1 as Crud<Sqlite>>::read(&connection, second_player.id)
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap();
+        assert_eq!(second_player, p2);
+
+        Crud::<Sqlite>::delete(second_player, &connection).unwrap();
+        let players = <PlayerV1 as Crud<Sqlite>>::read(&connection, p2.id)
+            .unwrap()
+            .map(|p| p.unwrap())
+            .collect::<Vec<_>>();
+        assert!(players.is_empty());
+    }
+
+    #[test]
+    fn p2_crud() {
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+        <PlayerV2 as Crud<Sqlite>>::create(&connection).unwrap();
+        let mut first_player = PlayerV2 {
+            id: 0,
+            name: "tymigrawr".to_string(),
+            age: 0.1,
+        };
+        Crud::<Sqlite>::insert(&first_player, &connection).unwrap();
+        let mut p1 = <PlayerV2 as Crud<Sqlite>>::read(&connection, first_player.id).unwrap();
+        assert_eq!(first_player, p1.next().unwrap().unwrap());
+
+        first_player.name = "software engineer".to_string();
+        Crud::<Sqlite>::update(&first_player, &connection).unwrap();
+        let p2 = <PlayerV2 as Crud<Sqlite>>::read(&connection, first_player.id)
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap();
+        assert_eq!(first_player, p2);
+
+        Crud::<Sqlite>::delete(first_player, &connection).unwrap();
+        let players = <PlayerV2 as Crud<Sqlite>>::read(&connection, p2.id)
+            .unwrap()
+            .map(|p| p.unwrap())
+            .collect::<Vec<_>>();
+        assert!(players.is_empty());
+    }
+
+    #[test]
+    fn read_all_rev() {
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+        <PlayerV1 as Crud<Sqlite>>::create(&connection).unwrap();
+        let players = (0..5)
+            .map(|id| PlayerV1 {
+                id,
+                name: format!("tymigrawr_{id}"),
+            })
+            .collect::<Vec<_>>();
+        for player in players.iter() {
+            Crud::<Sqlite>::insert(player, &connection).unwrap();
+        }
+        let players_from_db = <PlayerV1 as Crud<Sqlite>>::read_all_rev(&connection)
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect::<Vec<_>>();
+        let newest_first = players.into_iter().rev().collect::<Vec<_>>();
+        assert_eq!(newest_first, players_from_db);
+    }
+
+    #[test]
+    fn read_filtered() {
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+        <PlayerV1 as Crud<Sqlite>>::create(&connection).unwrap();
+        for id in 0..5 {
+            Crud::<Sqlite>::insert(
+                &PlayerV1 {
+                    id,
+                    name: format!("tymigrawr_{id}"),
+                },
+                &connection,
+            )
+            .unwrap();
+        }
+        let even = <PlayerV1 as Crud<Sqlite>>::read_filtered(&connection, |p| p.id % 2 == 0)
+            .unwrap()
+            .map(|r| r.unwrap().id)
+            .collect::<Vec<_>>();
+        assert_eq!(vec![0, 2, 4], even);
+
+        let calls = std::cell::Cell::new(0);
+        let first_two = <PlayerV1 as Crud<Sqlite>>::read_filtered(&connection, |p| {
+            calls.set(calls.get() + 1);
+            p.id % 2 == 0
+        })
+        .unwrap()
+        .take(2)
+        .map(|r| r.unwrap().id)
+        .collect::<Vec<_>>();
+        assert_eq!(vec![0, 2], first_two);
+        // rows 3 and 4 were never tested
+        assert_eq!(3, calls.get());
+    }
+
+    #[test]
+    fn read_all_vec() {
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+        <PlayerV1 as Crud<Sqlite>>::create(&connection).unwrap();
+        assert_eq!(
+            Vec::<PlayerV1>::new(),
+            <PlayerV1 as Crud<Sqlite>>::read_all_vec(&connection).unwrap()
+        );
+        let players = (0..3)
+            .map(|id| PlayerV1 {
+                id,
+                name: format!("tymigrawr_{id}"),
+            })
+            .collect::<Vec<_>>();
+        <PlayerV1 as Crud<Sqlite>>::insert_many(&connection, &players).unwrap();
+        assert_eq!(
+            players,
+            <PlayerV1 as Crud<Sqlite>>::read_all_vec(&connection).unwrap()
+        );
+
+        // a blob can't be read as a name
+        connection
+            .execute_batch("INSERT INTO playerv1 (id, name) VALUES (3, X'00');")
+            .unwrap();
+        assert!(<PlayerV1 as Crud<Sqlite>>::read_all_vec(&connection).is_err());
+    }
+
+    #[test]
+    fn read_page_typed() {
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+        <PlayerV1 as Crud<Sqlite>>::create(&connection).unwrap();
+        let players = (0..10)
+            .map(|id| PlayerV1 {
+                id,
+                name: format!("tymigrawr_{id}"),
+            })
+            .collect::<Vec<_>>();
+        <PlayerV1 as Crud<Sqlite>>::insert_many(&connection, &players).unwrap();
+        assert_eq!(10, <PlayerV1 as Crud<Sqlite>>::count(&connection).unwrap());
+
+        let page = <PlayerV1 as Crud<Sqlite>>::read_page_typed(&connection, 3, 3).unwrap();
+        assert_eq!(
+            Page {
+                items: players[3..6].to_vec(),
+                total: 10,
+                limit: 3,
+                offset: 3,
+                has_more: true,
+            },
+            page
+        );
+
+        let page = <PlayerV1 as Crud<Sqlite>>::read_page_typed(&connection, 3, 9).unwrap();
+        assert_eq!(players[9..].to_vec(), page.items);
+        assert!(!page.has_more);
+    }
+
+    #[test]
+    fn read_by_keys() {
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+        <PlayerV1 as Crud<Sqlite>>::create(&connection).unwrap();
+        let players = (0..2000)
+            .map(|id| PlayerV1 {
+                id,
+                name: format!("tymigrawr_{id}"),
+            })
+            .collect::<Vec<_>>();
+        <PlayerV1 as Crud<Sqlite>>::insert_many(&connection, &players).unwrap();
+
+        let ids = <PlayerV1 as Crud<Sqlite>>::read_by_keys(&connection, vec![3, 5, 8, 13, 5000])
+            .unwrap()
+            .into_iter()
+            .map(|player| player.id)
+            .collect::<Vec<_>>();
+        assert_eq!(vec![3, 5, 8, 13], ids);
+
+        // more keys than sqlite can bind in one statement
+        let evens = (0..2000).step_by(2).collect::<Vec<i64>>();
+        let from_db = <PlayerV1 as Crud<Sqlite>>::read_by_keys(&connection, evens).unwrap();
+        assert_eq!(1000, from_db.len());
+        assert!(from_db.iter().all(|player| player.id % 2 == 0));
+
+        assert!(
+            <PlayerV1 as Crud<Sqlite>>::read_by_keys(&connection, vec![])
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn read_in() {
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+        <PlayerV1 as Crud<Sqlite>>::create(&connection).unwrap();
+        let players = (0..5)
+            .map(|id| PlayerV1 {
+                id,
+                name: format!("tymigrawr_{id}"),
+            })
+            .collect::<Vec<_>>();
+        <PlayerV1 as Crud<Sqlite>>::insert_many(&connection, &players).unwrap();
+
+        let names = vec!["tymigrawr_1", "tymigrawr_3", "rawr"]
+            .into_iter()
+            .map(str::to_string)
+            .collect();
+        let read = <PlayerV1 as Crud<Sqlite>>::read_in(&connection, "name", names)
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(vec![players[1].clone(), players[3].clone()], read);
+
+        let err = <PlayerV1 as Crud<Sqlite>>::read_in(&connection, "nickname", vec![0i64])
+            .err()
+            .unwrap();
+        assert_eq!("table `playerv1` has no column `nickname`", err.to_string());
+    }
+
+    #[test]
+    fn read_in_empty() {
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+        // there's no table, so querying would fail
+        assert!(
+            <PlayerV1 as Crud<Sqlite>>::read_in(&connection, "id", Vec::<i64>::new())
+                .unwrap()
+                .next()
+                .is_none()
+        );
+        assert!(<PlayerV1 as Crud<Sqlite>>::read_in(&connection, "id", vec![0i64]).is_err());
+    }
+
+    #[test]
+    fn bool_roundtrip() {
+        for b in [true, false] {
+            let value = Value::from(b);
+            assert_eq!(Some(b), value.as_bool());
+            let sqlite_value = rusqlite::types::Value::from(value);
+            assert_eq!(rusqlite::types::Value::Integer(i64::from(b)), sqlite_value);
+            assert_eq!(Some(b), Value::from(sqlite_value).as_bool());
+        }
+        assert_eq!(None, Value::Integer(2).as_bool());
+        assert_eq!("true", Value::Boolean(true).to_string());
+    }
+
+    #[test]
+    fn value_from_str_typed() {
+        let parse = |s, ty| Value::from_str_typed(s, &ty);
+        assert_eq!(
+            Value::Integer(-66),
+            parse("-66", ValueType::Integer).unwrap()
+        );
+        assert_eq!(
+            Value::Integer(66),
+            parse(" 66 ", ValueType::Integer).unwrap()
+        );
+        assert_eq!(Value::Float(6.5), parse("6.5", ValueType::Float).unwrap());
+        assert_eq!(Value::Float(66.0), parse("66", ValueType::Float).unwrap());
+        assert_eq!(
+            Value::String(" rawr ".to_string()),
+            parse(" rawr ", ValueType::String).unwrap()
+        );
+        assert_eq!(
+            Value::Bytes(b"tymigrawr".to_vec()),
+            parse("dHltaWdyYXdy", ValueType::Bytes).unwrap()
+        );
+        for ty in [
+            ValueType::Integer,
+            ValueType::Float,
+            ValueType::String,
+            ValueType::Bytes,
+        ] {
+            assert_eq!(Value::None, parse("", ty).unwrap());
+        }
+
+        let err = parse("6.5", ValueType::Integer).unwrap_err();
+        assert_eq!("'6.5' is not an integer", err.to_string());
+        assert!(parse("12abc", ValueType::Integer).is_err());
+        assert!(parse("99999999999999999999", ValueType::Integer).is_err());
+        let err = parse("six", ValueType::Float).unwrap_err();
+        assert_eq!("'six' is not a float", err.to_string());
+        let err = parse("not base64!", ValueType::Bytes).unwrap_err();
+        assert_eq!("'not base64!' is not base64", err.to_string());
+    }
+
+    #[test]
+    fn value_coerce_to() {
+        let coerce = |value: Value, ty| value.coerce_to(ty);
+        assert_eq!(
+            Value::Float(600.0),
+            coerce(Value::Integer(600), ValueType::Float).unwrap()
+        );
+        assert_eq!(
+            Value::Integer(600),
+            coerce(Value::Float(600.0), ValueType::Integer).unwrap()
+        );
+        assert_eq!(
+            Value::Integer(66),
+            coerce(Value::String("66".into()), ValueType::Integer).unwrap()
+        );
+        assert_eq!(
+            Value::Float(6.5),
+            coerce(Value::String("6.5".into()), ValueType::Float).unwrap()
+        );
+        assert_eq!(
+            Value::String("66".into()),
+            coerce(Value::Integer(66), ValueType::String).unwrap()
+        );
+        assert_eq!(
+            Value::String("6.5".into()),
+            coerce(Value::Float(6.5), ValueType::String).unwrap()
+        );
+        assert_eq!(
+            Value::Integer(1),
+            coerce(Value::Boolean(true), ValueType::Integer).unwrap()
+        );
+        assert_eq!(
+            Value::Bytes(b"rawr".to_vec()),
+            coerce(Value::String("rawr".into()), ValueType::Bytes).unwrap()
+        );
+        assert_eq!(
+            Value::Float(6.5),
+            coerce(Value::Float(6.5), ValueType::Float).unwrap()
+        );
+        assert_eq!(
+            Value::None,
+            coerce(Value::None, ValueType::Integer).unwrap()
+        );
+
+        let err = coerce(Value::Float(6.5), ValueType::Integer).unwrap_err();
+        assert_eq!("6.5 is not an integer", err.to_string());
+        assert!(coerce(Value::Float(1e20), ValueType::Integer).is_err());
+        assert!(coerce(Value::Integer(i64::MAX), ValueType::Float).is_err());
+        assert!(coerce(Value::String("six".into()), ValueType::Integer).is_err());
+        assert!(coerce(Value::String("".into()), ValueType::Float).is_err());
+        assert!(coerce(Value::Bytes(vec![0xff]), ValueType::String).is_err());
+        let err = coerce(Value::Bytes(vec![]), ValueType::Integer).unwrap_err();
+        assert_eq!("Bytes([]) can't be coerced to Integer", err.to_string());
+    }
+
+    #[test]
+    fn create_table_sql() {
+        assert_eq!(
+            "CREATE TABLE IF NOT EXISTS \"playerv2\" \
+             (\"id\" INTEGER PRIMARY KEY NOT NULL, \"name\" TEXT NOT NULL, \"age\" REAL NOT NULL);",
+            Sqlite::create_table_sql::<PlayerV2>()
+        );
+    }
+
+    #[derive(Debug, Clone, PartialEq, HasCrudFields)]
+    pub struct Ticket {
+        #[primary_key(auto_increment)]
+        pub id: i64,
+        pub title: String,
+    }
+
+    #[test]
+    fn auto_increment() {
+        let fields = Ticket::crud_fields();
+        assert!(fields[0].primary_key && fields[0].auto_increment);
+        assert!(!fields[1].auto_increment);
+        assert_eq!(
+            "CREATE TABLE IF NOT EXISTS \"ticket\" \
+             (\"id\" INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL, \"title\" TEXT NOT NULL);",
+            Sqlite::create_table_sql::<Ticket>()
+        );
+
+        // inserts still write the key
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+        <Ticket as Crud<Sqlite>>::create(&connection).unwrap();
+        let tickets = [3, 7]
+            .map(|id| Ticket {
+                id,
+                title: format!("ticket {id}"),
+            })
+            .to_vec();
+        <Ticket as Crud<Sqlite>>::insert_many(&connection, &tickets).unwrap();
+        let tickets_from_db = <Ticket as Crud<Sqlite>>::read_all(&connection)
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(tickets, tickets_from_db);
+    }
+
+    #[test]
+    fn insert_many() {
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+        <PlayerV1 as Crud<Sqlite>>::create(&connection).unwrap();
+        let players = (0..5)
+            .map(|id| PlayerV1 {
+                id,
+                name: format!("tymigrawr_{id}"),
+            })
+            .collect::<Vec<_>>();
+        <PlayerV1 as Crud<Sqlite>>::insert_many(&connection, &players).unwrap();
+        let players_from_db = <PlayerV1 as Crud<Sqlite>>::read_all(&connection)
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(players, players_from_db);
+
+        // a duplicate primary key rolls back the whole batch
+        let more = (5..10)
+            .chain(Some(0))
+            .map(|id| PlayerV1 {
+                id,
+                name: format!("tymigrawr_{id}"),
+            })
+            .collect::<Vec<_>>();
+        assert!(<PlayerV1 as Crud<Sqlite>>::insert_many(&connection, &more).is_err());
+        assert_eq!(
+            5,
+            <PlayerV1 as Crud<Sqlite>>::read_all(&connection)
+                .unwrap()
+                .count()
+        );
+    }
+
+    #[test]
+    fn aggregate() {
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+        <PlayerV2 as Crud<Sqlite>>::create(&connection).unwrap();
+        let ages = [30.0, 40.0, 30.0, 30.0, 40.0, 50.0];
+        for (id, age) in (0..).zip(ages) {
+            let player = PlayerV2 {
+                id,
+                name: format!("tymigrawr_{id}"),
+                age,
+            };
+            Crud::<Sqlite>::insert(&player, &connection).unwrap();
+        }
+        let groups = <PlayerV2 as Crud<Sqlite>>::aggregate(&connection, "age").unwrap();
+        assert_eq!(
+            vec![
+                (Value::Float(30.0), 3),
+                (Value::Float(40.0), 2),
+                (Value::Float(50.0), 1)
+            ],
+            groups
+        );
+
+        let err = <PlayerV2 as Crud<Sqlite>>::aggregate(&connection, "height").unwrap_err();
+        assert_eq!("table `playerv2` has no column `height`", err.to_string());
+    }
+
+    #[test]
+    fn format_rows() {
+        let players = (0..3)
+            .map(|id| PlayerV2 {
+                id,
+                name: "tymigrawr".repeat(id as usize),
+                age: 66.5,
+            })
+            .collect::<Vec<_>>();
+        let rows = players
+            .iter()
+            .map(PlayerV2::as_crud_fields)
+            .collect::<Vec<_>>();
+        let table = crate::debug::format_rows(&rows, &["id", "name", "age"]);
+        assert_eq!(
+            "\
+| id | name               | age  |
+| -- | ------------------ | ---- |
+| 0  |                    | 66.5 |
+| 1  | tymigrawr          | 66.5 |
+| 2  | tymigrawrtymigrawr | 66.5 |
+",
+            table
+        );
+    }
+
+    #[derive(Debug, Clone, PartialEq, HasCrudFields)]
+    pub struct Profile {
+        #[primary_key]
+        pub id: i64,
+        pub name: String,
+        pub email: String,
+        pub age: f32,
+        pub score: i64,
+    }
+
+    #[test]
+    fn update_diff_count() {
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+        <Profile as Crud<Sqlite>>::create(&connection).unwrap();
+        let old = Profile {
+            id: 0,
+            name: "tymigrawr".to_string(),
+            email: "tymigrawr@example.com".to_string(),
+            age: 66.0,
+            score: 0,
+        };
+        Crud::<Sqlite>::insert(&old, &connection).unwrap();
+
+        let new = Profile {
+            email: "rawr@example.com".to_string(),
+            score: 100,
+            ..old.clone()
+        };
+        assert_eq!(
+            2,
+            Crud::<Sqlite>::update_diff_count(&new, &old, &connection).unwrap()
+        );
+        let profile = <Profile as Crud<Sqlite>>::read(&connection, 0)
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap();
+        assert_eq!(new, profile);
+
+        assert_eq!(
+            0,
+            Crud::<Sqlite>::update_diff_count(&new, &profile, &connection).unwrap()
+        );
+    }
+
+    #[test]
+    fn insert_sql_is_stable() {
+        let profile = Profile {
+            id: 0,
+            name: "tymigrawr".to_string(),
+            email: "tymigrawr@example.com".to_string(),
+            age: 66.0,
+            score: 0,
+        };
+        // each `HashMap` hashes differently, so repeat to catch reordering
+        for _ in 0..100 {
+            assert_eq!(
+                "INSERT INTO \"profile\" (\"id\", \"name\", \"email\", \"age\", \"score\") \
+                 VALUES (:p0, :p1, :p2, :p3, :p4);",
+                crate::sqlite_common::insert_sql(Profile::table_name(), &profile.as_crud_fields())
+            );
+        }
+    }
+
+    #[test]
+    fn update_diff_writes_changed_columns() {
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+        <Profile as Crud<Sqlite>>::create(&connection).unwrap();
+        connection
+            .execute_batch("CREATE TABLE writes (col TEXT NOT NULL)")
+            .unwrap();
+        for col in ["id", "name", "email", "age", "score"] {
+            connection
+                .execute_batch(&format!(
+                    "CREATE TRIGGER write_{col} AFTER UPDATE OF {col} ON profile \
+                     BEGIN INSERT INTO writes VALUES ('{col}'); END"
+                ))
+                .unwrap();
+        }
+        let writes = || {
+            connection
+                .prepare("SELECT col FROM writes ORDER BY rowid")
+                .unwrap()
+                .query_map([], |row| row.get::<_, String>(0))
+                .unwrap()
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap()
+        };
+        let old = Profile {
+            id: 0,
+            name: "tymigrawr".to_string(),
+            email: "tymigrawr@example.com".to_string(),
+            age: 66.0,
+            score: 0,
+        };
+        Crud::<Sqlite>::insert(&old, &connection).unwrap();
+
+        Crud::<Sqlite>::update_diff(&old, &old, &connection).unwrap();
+        assert!(writes().is_empty());
+
+        let new = Profile {
+            score: 100,
+            ..old.clone()
+        };
+        Crud::<Sqlite>::update_diff(&new, &old, &connection).unwrap();
+        assert_eq!(vec!["score".to_string()], writes());
+        let profile = <Profile as Crud<Sqlite>>::read(&connection, 0)
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap();
+        assert_eq!(new, profile);
+
+        // a full update writes every column
+        Crud::<Sqlite>::update(&new, &connection).unwrap();
+        assert!(writes().len() > 2);
+    }
+
+    #[derive(Debug, Clone, PartialEq, HasCrudFields)]
+    pub struct Grade {
+        #[primary_key]
+        pub id: i64,
+        pub grade: char,
+        pub bonus: Option<char>,
+    }
+
+    #[test]
+    fn char_roundtrip() {
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+        <Grade as Crud<Sqlite>>::create(&connection).unwrap();
+        let grades = vec![
+            Grade {
+                id: 0,
+                grade: 'A',
+                bonus: Some('🦖'),
+            },
+            Grade {
+                id: 1,
+                grade: 'C',
+                bonus: None,
+            },
+        ];
+        <Grade as Crud<Sqlite>>::insert_many(&connection, &grades).unwrap();
+        let grades_from_db = <Grade as Crud<Sqlite>>::read_all(&connection)
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(grades, grades_from_db);
+        assert_eq!(Value::String("A".to_string()), 'A'.into_value());
+    }
+
+    #[cfg(feature = "decimal")]
+    #[derive(Debug, Clone, PartialEq, HasCrudFields)]
+    pub struct Price {
+        #[primary_key]
+        pub id: i64,
+        pub amount: rust_decimal::Decimal,
+        pub discount: Option<rust_decimal::Decimal>,
+    }
+
+    #[cfg(feature = "decimal")]
+    #[test]
+    fn decimal_roundtrip() {
+        use std::str::FromStr;
+
+        let decimal = |s| rust_decimal::Decimal::from_str(s).unwrap();
+        // 0.10 + 0.20 isn't 0.30 as a float
+        assert_ne!(0.3, 0.1 + 0.2);
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+        <Price as Crud<Sqlite>>::create(&connection).unwrap();
+        let prices = vec![
+            Price {
+                id: 0,
+                amount: decimal("19.99"),
+                discount: Some(decimal("0.10")),
+            },
+            Price {
+                id: 1,
+                amount: decimal("0.20"),
+                discount: None,
+            },
+        ];
+        <Price as Crud<Sqlite>>::insert_many(&connection, &prices).unwrap();
+        let prices_from_db = <Price as Crud<Sqlite>>::read_all(&connection)
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(prices, prices_from_db);
+        assert_eq!(
+            Value::String("0.10".to_string()),
+            prices_from_db[0].discount.unwrap().into_value()
+        );
+        let total = prices_from_db[0].discount.unwrap() + prices_from_db[1].amount;
+        assert_eq!(decimal("0.30"), total);
+
+        let err =
+            rust_decimal::Decimal::maybe_from_value(&Value::from("rawr".to_string())).unwrap_err();
+        assert_eq!("'rawr' is not a decimal", err.to_string());
+    }
+
+    #[derive(Debug, Clone, PartialEq, HasCrudFields)]
+    pub struct Document {
+        #[primary_key]
+        pub id: i64,
+        pub modified: std::time::SystemTime,
+        pub deleted: Option<std::time::SystemTime>,
+    }
+
+    #[test]
+    fn system_time_roundtrip() {
+        use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+        <Document as Crud<Sqlite>>::create(&connection).unwrap();
+        let now = SystemTime::now();
+        let documents = vec![
+            Document {
+                id: 0,
+                modified: now,
+                deleted: None,
+            },
+            Document {
+                id: 1,
+                modified: UNIX_EPOCH - Duration::from_secs(66),
+                deleted: Some(UNIX_EPOCH + Duration::from_millis(66_600)),
+            },
+        ];
+        <Document as Crud<Sqlite>>::insert_many(&connection, &documents).unwrap();
+        let documents_from_db = <Document as Crud<Sqlite>>::read_all(&connection)
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect::<Vec<_>>();
+
+        // only whole seconds are stored
+        let modified = documents_from_db[0].modified;
+        assert!(modified <= now);
+        assert!(now.duration_since(modified).unwrap() < Duration::from_secs(1));
+        assert_eq!(
+            0,
+            modified.duration_since(UNIX_EPOCH).unwrap().subsec_nanos()
+        );
+        assert_eq!(documents[1].modified, documents_from_db[1].modified);
+        assert_eq!(
+            Some(UNIX_EPOCH + Duration::from_secs(66)),
+            documents_from_db[1].deleted
+        );
+        assert_eq!(Value::Integer(-66), documents[1].modified.into_value());
+
+        let err = SystemTime::maybe_from_value(&Value::from("yesterday".to_string())).unwrap_err();
+        assert_eq!("not an integer", err.to_string());
+    }
+
+    #[derive(Debug, Clone, PartialEq, HasCrudFields)]
+    pub struct Booking {
+        #[primary_key]
+        pub id: std::num::NonZeroI64,
+        pub seat: std::num::NonZeroU32,
+        pub row: std::num::NonZeroI32,
+        pub transferred_to: Option<std::num::NonZeroI64>,
+    }
+
+    #[test]
+    fn nonzero_roundtrip() {
+        use std::num::{NonZeroI32, NonZeroI64, NonZeroU32};
+
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+        <Booking as Crud<Sqlite>>::create(&connection).unwrap();
+        let bookings = vec![
+            Booking {
+                id: NonZeroI64::new(1).unwrap(),
+                seat: NonZeroU32::new(12).unwrap(),
+                row: NonZeroI32::new(-3).unwrap(),
+                transferred_to: None,
+            },
+            Booking {
+                id: NonZeroI64::new(i64::MIN).unwrap(),
+                seat: NonZeroU32::MAX,
+                row: NonZeroI32::MAX,
+                transferred_to: NonZeroI64::new(7),
+            },
+        ];
+        <Booking as Crud<Sqlite>>::insert_many(&connection, &bookings).unwrap();
+        let mut bookings_from_db = <Booking as Crud<Sqlite>>::read_all(&connection)
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect::<Vec<_>>();
+        bookings_from_db.reverse();
+        assert_eq!(bookings, bookings_from_db);
+
+        connection
+            .execute_batch(
+                "INSERT INTO booking (id, seat, row, transferred_to) VALUES (2, 1, 1, 0);",
+            )
+            .unwrap();
+        let err = <Booking as Crud<Sqlite>>::read(&connection, NonZeroI64::new(2).unwrap())
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap_err();
+        assert!(
+            format!("{err:?}").contains("stored zero for a nonzero integer"),
+            "{err:?}"
+        );
+        for err in [
+            NonZeroI64::maybe_from_value(&Value::Integer(0)).unwrap_err(),
+            NonZeroI32::maybe_from_value(&Value::Integer(0)).unwrap_err(),
+            NonZeroU32::maybe_from_value(&Value::Integer(0)).unwrap_err(),
+        ] {
+            assert_eq!("stored zero for a nonzero integer", err.to_string());
+        }
+        let err = NonZeroU32::maybe_from_value(&Value::Integer(-1)).unwrap_err();
+        assert_eq!("can't u32 from i64", err.to_string());
+    }
+
+    #[derive(Debug, Clone, PartialEq, HasCrudFields)]
+    pub struct Ability {
+        #[primary_key]
+        pub name: String,
+        pub cooldown: std::time::Duration,
+        pub charge: Option<std::time::Duration>,
+    }
+
+    #[test]
+    fn duration_roundtrip() {
+        use std::time::Duration;
+
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+        <Ability as Crud<Sqlite>>::create(&connection).unwrap();
+        let abilities = vec![
+            Ability {
+                name: "blink".into(),
+                cooldown: Duration::from_secs(15),
+                charge: None,
+            },
+            Ability {
+                name: "fireball".into(),
+                cooldown: Duration::from_micros(2_500_999),
+                charge: Some(Duration::from_nanos(999_999)),
+            },
+        ];
+        <Ability as Crud<Sqlite>>::insert_many(&connection, &abilities).unwrap();
+        let abilities_from_db = <Ability as Crud<Sqlite>>::read_all(&connection)
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(abilities[0], abilities_from_db[0]);
+        // only whole milliseconds are stored
+        assert_eq!(Duration::from_millis(2_500), abilities_from_db[1].cooldown);
+        assert_eq!(Some(Duration::ZERO), abilities_from_db[1].charge);
+        assert_eq!(Value::Integer(15_000), abilities[0].cooldown.into_value());
+
+        let err = Duration::maybe_from_value(&Value::Integer(-1)).unwrap_err();
+        assert_eq!("-1ms is a negative duration", err.to_string());
+    }
+
+    #[derive(Debug, Clone, PartialEq, HasCrudFields)]
+    pub struct Visit {
+        #[primary_key]
+        pub id: i64,
+        pub ip: std::net::IpAddr,
+        pub peer: Option<std::net::SocketAddr>,
+    }
+
+    #[test]
+    fn ip_addr_roundtrip() {
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+        <Visit as Crud<Sqlite>>::create(&connection).unwrap();
+        let visits = vec![
+            Visit {
+                id: 0,
+                ip: "192.168.0.66".parse().unwrap(),
+                peer: Some("192.168.0.66:8080".parse().unwrap()),
+            },
+            Visit {
+                id: 1,
+                ip: "2001:db8::66".parse().unwrap(),
+                peer: Some("[2001:db8::66]:443".parse().unwrap()),
+            },
+            Visit {
+                id: 2,
+                ip: "::1".parse().unwrap(),
+                peer: None,
+            },
+        ];
+        <Visit as Crud<Sqlite>>::insert_many(&connection, &visits).unwrap();
+        let visits_from_db = <Visit as Crud<Sqlite>>::read_all(&connection)
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(visits, visits_from_db);
+        assert_eq!(
+            Value::String("[2001:db8::66]:443".to_string()),
+            visits[1].peer.into_value()
+        );
+
+        let err =
+            std::net::IpAddr::maybe_from_value(&Value::from("localhost".to_string())).unwrap_err();
+        assert_eq!("'localhost' is not an IP address", err.to_string());
+        let err =
+            std::net::SocketAddr::maybe_from_value(&Value::from("::1".to_string())).unwrap_err();
+        assert_eq!("'::1' is not a socket address", err.to_string());
+    }
+
+    #[test]
+    fn char_from_multiple_chars() {
+        let err = char::maybe_from_value(&Value::String("AB".to_string())).unwrap_err();
+        assert_eq!("'AB' is not a single character", err.to_string());
+        assert!(char::maybe_from_value(&Value::String(String::new())).is_err());
+        assert!(Option::<char>::maybe_from_value(&Value::String("AB".to_string())).is_err());
+        assert_eq!(
+            None,
+            Option::<char>::maybe_from_value(&Value::None).unwrap()
+        );
+    }
+
+    #[derive(Debug, Clone, PartialEq, HasCrudFields)]
+    #[table_naming = "snake_case"]
+    pub struct HTTPServerV2 {
+        #[primary_key]
+        pub id: i64,
+    }
+
+    #[derive(Debug, Clone, PartialEq, HasCrudFields)]
+    #[table_naming = "as_is"]
+    pub struct ServerV2 {
+        #[primary_key]
+        pub id: i64,
+    }
+
+    #[derive(Debug, Clone, PartialEq, HasCrudFields)]
+    #[table_naming = "lowercase"]
+    pub struct ServerV3 {
+        #[primary_key]
+        pub id: i64,
+    }
+
+    #[derive(Debug, Clone, PartialEq, HasCrudFields)]
+    #[table_naming = "snake_case"]
+    pub enum ServerEvent {
+        Started {
+            #[primary_key]
+            id: i64,
+        },
+    }
+
+    #[test]
+    fn table_naming() {
+        assert_eq!("playerv3", PlayerV3::table_name());
+        assert_eq!("http_server_v2", HTTPServerV2::table_name());
+        assert_eq!("ServerV2", ServerV2::table_name());
+        assert_eq!("serverv3", ServerV3::table_name());
+        assert_eq!("server_event", ServerEvent::table_name());
+
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+        <HTTPServerV2 as Crud<Sqlite>>::create(&connection).unwrap();
+        Crud::<Sqlite>::insert(&HTTPServerV2 { id: 0 }, &connection).unwrap();
+        assert_eq!(
+            1,
+            <HTTPServerV2 as Crud<Sqlite>>::count(&connection).unwrap()
+        );
+    }
+
+    #[derive(Debug, Clone, PartialEq, CrudNewtype)]
+    pub struct Email(String);
+
+    #[derive(Debug, Clone, PartialEq, CrudNewtype)]
+    pub struct Age {
+        years: u32,
+    }
+
+    #[derive(Debug, Clone, PartialEq, HasCrudFields)]
+    pub struct Account {
+        #[primary_key]
+        pub id: i64,
+        pub email: Email,
+        pub age: Option<Age>,
+    }
+
+    #[test]
+    fn crud_newtype() {
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+        <Account as Crud<Sqlite>>::create(&connection).unwrap();
+        let accounts = vec![
+            Account {
+                id: 0,
+                email: Email("tymigrawr@example.com".to_string()),
+                age: Some(Age { years: 66 }),
+            },
+            Account {
+                id: 1,
+                email: Email("rawr@example.com".to_string()),
+                age: None,
+            },
+        ];
+        <Account as Crud<Sqlite>>::insert_many(&connection, &accounts).unwrap();
+        let accounts_from_db = <Account as Crud<Sqlite>>::read_all(&connection)
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(accounts, accounts_from_db);
+
+        let emails = <Account as Crud<Sqlite>>::read_where(
+            &connection,
+            "email",
+            "=",
+            Email("rawr@example.com".to_string()),
+        )
+        .unwrap()
+        .map(|r| r.unwrap().id)
+        .collect::<Vec<_>>();
+        assert_eq!(vec![1], emails);
+    }
+
+    #[derive(Debug, Clone, PartialEq, HasCrudFields)]
+    pub struct Note {
+        #[primary_key]
+        pub id: Option<i64>,
+        pub body: String,
+        pub status: Option<String>,
+    }
+
+    #[test]
+    fn insert_returning() {
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+        connection
+            .execute_batch(
+                "CREATE TABLE note (\
+                 id INTEGER PRIMARY KEY, \
+                 body TEXT NOT NULL, \
+                 status TEXT DEFAULT 'draft');",
+            )
+            .unwrap();
+        let note = Note {
+            id: None,
+            body: "rawr".to_string(),
+            status: None,
+        };
+        let stored = Crud::<Sqlite>::insert_returning(&note, &connection).unwrap();
+        assert_eq!(
+            Note {
+                id: Some(1),
+                body: "rawr".to_string(),
+                status: Some("draft".to_string()),
+            },
+            stored
+        );
+
+        let note = Note {
+            id: Some(66),
+            body: "rawr rawr".to_string(),
+            status: Some("published".to_string()),
+        };
+        assert_eq!(
+            note,
+            Crud::<Sqlite>::insert_returning(&note, &connection).unwrap()
+        );
+    }
+
+    #[derive(Debug, Clone, PartialEq, HasCrudFields)]
+    pub struct Range {
+        #[primary_key]
+        pub id: i64,
+        pub low: i64,
+        pub high: i64,
+    }
+
+    #[test]
+    fn read_column_cmp() {
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+        <Range as Crud<Sqlite>>::create(&connection).unwrap();
+        for (id, (low, high)) in (0..).zip([(0, 10), (5, 5), (8, 2), (-3, 3), (100, 99)]) {
+            Crud::<Sqlite>::insert(&Range { id, low, high }, &connection).unwrap();
+        }
+        let ids = <Range as Crud<Sqlite>>::read_column_cmp(&connection, "low", "<=", "high")
+            .unwrap()
+            .map(|r| r.unwrap().id)
+            .collect::<Vec<_>>();
+        assert_eq!(vec![0, 1, 3], ids);
+        let ids = <Range as Crud<Sqlite>>::read_column_cmp(&connection, "low", ">", "high")
+            .unwrap()
+            .map(|r| r.unwrap().id)
+            .collect::<Vec<_>>();
+        assert_eq!(vec![2, 4], ids);
+
+        let err = <Range as Crud<Sqlite>>::read_column_cmp(&connection, "low", "<=", "1")
+            .err()
+            .unwrap();
+        assert_eq!("table `range` has no column `1`", err.to_string());
+        let err =
+            <Range as Crud<Sqlite>>::read_column_cmp(&connection, "low", "<= high OR 1 =", "high")
+                .err()
+                .unwrap();
+        assert_eq!("unknown comparison `<= high OR 1 =`", err.to_string());
+    }
+
+    #[derive(Debug, Clone, PartialEq, HasCrudFields)]
+    pub struct Measurement {
+        #[primary_key]
+        pub id: i64,
+        pub value: f64,
+        pub single: f32,
+    }
+
+    #[test]
+    fn float_nan_inf_roundtrip() {
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+        <Measurement as Crud<Sqlite>>::create(&connection).unwrap();
+        let values = [f64::NAN, f64::INFINITY, f64::NEG_INFINITY, 1.5];
+        for (id, value) in (0..).zip(values) {
+            let measurement = Measurement {
+                id,
+                value,
+                single: value as f32,
+            };
+            Crud::<Sqlite>::insert(&measurement, &connection).unwrap();
+        }
+        let from_db = <Measurement as Crud<Sqlite>>::read_all(&connection)
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(4, from_db.len());
+        assert!(from_db[0].value.is_nan());
+        assert!(from_db[0].single.is_nan());
+        for (measurement, value) in from_db.iter().zip(values).skip(1) {
+            assert_eq!(value, measurement.value);
+            assert_eq!(value as f32, measurement.single);
         }
-        Ok(())
     }
-}
 
-#[cfg(test)]
-mod test {
-    use aws_sdk_dynamodb::types::AttributeValue;
-    use snafu::prelude::*;
+    fn query_players(connection: &rusqlite::Connection) {
+        <PlayerV2 as Crud<Sqlite>>::create(connection).unwrap();
+        let names = ["alice", "bob", "arnold", "anne", "carl"];
+        for (id, name) in (0..).zip(names) {
+            let player = PlayerV2 {
+                id,
+                name: name.to_string(),
+                age: 15.0 + 2.0 * id as f32,
+            };
+            Crud::<Sqlite>::insert(&player, connection).unwrap();
+        }
+    }
+
+    #[test]
+    fn query_two_conditions() {
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+        query_players(&connection);
+        let query = Query::<PlayerV2>::new()
+            .and("age", Comparison::Gt, 18.0)
+            .and("name", Comparison::Like, "a%".to_string());
+        let (statement, binds) = query.sqlite_statement().unwrap();
+        assert_eq!(
+            "SELECT * FROM \"playerv2\" WHERE \"age\" > :p0 AND \"name\" LIKE :p1;",
+            statement
+        );
+        assert_eq!(
+            vec![
+                (":p0".to_string(), Value::Float(18.0)),
+                (":p1".to_string(), Value::String("a%".to_string()))
+            ],
+            binds
+        );
+        let names = query
+            .execute(&connection)
+            .unwrap()
+            .map(|r| r.unwrap().name)
+            .collect::<Vec<_>>();
+        assert_eq!(vec!["arnold", "anne"], names);
+    }
+
+    #[test]
+    fn query_three_conditions() {
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+        query_players(&connection);
+        let names = Query::<PlayerV2>::new()
+            .and("age", Comparison::Ge, 17.0)
+            .and("age", Comparison::Lt, 23.0)
+            .and("name", Comparison::Ne, "anne".to_string())
+            .execute(&connection)
+            .unwrap()
+            .map(|r| r.unwrap().name)
+            .collect::<Vec<_>>();
+        assert_eq!(vec!["bob", "arnold"], names);
+
+        let err = Query::<PlayerV2>::new()
+            .and("age", Comparison::Ge, 17.0)
+            .and("height", Comparison::Lt, 2.0)
+            .and("name; DROP TABLE playerv2", Comparison::Eq, 0)
+            .execute(&connection)
+            .err()
+            .unwrap();
+        assert_eq!("table `playerv2` has no column `height`", err.to_string());
+    }
+
+    #[derive(Debug, Clone, PartialEq, HasCrudFields)]
+    pub struct Ranked {
+        #[primary_key]
+        pub id: i64,
+        pub level: i64,
+        pub score: f64,
+    }
+
+    #[test]
+    fn select_filter_order_limit() {
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+        <Ranked as Crud<Sqlite>>::create(&connection).unwrap();
+        let rows = [
+            (0, 3, 90.0),
+            (1, 6, 10.0),
+            (2, 9, 50.0),
+            (3, 7, 50.0),
+            (4, 8, 70.0),
+        ]
+        .map(|(id, level, score)| Ranked { id, level, score });
+        <Ranked as Crud<Sqlite>>::insert_many(&connection, &rows).unwrap();
 
-    use crate::{self as tymigrawr, Crud, HasCrudFields, IsCrudField, Migrations, Value, Sqlite};
+        let select = Select::<Ranked>::new()
+            .filter("level", Comparison::Gt, 5)
+            .order_by("score", true)
+            .limit(3);
+        let (statement, binds) = select.sqlite_statement().unwrap();
+        assert_eq!(
+            "SELECT * FROM \"ranked\" WHERE \"level\" > :p0 \
+             ORDER BY \"score\" DESC, \"id\" ASC LIMIT 3;",
+            statement
+        );
+        assert_eq!(vec![(":p0".to_string(), Value::Integer(5))], binds);
+        // the highest score is filtered out, ties are broken by primary key
+        let ids = select
+            .execute(&connection)
+            .unwrap()
+            .map(|r| r.unwrap().id)
+            .collect::<Vec<_>>();
+        assert_eq!(vec![4, 2, 3], ids);
+
+        let err = Select::<Ranked>::new()
+            .order_by("score; DROP TABLE ranked", false)
+            .execute(&connection)
+            .err()
+            .unwrap();
+        assert_eq!(
+            "table `ranked` has no column `score; DROP TABLE ranked`",
+            err.to_string()
+        );
+    }
 
     #[derive(Debug, Clone, PartialEq, HasCrudFields)]
-    pub struct PlayerV1 {
+    #[audited]
+    pub struct Ledger {
+        #[primary_key]
+        id: i64,
+        amount: i64,
+    }
+
+    #[test]
+    fn audited() {
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+        <Ledger as Crud<Sqlite>>::create(&connection).unwrap();
+        // creating again is fine
+        <Ledger as Crud<Sqlite>>::create(&connection).unwrap();
+        let entry = Ledger { id: 0, amount: 10 };
+        Crud::<Sqlite>::insert(&entry, &connection).unwrap();
+        let updated = Ledger { id: 0, amount: 20 };
+        Crud::<Sqlite>::update(&updated, &connection).unwrap();
+        Crud::<Sqlite>::delete(updated.clone(), &connection).unwrap();
+
+        let history = <Ledger as Crud<Sqlite>>::read_history(&connection).unwrap();
+        let ops = history.iter().map(|h| h.op).collect::<Vec<_>>();
+        assert_eq!(vec![AuditOp::Insert, AuditOp::Update, AuditOp::Delete], ops);
+        let rows = history.into_iter().map(|h| h.row).collect::<Vec<_>>();
+        assert_eq!(vec![entry.clone(), entry, updated], rows);
+
+        let err = <PlayerV1 as Crud<Sqlite>>::read_history(&connection).unwrap_err();
+        assert_eq!("table `playerv1` is not audited", err.to_string());
+    }
+
+    #[test]
+    fn field_names() {
+        assert_eq!(vec!["id", "name", "description"], PlayerV3::field_names());
+    }
+
+    #[derive(Debug, Clone, PartialEq, HasCrudFields)]
+    pub struct Setting<T> {
         #[primary_key]
         pub id: i64,
+        pub value: T,
+        pub fallback: Option<T>,
+    }
+
+    #[test]
+    fn generic_struct() {
+        assert_eq!("setting", Setting::<String>::table_name());
+        let fields = Setting::<f64>::crud_fields();
+        assert_eq!(ValueType::Float, fields[1].ty);
+        assert!(fields[2].nullable);
+
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+        <Setting<String> as Crud<Sqlite>>::create(&connection).unwrap();
+        let settings = vec![
+            Setting {
+                id: 0,
+                value: "dark".to_string(),
+                fallback: Some("light".to_string()),
+            },
+            Setting {
+                id: 1,
+                value: "en".to_string(),
+                fallback: None,
+            },
+        ];
+        <Setting<_> as Crud<Sqlite>>::insert_many(&connection, &settings).unwrap();
+        let settings_from_db = <Setting<String> as Crud<Sqlite>>::read_all(&connection)
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(settings, settings_from_db);
+
+        // the same table read with a mismatched type fails to convert
+        assert!(<Setting::<i64> as Crud<Sqlite>>::read(&connection, 0)
+            .unwrap()
+            .next()
+            .unwrap()
+            .is_err());
+    }
+
+    #[test]
+    fn crud_fields_are_cached() {
+        let fields = PlayerV2::crud_fields();
+        for _ in 0..1000 {
+            // the same slice every time, not a rebuilt one
+            assert!(std::ptr::eq(fields, PlayerV2::crud_fields()));
+        }
+        assert_eq!(
+            vec![
+                ("id", ValueType::Integer),
+                ("name", ValueType::String),
+                ("age", ValueType::Float)
+            ],
+            fields.iter().map(|f| (f.name, f.ty)).collect::<Vec<_>>()
+        );
+
+        // each instantiation of a generic type has its own fields
+        assert_eq!(ValueType::Float, Setting::<f64>::crud_fields()[1].ty);
+        assert_eq!(ValueType::String, Setting::<String>::crud_fields()[1].ty);
+        assert!(std::ptr::eq(
+            Setting::<f64>::crud_fields(),
+            Setting::<f64>::crud_fields()
+        ));
+    }
+
+    #[derive(Debug, Clone, PartialEq, HasCrudFields)]
+    pub struct Tag {
+        #[primary_key]
         pub name: String,
+        pub uses: i64,
     }
 
-    impl From<PlayerV2> for PlayerV1 {
-        fn from(value: PlayerV2) -> PlayerV1 {
-            PlayerV1 {
-                id: value.id,
-                name: value.name,
+    #[test]
+    fn create_temp() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("temp.db");
+        assert_eq!(
+            "CREATE TEMP TABLE IF NOT EXISTS \"tag\" \
+             (\"name\" TEXT PRIMARY KEY NOT NULL, \"uses\" INTEGER NOT NULL);",
+            Sqlite::create_temp_table_sql::<Tag>().unwrap()
+        );
+        {
+            let connection = rusqlite::Connection::open(&path).unwrap();
+            <Tag as Crud<Sqlite>>::create_temp(&connection).unwrap();
+            let mut tag = Tag {
+                name: "scratch".into(),
+                uses: 1,
+            };
+            Crud::<Sqlite>::insert(&tag, &connection).unwrap();
+            tag.uses = 2;
+            Crud::<Sqlite>::update(&tag, &connection).unwrap();
+            assert_eq!(
+                vec![tag.clone()],
+                <Tag as Crud<Sqlite>>::read_all_vec(&connection).unwrap()
+            );
+            <Tag as Crud<Sqlite>>::validate_schema(&connection).unwrap();
+            assert!(Crud::<Sqlite>::delete(tag, &connection).unwrap());
+            assert_eq!(0, <Tag as Crud<Sqlite>>::count(&connection).unwrap());
+        }
+        // gone once the connection that created it is closed
+        let connection = rusqlite::Connection::open(&path).unwrap();
+        let err = <Tag as Crud<Sqlite>>::read_all_vec(&connection).unwrap_err();
+        assert_eq!(
+            "table `tag` does not exist; call create() first",
+            err.to_string()
+        );
+
+        let err = <Ledger as Crud<Sqlite>>::create_temp(&connection).unwrap_err();
+        assert_eq!(
+            "can't create a temporary `ledger`, it is audited",
+            err.to_string()
+        );
+    }
+
+    #[test]
+    fn delete_reports_removal() {
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+        <Tag as Crud<Sqlite>>::create(&connection).unwrap();
+        let tag = |name: &str| Tag {
+            name: name.to_string(),
+            uses: 0,
+        };
+        <Tag as Crud<Sqlite>>::insert_many(&connection, &[tag("a"), tag("b")]).unwrap();
+        assert!(Crud::<Sqlite>::delete(tag("a"), &connection).unwrap());
+        assert!(!Crud::<Sqlite>::delete(tag("a"), &connection).unwrap());
+        assert!(!Crud::<Sqlite>::delete(tag("z"), &connection).unwrap());
+        assert_eq!(1, <Tag as Crud<Sqlite>>::count(&connection).unwrap());
+    }
+
+    #[test]
+    fn read_all_ordered_by_primary_key() {
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+        <Tag as Crud<Sqlite>>::create(&connection).unwrap();
+        let tag = |name: &str| Tag {
+            name: name.to_string(),
+            uses: 0,
+        };
+        <Tag as Crud<Sqlite>>::insert_many(&connection, &[tag("b"), tag("c"), tag("a")]).unwrap();
+        Crud::<Sqlite>::delete(tag("b"), &connection).unwrap();
+        Crud::<Sqlite>::insert(&tag("b"), &connection).unwrap();
+        let names = || {
+            <Tag as Crud<Sqlite>>::read_all(&connection)
+                .unwrap()
+                .map(|r| r.unwrap().name)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(vec!["a", "b", "c"], names());
+        connection.execute_batch("VACUUM;").unwrap();
+        assert_eq!(vec!["a", "b", "c"], names());
+
+        // reads without a typed key fall back to `rowid` order
+        let rows = Sqlite::read_all_values(&connection, "tag", vec!["name"]).unwrap();
+        let names = rows
+            .into_iter()
+            .map(|row| row.unwrap()["name"].clone())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                Value::from("c".to_string()),
+                Value::from("a".to_string()),
+                Value::from("b".to_string())
+            ],
+            names
+        );
+    }
+
+    #[test]
+    fn primary_key() {
+        let player = PlayerV2 {
+            id: 3,
+            name: "tymigrawr".to_string(),
+            age: 66.5,
+        };
+        assert_eq!(("id", Value::Integer(3)), player.primary_key());
+        let shape = Shape::Rect {
+            id: 6,
+            w: 1.0,
+            h: 2.0,
+        };
+        assert_eq!(("id", Value::Integer(6)), shape.primary_key());
+    }
+
+    #[test]
+    fn field_values() {
+        let player = PlayerV2 {
+            id: 3,
+            name: "tymigrawr".to_string(),
+            age: 66.5,
+        };
+        let pairs = player.field_values();
+        let names = pairs.iter().map(|(f, _)| f.name).collect::<Vec<_>>();
+        assert_eq!(vec!["id", "name", "age"], names);
+        assert!(pairs[0].0.primary_key);
+        assert_eq!(ValueType::String, pairs[1].0.ty);
+        let values = pairs.into_iter().map(|(_, v)| v).collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                Value::Integer(3),
+                Value::String("tymigrawr".to_string()),
+                Value::Float(66.5)
+            ],
+            values
+        );
+    }
+
+    #[cfg(feature = "compress")]
+    #[derive(Debug, Clone, PartialEq, HasCrudFields)]
+    pub struct Article {
+        #[primary_key]
+        id: i64,
+        #[compress]
+        body: String,
+    }
+
+    #[cfg(feature = "compress")]
+    #[test]
+    fn compress_field() {
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+        <Article as Crud<Sqlite>>::create(&connection).unwrap();
+        let article = Article {
+            id: 0,
+            body: "tymigrawr! ".repeat(1000),
+        };
+        Crud::<Sqlite>::insert(&article, &connection).unwrap();
+        let read = <Article as Crud<Sqlite>>::read(&connection, 0)
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap();
+        assert_eq!(article, read);
+
+        let stored = connection
+            .query_row("SELECT body FROM article WHERE id = 0;", [], |row| {
+                row.get::<_, Vec<u8>>(0)
+            })
+            .unwrap();
+        assert!(stored.starts_with(tymigrawr::COMPRESSED_MAGIC));
+        assert!(stored.len() < article.body.len() / 10, "{}", stored.len());
+
+        // values written before the field was compressed are still readable
+        connection
+            .execute_batch("INSERT INTO article (id, body) VALUES (1, 'raw');")
+            .unwrap();
+        let read = <Article as Crud<Sqlite>>::read(&connection, 1)
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap();
+        assert_eq!("raw", read.body);
+    }
+
+    static DECODED_ON: std::sync::Mutex<Vec<std::thread::ThreadId>> =
+        std::sync::Mutex::new(Vec::new());
+
+    /// An integer that records which thread decoded it.
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct ThreadTracked(i64);
+
+    impl IsCrudField for ThreadTracked {
+        type MaybeSelf = Option<Self>;
+
+        fn field() -> CrudField {
+            i64::field()
+        }
+
+        fn into_value(&self) -> Value {
+            self.0.into_value()
+        }
+
+        fn maybe_from_value(value: &Value) -> Option<Self> {
+            DECODED_ON.lock().unwrap().push(std::thread::current().id());
+            value.as_i64().map(ThreadTracked)
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq, HasCrudFields)]
+    pub struct Reading {
+        #[primary_key]
+        id: i64,
+        tracked: ThreadTracked,
+    }
+
+    #[test]
+    fn read_all_parallel() {
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+        <Reading as Crud<Sqlite>>::create(&connection).unwrap();
+        let readings = (0..10_000)
+            .map(|id| Reading {
+                id,
+                tracked: ThreadTracked(id * 2),
+            })
+            .collect::<Vec<_>>();
+        <Reading as Crud<Sqlite>>::insert_many(&connection, &readings).unwrap();
+
+        DECODED_ON.lock().unwrap().clear();
+        let read = <Reading as Crud<Sqlite>>::read_all_parallel(&connection, 4).unwrap();
+        assert_eq!(readings, read);
+
+        let mut threads = DECODED_ON.lock().unwrap().clone();
+        assert_eq!(readings.len(), threads.len());
+        threads.sort_by_key(|id| format!("{id:?}"));
+        threads.dedup();
+        assert_eq!(4, threads.len());
+        assert!(!threads.contains(&std::thread::current().id()));
+
+        assert!(<Reading as Crud<Sqlite>>::read_all_parallel(&connection, 0).is_ok());
+    }
+
+    thread_local! {
+        static LABEL_COPIES: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+    }
+
+    /// A string that counts how often it's read from an owned `Value`, which
+    /// copies it once more than reading it from a `ValueRef`.
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct Label(String);
+
+    impl IsCrudField for Label {
+        type MaybeSelf = Option<Self>;
+
+        fn field() -> CrudField {
+            String::field()
+        }
+
+        fn into_value(&self) -> Value {
+            self.0.into_value()
+        }
+
+        fn maybe_from_value(value: &Value) -> Option<Self> {
+            LABEL_COPIES.with(|copies| copies.set(copies.get() + 1));
+            value.as_string().cloned().map(Label)
+        }
+
+        fn maybe_from_value_ref(value: ValueRef<'_>) -> Option<Self> {
+            match value {
+                ValueRef::String(s) => Some(Label(s.to_owned())),
+                _ => None,
             }
         }
     }
 
-    impl From<PlayerV1> for PlayerV2 {
-        fn from(value: PlayerV1) -> PlayerV2 {
-            PlayerV2 {
-                id: value.id,
-                name: value.name,
-                age: 0.0,
-            }
-        }
+    #[derive(Debug, Clone, PartialEq, HasCrudFields)]
+    pub struct Labelled {
+        #[primary_key]
+        id: i64,
+        label: Label,
+        data: Vec<u8>,
+        note: Option<String>,
+    }
+
+    #[test]
+    fn value_ref_reads() {
+        for value in [
+            Value::Integer(6),
+            Value::Float(6.6),
+            Value::String("rawr".into()),
+            Value::Bytes(vec![0, 159]),
+            Value::Boolean(true),
+            Value::None,
+        ] {
+            assert_eq!(value, value.as_value_ref().to_value());
+        }
+
+        let labelled = (0..3)
+            .map(|id| Labelled {
+                id,
+                label: Label(format!("label_{id}")),
+                data: vec![id as u8; 3],
+                note: (id == 1).then(|| "rawr".to_string()),
+            })
+            .collect::<Vec<_>>();
+        for row in labelled.iter() {
+            let fields = row.as_crud_fields();
+            let by_ref = Labelled::try_from_row_ref(|name| fields[name].as_value_ref()).unwrap();
+            let by_value = Labelled::try_from_row(|name| fields[name].clone()).unwrap();
+            assert_eq!(by_value, by_ref);
+            assert_eq!(row, &by_ref);
+        }
+
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+        <Labelled as Crud<Sqlite>>::create(&connection).unwrap();
+        <Labelled as Crud<Sqlite>>::insert_many(&connection, &labelled).unwrap();
+        LABEL_COPIES.with(|copies| copies.set(0));
+        assert_eq!(
+            labelled,
+            <Labelled as Crud<Sqlite>>::read_all_vec(&connection).unwrap()
+        );
+        let read = <Labelled as Crud<Sqlite>>::read(&connection, 1)
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(vec![labelled[1].clone()], read);
+        // rows are read through `ValueRef`s, never an extra owned `Value`
+        assert_eq!(0, LABEL_COPIES.with(|copies| copies.get()));
+    }
+
+    #[derive(Debug, Clone, PartialEq, HasCrudFields)]
+    #[append_only]
+    pub struct Event {
+        #[primary_key]
+        id: i64,
+        payload: String,
+    }
+
+    #[test]
+    fn append_only() {
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+        <Event as Crud<Sqlite>>::create(&connection).unwrap();
+        let event = Event {
+            id: 1,
+            payload: "created".into(),
+        };
+        Crud::<Sqlite>::insert(&event, &connection).unwrap();
+        let events = <Event as Crud<Sqlite>>::read(&connection, 1)
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(vec![event.clone()], events);
+
+        let updated = Event {
+            payload: "edited".into(),
+            ..event.clone()
+        };
+        let err = Crud::<Sqlite>::update(&updated, &connection).unwrap_err();
+        assert_eq!(
+            "can't update `event`, it is an append-only table",
+            err.to_string()
+        );
+        assert!(Crud::<Sqlite>::update_diff_count(&updated, &event, &connection).is_err());
+        let err = Crud::<Sqlite>::delete(event.clone(), &connection).unwrap_err();
+        assert_eq!(
+            "can't delete from `event`, it is an append-only table",
+            err.to_string()
+        );
+        assert!(<Event as Crud<Sqlite>>::clear(&connection).is_err());
+        assert_eq!(1, <Event as Crud<Sqlite>>::count(&connection).unwrap());
+    }
+
+    #[test]
+    fn delete_all_where() {
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+        query_players(&connection);
+        let err = <PlayerV2 as Crud<Sqlite>>::delete_all_where(&connection, &[]).unwrap_err();
+        assert_eq!(
+            "refusing to delete from `playerv2` without conditions, use `clear` to delete every row",
+            err.to_string()
+        );
+        assert_eq!(5, <PlayerV2 as Crud<Sqlite>>::count(&connection).unwrap());
+
+        let deleted = <PlayerV2 as Crud<Sqlite>>::delete_all_where(
+            &connection,
+            &[
+                Condition::new("age", Comparison::Gt, 18.0),
+                Condition::new("name", Comparison::Like, "a%".to_string()),
+            ],
+        )
+        .unwrap();
+        assert_eq!(2, deleted);
+        let names = <PlayerV2 as Crud<Sqlite>>::read_all(&connection)
+            .unwrap()
+            .map(|r| r.unwrap().name)
+            .collect::<Vec<_>>();
+        assert_eq!(vec!["alice", "bob", "carl"], names);
+
+        assert_eq!(3, <PlayerV2 as Crud<Sqlite>>::clear(&connection).unwrap());
+        assert_eq!(0, <PlayerV2 as Crud<Sqlite>>::count(&connection).unwrap());
+    }
+
+    #[test]
+    fn delete_where() {
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+        query_players(&connection);
+        let err = <PlayerV2 as Crud<Sqlite>>::delete_where(&connection, &Query::new()).unwrap_err();
+        assert_eq!(
+            "refusing to delete from `playerv2` without conditions, use `clear` to delete every row",
+            err.to_string()
+        );
+
+        let query = Query::<PlayerV2>::new().and("age", Comparison::Lt, 20.0);
+        assert_eq!(
+            3,
+            <PlayerV2 as Crud<Sqlite>>::delete_where(&connection, &query).unwrap()
+        );
+        let names = <PlayerV2 as Crud<Sqlite>>::read_all(&connection)
+            .unwrap()
+            .map(|r| r.unwrap().name)
+            .collect::<Vec<_>>();
+        assert_eq!(vec!["anne", "carl"], names);
+    }
+
+    #[cfg(feature = "csv")]
+    #[derive(Debug, Clone, PartialEq, HasCrudFields)]
+    pub struct Quote {
+        #[primary_key]
+        pub id: i64,
+        pub text: String,
+        pub author: Option<String>,
+        pub score: f64,
+        pub data: Vec<u8>,
+    }
+
+    #[cfg(feature = "csv")]
+    #[test]
+    fn csv_roundtrip() {
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+        <Quote as Crud<Sqlite>>::create(&connection).unwrap();
+        let quotes = vec![
+            Quote {
+                id: 0,
+                text: "rawr, said the \"dinosaur\"\nloudly".into(),
+                author: None,
+                score: 0.1,
+                data: vec![0, 159, 146, 150],
+            },
+            Quote {
+                id: 1,
+                text: String::new(),
+                author: Some("anne".into()),
+                score: -6.5,
+                data: vec![],
+            },
+        ];
+        <Quote as Crud<Sqlite>>::insert_many(&connection, &quotes).unwrap();
+
+        let mut csv = vec![];
+        assert_eq!(
+            2,
+            <Quote as Crud<Sqlite>>::export_csv(&connection, &mut csv).unwrap()
+        );
+        let csv = String::from_utf8(csv).unwrap();
+        assert!(csv.starts_with("id,text,author,score,data\n"), "{csv}");
+        assert!(csv.contains(",,0.1,AJ+Slg==\n"), "{csv}");
+
+        <Quote as Crud<Sqlite>>::clear(&connection).unwrap();
+        assert_eq!(
+            2,
+            <Quote as Crud<Sqlite>>::import_csv(&connection, csv.as_bytes()).unwrap()
+        );
+        let from_db = <Quote as Crud<Sqlite>>::read_all(&connection)
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(quotes, from_db);
+
+        let err = <Quote as Crud<Sqlite>>::import_csv(&connection, "id,text\n2,hi\n".as_bytes())
+            .unwrap_err();
+        assert_eq!("csv has no column `author`", err.to_string());
+    }
+
+    #[test]
+    fn transaction() {
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+        <PlayerV1 as Crud<Sqlite>>::create(&connection).unwrap();
+        let players = (0..3)
+            .map(|id| PlayerV1 {
+                id,
+                name: format!("tymigrawr_{id}"),
+            })
+            .collect::<Vec<_>>();
+
+        // dropping without committing rolls back
+        {
+            let tx = Transaction::new(&connection).unwrap();
+            <PlayerV1 as Crud<Sqlite>>::insert_many(tx.connection(), &players[..2]).unwrap();
+            Crud::<Sqlite>::insert(&players[2], tx.connection()).unwrap();
+            assert_eq!(
+                3,
+                <PlayerV1 as Crud<Sqlite>>::count(tx.connection()).unwrap()
+            );
+        }
+        assert_eq!(0, <PlayerV1 as Crud<Sqlite>>::count(&connection).unwrap());
+
+        let tx = Transaction::new(&connection).unwrap();
+        <PlayerV1 as Crud<Sqlite>>::insert_many(tx.connection(), &players).unwrap();
+        tx.commit().unwrap();
+        assert_eq!(3, <PlayerV1 as Crud<Sqlite>>::count(&connection).unwrap());
+
+        let tx = Transaction::new(&connection).unwrap();
+        <PlayerV1 as Crud<Sqlite>>::clear(tx.connection()).unwrap();
+        tx.rollback().unwrap();
+        assert_eq!(3, <PlayerV1 as Crud<Sqlite>>::count(&connection).unwrap());
+    }
+
+    #[test]
+    fn read_like() {
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+        <PlayerV1 as Crud<Sqlite>>::create(&connection).unwrap();
+        let names = ["100% rawr", "100x rawr", "rawr_1", "rawrs1", "back\\slash"];
+        for (id, name) in (0..).zip(names) {
+            let name = name.to_string();
+            Crud::<Sqlite>::insert(&PlayerV1 { id, name }, &connection).unwrap();
+        }
+        let like = |pattern| {
+            <PlayerV1 as Crud<Sqlite>>::read_like(&connection, "name", pattern)
+                .unwrap()
+                .map(|r| r.unwrap().name)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!("100\\%%", LikePattern::starts_with("100%").as_str());
+        assert_eq!(vec!["100% rawr"], like(LikePattern::starts_with("100%")));
+        assert_eq!(vec!["100% rawr"], like(LikePattern::contains("0% r")));
+        assert_eq!(vec!["rawr_1"], like(LikePattern::ends_with("r_1")));
+        assert_eq!(
+            vec!["back\\slash"],
+            like(LikePattern::ends_with("k\\slash"))
+        );
+        assert_eq!(vec!["rawr_1"], like(LikePattern::exact("RAWR_1")));
+        assert!(like(LikePattern::exact("rawr")).is_empty());
+
+        let err = <PlayerV1 as Crud<Sqlite>>::read_like(
+            &connection,
+            "nickname",
+            LikePattern::exact("rawr"),
+        )
+        .err()
+        .unwrap();
+        assert_eq!("table `playerv1` has no column `nickname`", err.to_string());
+    }
+
+    #[test]
+    fn update_where() {
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+        query_players(&connection);
+        let updated = <PlayerV2 as Crud<Sqlite>>::update_where(
+            &connection,
+            "name",
+            "minor".to_string(),
+            "age",
+            Comparison::Lt,
+            18.0,
+        )
+        .unwrap();
+        assert_eq!(2, updated);
+        let names = <PlayerV2 as Crud<Sqlite>>::read_all(&connection)
+            .unwrap()
+            .map(|r| r.unwrap().name)
+            .collect::<Vec<_>>();
+        assert_eq!(vec!["minor", "minor", "arnold", "anne", "carl"], names);
+
+        let err = <PlayerV2 as Crud<Sqlite>>::update_where(
+            &connection,
+            "nickname",
+            "rawr".to_string(),
+            "age",
+            Comparison::Lt,
+            18.0,
+        )
+        .unwrap_err();
+        assert_eq!("table `playerv2` has no column `nickname`", err.to_string());
+    }
+
+    #[test]
+    fn query_empty() {
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+        query_players(&connection);
+        let all = <PlayerV2 as Crud<Sqlite>>::read_all(&connection)
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect::<Vec<_>>();
+        let queried = Query::<PlayerV2>::new()
+            .execute(&connection)
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(5, queried.len());
+        assert_eq!(all, queried);
     }
 
+    #[cfg(feature = "json")]
     #[derive(Debug, Clone, PartialEq, HasCrudFields)]
-    pub struct PlayerV2 {
+    pub struct Post {
         #[primary_key]
         pub id: i64,
-        pub name: String,
-        pub age: f32,
+        pub tags: tymigrawr::Json<Vec<String>>,
+        pub thumbnail: Vec<u8>,
     }
 
+    #[cfg(feature = "json")]
     #[test]
-    fn p1_crud() {
-        let connection = sqlite::open(":memory:").unwrap();
-        PlayerV1::create(&connection).unwrap();
-        let first_player = PlayerV1 {
+    fn json_vec_roundtrip() {
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+        <Post as Crud<Sqlite>>::create(&connection).unwrap();
+        let post = Post {
             id: 0,
-            name: "tymigrawr".to_string(),
+            tags: vec!["dinosaurs".to_string(), "migrations".to_string()].into(),
+            thumbnail: vec![0, 1, 2, 3],
         };
-        first_player.insert(&connection).unwrap();
-        let player = PlayerV1::read(&connection, 0)
+        Crud::<Sqlite>::insert(&post, &connection).unwrap();
+        let post_from_db = <Post as Crud<Sqlite>>::read(&connection, 0)
             .unwrap()
             .next()
             .unwrap()
             .unwrap();
-        assert_eq!(first_player, player);
-        let mut second_player = PlayerV1 {
+        assert_eq!(post, post_from_db);
+
+        let types = connection
+            .query_row(
+                "SELECT typeof(tags), typeof(thumbnail) FROM post",
+                [],
+                |row| Ok(vec![row.get::<_, String>(0)?, row.get::<_, String>(1)?]),
+            )
+            .unwrap();
+        assert_eq!(vec!["text", "blob"], types);
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn json_export_import() {
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+        <Post as Crud<Sqlite>>::create(&connection).unwrap();
+        let posts = (0..3)
+            .map(|id| Post {
+                id,
+                tags: vec![format!("tag {id}")].into(),
+                thumbnail: vec![0, 159, id as u8],
+            })
+            .collect::<Vec<_>>();
+        <Post as Crud<Sqlite>>::insert_many(&connection, &posts).unwrap();
+
+        let json = <Post as Crud<Sqlite>>::export_json(&connection).unwrap();
+        assert_eq!(
+            serde_json::json!({"id": 0, "tags": "[\"tag 0\"]", "thumbnail": "AJ8A"}),
+            json[0]
+        );
+
+        <Post as Crud<Sqlite>>::clear(&connection).unwrap();
+        assert_eq!(
+            3,
+            <Post as Crud<Sqlite>>::import_json(&connection, &json).unwrap()
+        );
+        let from_db = <Post as Crud<Sqlite>>::read_all(&connection)
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(posts, from_db);
+
+        let err =
+            <Post as Crud<Sqlite>>::import_json(&connection, &serde_json::json!({})).unwrap_err();
+        assert_eq!("json is not an array", err.to_string());
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn json_bytes_roundtrip() {
+        let field = CrudField {
+            name: "data",
+            ty: ValueType::Bytes,
+            ..Default::default()
+        };
+        let bytes = Value::Bytes(vec![0, 159, 146, 150, 255]);
+        let json = crate::json::value_to_json(&bytes);
+        assert_eq!(serde_json::Value::from("AJ+Slv8="), json);
+        assert_eq!(bytes, crate::json::value_from_json(&json, &field).unwrap());
+        assert_eq!(
+            Value::None,
+            crate::json::value_from_json(&serde_json::Value::Null, &field).unwrap()
+        );
+        assert!(crate::json::value_from_json(&serde_json::Value::from(1), &field).is_err());
+    }
+
+    #[derive(Debug, Clone, PartialEq, HasCrudFields)]
+    pub enum Shape {
+        Circle {
+            #[primary_key]
+            id: i64,
+            r: f64,
+        },
+        Rect {
+            id: i64,
+            w: f64,
+            h: f64,
+        },
+    }
+
+    #[test]
+    fn enum_crud() {
+        let field_names = Shape::crud_fields()
+            .iter()
+            .map(|field| (field.name, field.nullable))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                ("kind", false),
+                ("id", false),
+                ("r", true),
+                ("w", true),
+                ("h", true)
+            ],
+            field_names
+        );
+
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+        <Shape as Crud<Sqlite>>::create(&connection).unwrap();
+        let circle = Shape::Circle { id: 0, r: 1.5 };
+        let rect = Shape::Rect {
             id: 1,
-            name: "developer".to_string(),
+            w: 2.0,
+            h: 3.0,
         };
-        second_player.insert(&connection).unwrap();
-        let player = PlayerV1::read(&connection, 1)
+        Crud::<Sqlite>::insert(&circle, &connection).unwrap();
+        Crud::<Sqlite>::insert(&rect, &connection).unwrap();
+
+        let circle_from_db = <Shape as Crud<Sqlite>>::read(&connection, 0)
             .unwrap()
             .next()
             .unwrap()
             .unwrap();
-        assert_eq!(second_player, player);
-
-        let mut p1 = PlayerV1::read(&connection, first_player.id).unwrap();
-        assert_eq!(first_player, p1.next().unwrap().unwrap());
-        let mut p2 = PlayerV1::read(&connection, second_player.id).unwrap();
-        assert_eq!(second_player, p2.next().unwrap().unwrap());
-
-        second_player.name = "software engineer".to_string();
-        second_player.update(&connection).unwrap();
-        let p2 = PlayerV1::read(&connection, second_player.id)
+        assert_eq!(circle, circle_from_db);
+        let rect_from_db = <Shape as Crud<Sqlite>>::read(&connection, 1)
             .unwrap()
             .next()
             .unwrap()
             .unwrap();
-        assert_eq!(second_player, p2);
+        assert_eq!(rect, rect_from_db);
+    }
 
-        second_player.delete(&connection).unwrap();
-        let players = PlayerV1::read(&connection, p2.id)
-            .unwrap()
-            .map(|p| p.unwrap())
-            .collect::<Vec<_>>();
-        assert!(players.is_empty());
+    #[test]
+    fn validate_schema() {
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+        <PlayerV2 as Crud<Sqlite>>::create(&connection).unwrap();
+        <PlayerV2 as Crud<Sqlite>>::validate_schema(&connection).unwrap();
+
+        let err = <PlayerV1 as Crud<Sqlite>>::validate_schema(&connection).unwrap_err();
+        assert!(err.to_string().contains("`playerv1` does not exist"));
+
+        connection
+            .execute_batch("CREATE TABLE playerv1 (id INTEGER PRIMARY KEY, name BLOB, age FLOAT);")
+            .unwrap();
+        let err = <PlayerV1 as Crud<Sqlite>>::validate_schema(&connection)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("column `name` has type `BLOB`, expected `TEXT`"));
+        assert!(err.contains("unexpected column `age`"));
+
+        connection.execute_batch("DROP TABLE playerv1;").unwrap();
+        connection
+            .execute_batch("CREATE TABLE playerv1 (id INTEGER PRIMARY KEY);")
+            .unwrap();
+        let err = <PlayerV1 as Crud<Sqlite>>::validate_schema(&connection)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("missing column `name`"));
     }
 
     #[test]
-    fn p2_crud() {
-        let connection = sqlite::open(":memory:").unwrap();
-        PlayerV2::create(&connection).unwrap();
-        let mut first_player = PlayerV2 {
+    fn sqlite_dialect() {
+        assert_eq!("INTEGER", SqliteDialect::column_type(&ValueType::Integer));
+        assert_eq!("REAL", SqliteDialect::column_type(&ValueType::Float));
+        assert_eq!("TEXT", SqliteDialect::column_type(&ValueType::String));
+        assert_eq!("BLOB", SqliteDialect::column_type(&ValueType::Bytes));
+        assert_eq!(":p0", SqliteDialect::placeholder(0));
+        assert_eq!(":p12", SqliteDialect::placeholder(12));
+        assert_eq!("\"name\"", SqliteDialect::quote_ident("name"));
+        assert_eq!(
+            "\"say \"\"rawr\"\"\"",
+            SqliteDialect::quote_ident("say \"rawr\"")
+        );
+    }
+
+    /// Every statement quotes its identifiers, so keywords are fine as table
+    /// and column names.
+    #[test]
+    fn sqlite_keyword_identifiers() {
+        #[derive(Debug, Clone, PartialEq, HasCrudFields)]
+        struct Order {
+            #[primary_key]
+            id: i64,
+            group: String,
+            select: i64,
+        }
+
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+        <Order as Crud<Sqlite>>::create(&connection).unwrap();
+        <Order as Crud<Sqlite>>::validate_schema(&connection).unwrap();
+        let mut order = Order {
             id: 0,
-            name: "tymigrawr".to_string(),
-            age: 0.1,
+            group: "dinos".to_string(),
+            select: 1,
         };
-        first_player.insert(&connection).unwrap();
-        let mut p1 = PlayerV2::read(&connection, first_player.id).unwrap();
-        assert_eq!(first_player, p1.next().unwrap().unwrap());
-
-        first_player.name = "software engineer".to_string();
-        first_player.update(&connection).unwrap();
-        let p2 = PlayerV2::read(&connection, first_player.id)
+        Crud::<Sqlite>::insert(&order, &connection).unwrap();
+        assert!(!Crud::<Sqlite>::insert_or_ignore(&order, &connection).unwrap());
+        let old = order.clone();
+        order.select = 2;
+        assert_eq!(
+            1,
+            Crud::<Sqlite>::update_diff_count(&order, &old, &connection).unwrap()
+        );
+        order.group = "raptors".to_string();
+        Crud::<Sqlite>::update(&order, &connection).unwrap();
+        assert_eq!(
+            1,
+            <Order as Crud<Sqlite>>::update_where(
+                &connection,
+                "select",
+                3,
+                "group",
+                Comparison::Eq,
+                "raptors".to_string()
+            )
             .unwrap()
-            .next()
+        );
+        let read = <Order as Crud<Sqlite>>::read_where(&connection, "select", "=", 3)
             .unwrap()
+            .map(|r| r.unwrap())
+            .collect::<Vec<_>>();
+        order.select = 3;
+        assert_eq!(vec![order.clone()], read);
+        let query = Query::<Order>::new().and("group", Comparison::Eq, "raptors".to_string());
+        assert_eq!(
+            1,
+            <Order as Crud<Sqlite>>::delete_where(&connection, &query).unwrap()
+        );
+        assert_eq!(0, <Order as Crud<Sqlite>>::count(&connection).unwrap());
+    }
+
+    #[test]
+    fn sqlite_create_field() {
+        let field = CrudField {
+            name: "score",
+            ty: ValueType::Float,
+            primary_key: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            "\"score\" REAL PRIMARY KEY NOT NULL",
+            field.sqlite_create_field()
+        );
+
+        let field = CrudField {
+            name: "nickname",
+            ty: ValueType::String,
+            nullable: true,
+            ..Default::default()
+        };
+        assert_eq!("\"nickname\" TEXT", field.sqlite_create_field());
+    }
+
+    #[test]
+    fn check_constraint() {
+        #[derive(Debug, Clone, PartialEq, HasCrudFields)]
+        struct Member {
+            #[primary_key]
+            id: i64,
+            #[check = "age >= 0"]
+            age: i64,
+        }
+
+        assert_eq!(
+            "\"age\" INTEGER NOT NULL CHECK (age >= 0)",
+            Member::crud_fields()[1].sqlite_create_field()
+        );
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+        <Member as Crud<Sqlite>>::create(&connection).unwrap();
+        Crud::<Sqlite>::insert(&Member { id: 0, age: 0 }, &connection).unwrap();
+        assert!(Crud::<Sqlite>::insert(&Member { id: 1, age: -1 }, &connection).is_err());
+        assert_eq!(1, <Member as Crud<Sqlite>>::count(&connection).unwrap());
+    }
+
+    #[test]
+    fn foreign_key() {
+        #[derive(Debug, Clone, PartialEq, HasCrudFields)]
+        struct Team {
+            #[primary_key]
+            id: i64,
+            label: String,
+        }
+
+        #[derive(Debug, Clone, PartialEq, HasCrudFields)]
+        struct Score {
+            #[primary_key]
+            id: i64,
+            #[references(team, id)]
+            team_id: i64,
+            points: i64,
+        }
+
+        assert_eq!(
+            "CREATE TABLE IF NOT EXISTS \"score\" (\"id\" INTEGER PRIMARY KEY NOT NULL, \
+             \"team_id\" INTEGER NOT NULL, \"points\" INTEGER NOT NULL, \
+             FOREIGN KEY (\"team_id\") REFERENCES \"team\"(\"id\"));",
+            Sqlite::create_table_sql::<Score>()
+        );
+        // a prefix applies to the referenced table too
+        crate::with_table_prefix("t_", || {
+            assert_eq!(
+                Some("FOREIGN KEY (\"team_id\") REFERENCES \"t_team\"(\"id\")".to_string()),
+                Score::crud_fields()[1].foreign_key_clause::<SqliteDialect>()
+            );
+        });
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+        <Team as Crud<Sqlite>>::create(&connection).unwrap();
+        <Score as Crud<Sqlite>>::create(&connection).unwrap();
+        Crud::<Sqlite>::insert(
+            &Team {
+                id: 0,
+                label: "dinos".to_string(),
+            },
+            &connection,
+        )
+        .unwrap();
+        Crud::<Sqlite>::insert(
+            &Score {
+                id: 0,
+                team_id: 0,
+                points: 3,
+            },
+            &connection,
+        )
+        .unwrap();
+        let dangling = Score {
+            id: 1,
+            team_id: 1,
+            points: 3,
+        };
+        let err = Crud::<Sqlite>::insert(&dangling, &connection)
+            .err()
             .unwrap();
-        assert_eq!(first_player, p2);
+        assert!(
+            format!("{err:?}").contains("FOREIGN KEY constraint failed"),
+            "{err:?}"
+        );
+        assert_eq!(1, <Score as Crud<Sqlite>>::count(&connection).unwrap());
+    }
 
-        first_player.delete(&connection).unwrap();
-        let players = PlayerV2::read(&connection, p2.id)
-            .unwrap()
-            .map(|p| p.unwrap())
-            .collect::<Vec<_>>();
-        assert!(players.is_empty());
+    #[test]
+    fn healthcheck() {
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+        <PlayerV1 as Crud<Sqlite>>::create(&connection).unwrap();
+        <PlayerV1 as Crud<Sqlite>>::healthcheck(&connection).unwrap();
+        let err = <PlayerV2 as Crud<Sqlite>>::healthcheck(&connection).unwrap_err();
+        assert!(err.to_string().contains("`playerv2` does not exist"));
     }
 
     #[derive(Debug, Clone, PartialEq, HasCrudFields)]
@@ -612,20 +4937,380 @@ mod test {
                 description: format!("{age} years old"),
             }
         }
-    }
+    }
+
+    impl From<PlayerV3> for PlayerV2 {
+        fn from(value: PlayerV3) -> Self {
+            let PlayerV3 {
+                id,
+                name,
+                description: _,
+            } = value;
+            PlayerV2 { id, name, age: 0.0 }
+        }
+    }
+
+    impl MigrationStep<PlayerV1> for PlayerV2 {}
+    impl MigrationStep<PlayerV2> for PlayerV3 {}
+    // backwards
+    impl MigrationStep<PlayerV3> for PlayerV2 {}
+    impl MigrationStep<PlayerV2> for PlayerV1 {}
+
+    pub type Player = PlayerV3;
+
+    #[test]
+    fn migrations_inspect() {
+        let migrations = Migrations::<PlayerV1, Sqlite>::default();
+        assert_eq!(1, migrations.len());
+        assert!(!migrations.is_empty());
+        let migrations = migrations
+            .with_version::<PlayerV2>()
+            .with_version::<PlayerV3>();
+        assert_eq!(3, migrations.len());
+        assert_eq!(
+            vec!["playerv1", "playerv2", "playerv3"],
+            migrations.table_names()
+        );
+    }
+
+    #[test]
+    fn migrate_single_version() {
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+        <PlayerV1 as Crud<Sqlite>>::create(&connection).unwrap();
+        let player = PlayerV1 {
+            id: 0,
+            name: "tymigrawr".to_string(),
+        };
+        Crud::<Sqlite>::insert(&player, &connection).unwrap();
+        Migrations::<PlayerV1, Sqlite>::default()
+            .run(&connection)
+            .unwrap();
+        let players = <PlayerV1 as Crud<Sqlite>>::read_all(&connection)
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(vec![player], players);
+    }
+
+    mod same_table {
+        use super::*;
+
+        /// Shares the `playerv2` table with [`super::PlayerV2`].
+        #[derive(Debug, Clone, PartialEq, HasCrudFields)]
+        pub struct PlayerV2 {
+            #[primary_key]
+            pub id: i64,
+            pub name: String,
+            pub age: f32,
+        }
+
+        impl From<super::PlayerV2> for PlayerV2 {
+            fn from(value: super::PlayerV2) -> Self {
+                let super::PlayerV2 { id, name, age } = value;
+                PlayerV2 { id, name, age }
+            }
+        }
+
+        impl MigrationStep<super::PlayerV2> for PlayerV2 {}
+    }
+
+    #[test]
+    fn migrate_identical_table_name() {
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+        <PlayerV1 as Crud<Sqlite>>::create(&connection).unwrap();
+        <PlayerV2 as Crud<Sqlite>>::create(&connection).unwrap();
+        let old = PlayerV1 {
+            id: 0,
+            name: "old".to_string(),
+        };
+        Crud::<Sqlite>::insert(&old, &connection).unwrap();
+        let current = PlayerV2 {
+            id: 1,
+            name: "current".to_string(),
+            age: 66.5,
+        };
+        Crud::<Sqlite>::insert(&current, &connection).unwrap();
+
+        Migrations::<PlayerV1, Sqlite>::default()
+            .with_version::<PlayerV2>()
+            .with_version::<same_table::PlayerV2>()
+            .run(&connection)
+            .unwrap();
+
+        assert_eq!(0, <PlayerV1 as Crud<Sqlite>>::count(&connection).unwrap());
+        let players = <same_table::PlayerV2 as Crud<Sqlite>>::read_all(&connection)
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect::<Vec<_>>();
+        let expected = vec![PlayerV2::from(old), current]
+            .into_iter()
+            .map(same_table::PlayerV2::from)
+            .collect::<Vec<_>>();
+        assert_eq!(expected, players);
+    }
+
+    #[test]
+    fn migrate_from_registry() {
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+        <PlayerV1 as Crud<Sqlite>>::create(&connection).unwrap();
+        <PlayerV2 as Crud<Sqlite>>::create(&connection).unwrap();
+        <PlayerV3 as Crud<Sqlite>>::create(&connection).unwrap();
+        let players_v1 = (0..3)
+            .map(|id| PlayerV1 {
+                id,
+                name: format!("tymigrawr_{id}"),
+            })
+            .collect::<Vec<_>>();
+        <PlayerV1 as Crud<Sqlite>>::insert_many(&connection, &players_v1).unwrap();
+
+        let mut registry = MigrationRegistry::default();
+        // registered out of order, eg. by different plugins
+        registry.register::<PlayerV2, PlayerV3>();
+        registry.register::<PlayerV1, PlayerV2>();
+        let migrations = Migrations::<Player, Sqlite>::from_registry(&registry).unwrap();
+        assert_eq!(3, migrations.all.len());
+        migrations.run(&connection).unwrap();
+
+        assert_eq!(
+            0,
+            <PlayerV1 as Crud<Sqlite>>::read_all(&connection)
+                .unwrap()
+                .count()
+        );
+        let players = <Player as Crud<Sqlite>>::read_all(&connection)
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect::<Vec<_>>();
+        let expected = players_v1
+            .into_iter()
+            .map(PlayerV2::from)
+            .map(Player::from)
+            .collect::<Vec<_>>();
+        assert_eq!(expected, players);
+
+        registry.register::<PlayerV2, PlayerV1>();
+        let err = Migrations::<Player, Sqlite>::from_registry(&registry)
+            .err()
+            .unwrap();
+        assert!(err.to_string().ends_with("form a cycle"), "{err}");
+    }
+
+    #[test]
+    fn migrate_validate() {
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+        <PlayerV1 as Crud<Sqlite>>::create(&connection).unwrap();
+        let migrations = || {
+            Migrations::<PlayerV1, Sqlite>::default()
+                .with_version::<PlayerV2>()
+                .with_version::<Player>()
+        };
+        let err = migrations().validate(&connection).unwrap_err();
+        assert_eq!(
+            "can't migrate to `playerv3`, tables of earlier versions are missing: `playerv2`",
+            err.to_string()
+        );
+
+        // the current table is created by `run`
+        <PlayerV2 as Crud<Sqlite>>::create(&connection).unwrap();
+        migrations().validate(&connection).unwrap();
+        assert!(!Sqlite::table_exists(&connection, "playerv3").unwrap());
+        migrations().run(&connection).unwrap();
+        assert!(Sqlite::table_exists(&connection, "playerv3").unwrap());
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    #[tracing_test::traced_test]
+    fn migrate_tracing() {
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+        <PlayerV1 as Crud<Sqlite>>::create(&connection).unwrap();
+        <PlayerV2 as Crud<Sqlite>>::create(&connection).unwrap();
+        for id in 0..3 {
+            let player = PlayerV1 {
+                id,
+                name: format!("tymigrawr_{id}"),
+            };
+            Crud::<Sqlite>::insert(&player, &connection).unwrap();
+        }
+        Migrations::<PlayerV1, Sqlite>::default()
+            .with_version::<PlayerV2>()
+            .run(&connection)
+            .unwrap();
+        assert!(logs_contain("migrate_table{table=\"playerv1\" entries=3}"));
+        assert!(logs_contain("migrated entries=3"));
+    }
+
+    #[test]
+    fn migrate_lossy() {
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+        <PlayerV2 as Crud<Sqlite>>::create(&connection).unwrap();
+        <PlayerV1 as Crud<Sqlite>>::create(&connection).unwrap();
+        let player = PlayerV2 {
+            id: 0,
+            name: "tymigrawr".to_string(),
+            age: 66.0,
+        };
+        Crud::<Sqlite>::insert(&player, &connection).unwrap();
+
+        let migrations = Migrations::<PlayerV2, Sqlite>::default().with_version::<PlayerV1>();
+        let lossy = migrations
+            .all
+            .iter()
+            .filter(|m| m.is_lossy())
+            .collect::<Vec<_>>();
+        assert_eq!(1, lossy.len());
+        assert_eq!(&["age"], lossy[0].dropped_columns());
+
+        let err = migrations.deny_lossy().run(&connection).unwrap_err();
+        assert_eq!(
+            "migrating to playerv1 would discard columns age",
+            err.to_string()
+        );
+        // nothing was migrated
+        let players = <PlayerV2 as Crud<Sqlite>>::read_all(&connection)
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(vec![player], players);
+        assert_eq!(
+            0,
+            <PlayerV1 as Crud<Sqlite>>::read_all(&connection)
+                .unwrap()
+                .count()
+        );
+
+        // forward migrations only add columns
+        let migrations = Migrations::<PlayerV1, Sqlite>::default().with_version::<PlayerV2>();
+        assert!(migrations.all.iter().all(|m| !m.is_lossy()));
+    }
+
+    #[derive(Debug, Clone, PartialEq, HasCrudFields)]
+    pub struct BookV1 {
+        #[primary_key]
+        pub id: i64,
+        pub title: String,
+        pub pages: i64,
+    }
+
+    #[derive(Debug, Clone, PartialEq, HasCrudFields)]
+    #[migrate_from(BookV1)]
+    pub struct BookV2 {
+        #[primary_key]
+        pub id: i64,
+        pub title: String,
+        #[migrate_default]
+        pub rating: f64,
+        #[from_expr = "prev.title.to_lowercase().replace(' ', \"-\")"]
+        pub slug: String,
+    }
+
+    #[test]
+    fn derive_migrate_from() {
+        let book = BookV2::from(BookV1 {
+            id: 6,
+            title: "Rawr Means Hello".to_string(),
+            pages: 66,
+        });
+        assert_eq!(
+            BookV2 {
+                id: 6,
+                title: "Rawr Means Hello".to_string(),
+                rating: 0.0,
+                slug: "rawr-means-hello".to_string(),
+            },
+            book
+        );
+
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+        <BookV1 as Crud<Sqlite>>::create(&connection).unwrap();
+        let books = (0..3)
+            .map(|id| BookV1 {
+                id,
+                title: format!("Book {id}"),
+                pages: 100 * id,
+            })
+            .collect::<Vec<_>>();
+        <BookV1 as Crud<Sqlite>>::insert_many(&connection, &books).unwrap();
+        <BookV2 as Crud<Sqlite>>::create(&connection).unwrap();
+        Migrations::<BookV1, Sqlite>::default()
+            .with_version::<BookV2>()
+            .run(&connection)
+            .unwrap();
+        let slugs = <BookV2 as Crud<Sqlite>>::read_all(&connection)
+            .unwrap()
+            .map(|r| r.unwrap().slug)
+            .collect::<Vec<_>>();
+        assert_eq!(vec!["book-0", "book-1", "book-2"], slugs);
+    }
+
+    #[test]
+    fn migrate_rollback_to() {
+        let chain = || {
+            Migrations::<PlayerV1, Sqlite>::default()
+                .with_reversible_version::<PlayerV2>()
+                .with_reversible_version::<PlayerV3>()
+        };
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+        <PlayerV1 as Crud<Sqlite>>::create(&connection).unwrap();
+        <PlayerV2 as Crud<Sqlite>>::create(&connection).unwrap();
+        <PlayerV3 as Crud<Sqlite>>::create(&connection).unwrap();
+        for id in 0..3 {
+            let name = format!("tymigrawr_{id}");
+            Crud::<Sqlite>::insert(&PlayerV1 { id, name }, &connection).unwrap();
+        }
+        chain().run(&connection).unwrap();
+        assert_eq!(3, <PlayerV3 as Crud<Sqlite>>::count(&connection).unwrap());
 
-    impl From<PlayerV3> for PlayerV2 {
-        fn from(value: PlayerV3) -> Self {
-            let PlayerV3 {
+        // rows of versions before the target are left alone
+        let untouched = PlayerV1 {
+            id: 66,
+            name: "untouched".to_string(),
+        };
+        Crud::<Sqlite>::insert(&untouched, &connection).unwrap();
+        let err = chain()
+            .deny_lossy()
+            .rollback_to::<PlayerV2>(&connection)
+            .unwrap_err();
+        assert_eq!(
+            "rolling back to playerv2 would discard columns description",
+            err.to_string()
+        );
+        let err = chain().rollback_to::<PlayerV3>(&connection).unwrap_err();
+        assert!(
+            err.to_string().contains("is not an earlier version"),
+            "{err}"
+        );
+
+        chain().rollback_to::<PlayerV2>(&connection).unwrap();
+        assert_eq!(0, <PlayerV3 as Crud<Sqlite>>::count(&connection).unwrap());
+        let players_v2 = <PlayerV2 as Crud<Sqlite>>::read_all(&connection)
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect::<Vec<_>>();
+        let expected = (0..3)
+            .map(|id| PlayerV2 {
                 id,
-                name,
-                description: _,
-            } = value;
-            PlayerV2 { id, name, age: 0.0 }
-        }
-    }
+                name: format!("tymigrawr_{id}"),
+                age: 0.0,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(expected, players_v2);
+        let players_v1 = <PlayerV1 as Crud<Sqlite>>::read_all(&connection)
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(vec![untouched], players_v1);
 
-    pub type Player = PlayerV3;
+        let err = Migrations::<PlayerV2, Sqlite>::default()
+            .with_version::<PlayerV3>()
+            .rollback_to::<PlayerV2>(&connection)
+            .unwrap_err();
+        assert_eq!(
+            "the step from playerv2 to playerv3 can't be rolled back, \
+             add it with `with_reversible_version`",
+            err.to_string()
+        );
+    }
 
     #[test]
     fn migrate() {
@@ -637,13 +5322,13 @@ mod test {
         log::debug!("migration setup");
         let tempdir = tempfile::tempdir().unwrap();
         let path = tempdir.path().join("data.db");
-        let connection = sqlite::open(path).unwrap();
+        let connection = rusqlite::Connection::open(path).unwrap();
         let path = tempdir.path().join("data_v3.db");
-        let connection_v3 = sqlite::open(path).unwrap();
+        let connection_v3 = rusqlite::Connection::open(path).unwrap();
         log::debug!("creating tables");
-        PlayerV1::create(&connection).unwrap();
-        PlayerV2::create(&connection).unwrap();
-        PlayerV3::create(&connection_v3).unwrap();
+        <PlayerV1 as Crud<Sqlite>>::create(&connection).unwrap();
+        <PlayerV2 as Crud<Sqlite>>::create(&connection).unwrap();
+        <PlayerV3 as Crud<Sqlite>>::create(&connection_v3).unwrap();
 
         log::debug!("populating v1");
         let players_v1 = (0..100)
@@ -653,7 +5338,7 @@ mod test {
             })
             .collect::<Vec<_>>();
         for player in players_v1.iter() {
-            player.insert(&connection).unwrap();
+            Crud::<Sqlite>::insert(player, &connection).unwrap();
         }
         let players_v3 = players_v1
             .iter()
@@ -673,13 +5358,13 @@ mod test {
             })
             .unwrap();
 
-        let players_v1_from_db = PlayerV1::read_all(&connection)
+        let players_v1_from_db = <PlayerV1 as Crud<Sqlite>>::read_all(&connection)
             .unwrap()
             .map(|r| r.unwrap())
             .collect::<Vec<_>>();
         assert_eq!(Vec::<PlayerV1>::new(), players_v1_from_db);
 
-        let players_v3_from_db = PlayerV3::read_all(&connection_v3)
+        let players_v3_from_db = <PlayerV3 as Crud<Sqlite>>::read_all(&connection_v3)
             .unwrap()
             .filter_map(Result::ok)
             .collect::<Vec<_>>();
@@ -695,7 +5380,7 @@ mod test {
                 _ => &connection,
             })
             .unwrap();
-        let players_v1_from_db = PlayerV1::read_all(&connection)
+        let players_v1_from_db = <PlayerV1 as Crud<Sqlite>>::read_all(&connection)
             .unwrap()
             .map(|r| r.unwrap())
             .collect::<Vec<_>>();
@@ -703,17 +5388,735 @@ mod test {
     }
 
     #[test]
-    fn dynamodb_float_int_roundtrip() {
-        let int_value = Value::Integer(66);
-        let int_dydb = AttributeValue::from(int_value.clone());
-        assert_eq!(int_value, Value::from(int_dydb));
+    fn sqlite_errors_name_the_statement() {
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+        <PlayerV1 as Crud<Sqlite>>::create(&connection).unwrap();
+        let fields = FieldMap::from([("id", Value::Integer(0)), ("nickname", Value::None)]);
+        let err = Sqlite::insert_fields(&connection, "playerv1", &fields)
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(err.contains("INSERT INTO \"playerv1\""), "{err}");
+        assert!(err.contains("nickname"), "{err}");
+    }
+
+    #[test]
+    fn sqlite_missing_table() {
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+        let player = PlayerV1 {
+            id: 0,
+            name: "tymigrawr".to_string(),
+        };
+        let errors = [
+            <PlayerV1 as Crud<Sqlite>>::read_all(&connection)
+                .err()
+                .unwrap(),
+            Crud::<Sqlite>::insert(&player, &connection).err().unwrap(),
+            Crud::<Sqlite>::update(&player, &connection).err().unwrap(),
+            Crud::<Sqlite>::delete(player.clone(), &connection)
+                .err()
+                .unwrap(),
+        ];
+        for err in errors {
+            assert_eq!(
+                "table `playerv1` does not exist; call create() first",
+                err.to_string()
+            );
+        }
+    }
+
+    #[test]
+    fn migrations_debug_and_clone() {
+        let migrations = Migrations::<PlayerV1, Sqlite>::default()
+            .with_version::<PlayerV2>()
+            .with_version::<Player>();
+        let cloned = migrations.clone();
+        assert_eq!(migrations.table_names(), cloned.table_names());
+
+        let debug = format!("{migrations:?}");
+        for table_name in ["playerv1", "playerv2", "playerv3"] {
+            assert!(debug.contains(table_name), "{debug}");
+        }
+        assert!(debug.contains("crud_fields"), "{debug}");
+        assert_eq!(debug, format!("{cloned:?}"));
+    }
+
+    #[test]
+    fn read_projected() {
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+        query_players(&connection);
+        let rows =
+            <PlayerV2 as Crud<Sqlite>>::read_projected(&connection, &["name", "id"]).unwrap();
+        assert_eq!(5, rows.len());
+        for (id, row) in (0..).zip(&rows) {
+            assert_eq!(2, row.len());
+            assert_eq!(Some(&Value::Integer(id)), row.get("id"));
+            assert!(!row.contains_key("age"));
+        }
+        assert_eq!(
+            Some(&Value::String("alice".to_string())),
+            rows[0].get("name")
+        );
+
+        let err = <PlayerV2 as Crud<Sqlite>>::read_projected(&connection, &["id", "nickname"])
+            .err()
+            .unwrap();
+        assert_eq!("table `playerv2` has no column `nickname`", err.to_string());
+    }
+
+    #[test]
+    fn sync_schema_adds_columns() {
+        // the next version of `PlayerV1`, using the same table
+        mod next {
+            use snafu::prelude::*;
+
+            use crate::{self as tymigrawr, HasCrudFields, IsCrudField};
+
+            #[derive(Debug, Clone, PartialEq, HasCrudFields)]
+            pub struct PlayerV1 {
+                #[primary_key]
+                pub id: i64,
+                pub name: String,
+                pub nickname: Option<String>,
+            }
+        }
+
+        mod required {
+            use snafu::prelude::*;
+
+            use crate::{self as tymigrawr, HasCrudFields, IsCrudField};
+
+            #[derive(Debug, Clone, PartialEq, HasCrudFields)]
+            pub struct PlayerV1 {
+                #[primary_key]
+                pub id: i64,
+                pub name: String,
+                pub level: i64,
+            }
+        }
+
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+        assert!(<next::PlayerV1 as Crud<Sqlite>>::sync_schema(&connection)
+            .unwrap()
+            .is_empty());
+        <next::PlayerV1 as Crud<Sqlite>>::validate_schema(&connection).unwrap();
+
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+        <PlayerV1 as Crud<Sqlite>>::create(&connection).unwrap();
+        let player = PlayerV1 {
+            id: 0,
+            name: "tymigrawr".to_string(),
+        };
+        Crud::<Sqlite>::insert(&player, &connection).unwrap();
+        let err = <required::PlayerV1 as Crud<Sqlite>>::sync_schema(&connection)
+            .err()
+            .unwrap();
+        assert_eq!(
+            "can't add column `level` to `playerv1`, existing rows would be NULL, \
+             make it an `Option` or add it with a migration",
+            err.to_string()
+        );
+
+        assert_eq!(
+            vec!["nickname"],
+            <next::PlayerV1 as Crud<Sqlite>>::sync_schema(&connection).unwrap()
+        );
+        <next::PlayerV1 as Crud<Sqlite>>::validate_schema(&connection).unwrap();
+        assert!(<next::PlayerV1 as Crud<Sqlite>>::sync_schema(&connection)
+            .unwrap()
+            .is_empty());
+        let read = <next::PlayerV1 as Crud<Sqlite>>::read_all(&connection)
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(
+            vec![next::PlayerV1 {
+                id: 0,
+                name: "tymigrawr".to_string(),
+                nickname: None,
+            }],
+            read
+        );
+
+        let err = <PlayerV1 as Crud<Sqlite>>::sync_schema(&connection)
+            .err()
+            .unwrap();
+        assert_eq!(
+            "table `playerv1` has columns `nickname` that aren't fields, \
+             dropping or renaming columns needs a migration",
+            err.to_string()
+        );
+    }
+
+    #[test]
+    fn create_strict() {
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+        <PlayerV1 as Crud<Sqlite>>::create_strict(&connection).unwrap();
+        <PlayerV1 as Crud<Sqlite>>::create_strict(&connection).unwrap();
+
+        connection
+            .execute_batch("ALTER TABLE playerv1 ADD COLUMN nickname TEXT;")
+            .unwrap();
+        <PlayerV1 as Crud<Sqlite>>::create(&connection).unwrap();
+        let err = <PlayerV1 as Crud<Sqlite>>::create_strict(&connection)
+            .err()
+            .unwrap();
+        assert_eq!(
+            "table `playerv1` does not match its fields: unexpected column `nickname`",
+            err.to_string()
+        );
+    }
+
+    #[derive(Clone, Debug, PartialEq, HasCrudFields)]
+    pub struct Checksum {
+        #[primary_key]
+        pub id: i64,
+        pub digest: HexBytes,
+    }
+
+    #[test]
+    fn hex_bytes_roundtrip() {
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+        <Checksum as Crud<Sqlite>>::create(&connection).unwrap();
+        let checksum = Checksum {
+            id: 0,
+            digest: HexBytes(vec![0x00, 0x0f, 0xab, 0xff]),
+        };
+        assert_eq!(
+            Value::String("000fabff".to_string()),
+            checksum.digest.into_value()
+        );
+        Crud::<Sqlite>::insert(&checksum, &connection).unwrap();
+        let read = <Checksum as Crud<Sqlite>>::read(&connection, 0)
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap();
+        assert_eq!(checksum, read);
+        assert_eq!(
+            Ok(HexBytes(vec![0xab])),
+            HexBytes::maybe_from_value(&Value::String("AB".to_string())).map_err(|e| e.to_string())
+        );
+    }
+
+    #[test]
+    fn hex_bytes_invalid() {
+        let err = HexBytes::maybe_from_value(&Value::String("abc".to_string()))
+            .err()
+            .unwrap();
+        assert_eq!("'abc' has an odd number of hex digits", err.to_string());
+        let err = HexBytes::maybe_from_value(&Value::String("zz".to_string()))
+            .err()
+            .unwrap();
+        assert_eq!("'zz' is not hex", err.to_string());
+        let err = HexBytes::maybe_from_value(&Value::String("+f".to_string()))
+            .err()
+            .unwrap();
+        assert_eq!("'+f' is not hex", err.to_string());
+    }
+
+    #[test]
+    fn migrate_into_fresh_database() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let connection = rusqlite::Connection::open(tempdir.path().join("data.db")).unwrap();
+        let connection_v3 = rusqlite::Connection::open(tempdir.path().join("data_v3.db")).unwrap();
+        <PlayerV1 as Crud<Sqlite>>::create(&connection).unwrap();
+        <PlayerV2 as Crud<Sqlite>>::create(&connection).unwrap();
+        let players_v1 = (0..3)
+            .map(|i| PlayerV1 {
+                id: i,
+                name: format!("tymigrawr_{i}"),
+            })
+            .collect::<Vec<_>>();
+        for player in players_v1.iter() {
+            Crud::<Sqlite>::insert(player, &connection).unwrap();
+        }
+        assert!(!Sqlite::table_exists(&connection_v3, "playerv3").unwrap());
+
+        Migrations::<PlayerV1, Sqlite>::default()
+            .with_version::<PlayerV2>()
+            .with_version::<Player>()
+            .run_with(|table| match table {
+                "playerv3" => &connection_v3,
+                _ => &connection,
+            })
+            .unwrap();
+
+        <PlayerV3 as Crud<Sqlite>>::validate_schema(&connection_v3).unwrap();
+        let players_v3 = <PlayerV3 as Crud<Sqlite>>::read_all(&connection_v3)
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect::<Vec<_>>();
+        let expected = players_v1
+            .into_iter()
+            .map(PlayerV2::from)
+            .map(Player::from)
+            .collect::<Vec<_>>();
+        assert_eq!(expected, players_v3);
+    }
+
+    #[test]
+    fn value_approx_eq() {
+        let sum = Value::Float(0.1 + 0.2);
+        assert_ne!(Value::Float(0.3), sum);
+        assert!(sum.approx_eq(&Value::Float(0.3), 1e-9));
+        assert!(!Value::Float(0.3).approx_eq(&Value::Float(0.31), 1e-9));
+        assert!(Value::Float(0.3).approx_eq(&Value::Float(0.31), 0.1));
+        assert!(Value::Float(f64::NAN).approx_eq(&Value::Float(f64::NAN), 0.0));
+        assert!(Value::Float(f64::INFINITY).approx_eq(&Value::Float(f64::INFINITY), 0.0));
+        assert!(!Value::Float(f64::INFINITY).approx_eq(&Value::Float(f64::NEG_INFINITY), 1.0));
+
+        assert!(Value::Integer(3).approx_eq(&Value::Integer(3), 1.0));
+        assert!(!Value::Integer(3).approx_eq(&Value::Integer(4), 1.0));
+
+        assert!(!Value::Integer(1).approx_eq(&Value::Float(1.0), 1.0));
+        assert!(!Value::Float(1.0).approx_eq(&Value::None, 1.0));
+        assert!(Value::None.approx_eq(&Value::None, 0.0));
+        assert!(!Value::String("1".to_string()).approx_eq(&Value::Integer(1), 1.0));
+    }
+
+    #[derive(Debug, Clone, PartialEq, HasCrudFields)]
+    pub struct Sample {
+        #[primary_key]
+        pub id: i64,
+        pub label: String,
+        pub note: Option<String>,
+        pub weight: f64,
+        pub raw: Vec<u8>,
+        pub grade: char,
+    }
+
+    #[test]
+    fn read_all_from_rows_matches_fields() {
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+        <Sample as Crud<Sqlite>>::create(&connection).unwrap();
+        for id in 0..4 {
+            let sample = Sample {
+                id,
+                label: format!("sample_{id}"),
+                note: (id % 2 == 0).then(|| "even".to_string()),
+                weight: id as f64 / 3.0,
+                raw: vec![id as u8; id as usize],
+                grade: char::from(b'a' + id as u8),
+            };
+            Crud::<Sqlite>::insert(&sample, &connection).unwrap();
+        }
+        let from_rows = <Sample as Crud<Sqlite>>::read_all(&connection)
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect::<Vec<_>>();
+        let from_fields = Sqlite::read_all_values(&connection, "sample", Sample::field_names())
+            .unwrap()
+            .into_iter()
+            .map(|fields| Sample::try_from_crud_fields(&fields.unwrap()).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(4, from_rows.len());
+        assert_eq!(from_fields, from_rows);
+    }
+
+    #[test]
+    fn insert_or_ignore() {
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+        <PlayerV1 as Crud<Sqlite>>::create(&connection).unwrap();
+        let player = PlayerV1 {
+            id: 0,
+            name: "rawr".to_string(),
+        };
+        assert!(Crud::<Sqlite>::insert_or_ignore(&player, &connection).unwrap());
+        let duplicate = PlayerV1 {
+            id: 0,
+            name: "not rawr".to_string(),
+        };
+        assert!(!Crud::<Sqlite>::insert_or_ignore(&duplicate, &connection).unwrap());
+        assert!(Crud::<Sqlite>::insert(&duplicate, &connection).is_err());
+
+        let players = <PlayerV1 as Crud<Sqlite>>::read_all(&connection)
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(vec![player], players);
+    }
+
+    #[test]
+    fn from_sqlite_decltype() {
+        for ty in [
+            ValueType::Integer,
+            ValueType::Float,
+            ValueType::String,
+            ValueType::Bytes,
+        ] {
+            assert_eq!(Some(ty), ValueType::from_sqlite_decltype(ty.sqlite_type()));
+        }
+        let cases = [
+            ("int", Some(ValueType::Integer)),
+            ("BIGINT", Some(ValueType::Integer)),
+            ("UNSIGNED BIG INT", Some(ValueType::Integer)),
+            // "INT" wins over "CHAR" and "FLOA"
+            ("CHARINT", Some(ValueType::Integer)),
+            ("FLOATING POINT", Some(ValueType::Integer)),
+            ("VARCHAR(20)", Some(ValueType::String)),
+            ("NATIVE CHARACTER(70)", Some(ValueType::String)),
+            ("clob", Some(ValueType::String)),
+            ("", Some(ValueType::Bytes)),
+            ("DOUBLE PRECISION", Some(ValueType::Float)),
+            ("float", Some(ValueType::Float)),
+            ("NUMERIC", None),
+            ("DECIMAL(10,5)", None),
+            ("BOOLEAN", None),
+            ("DATETIME", None),
+            // "STRING" has NUMERIC affinity in sqlite
+            ("STRING", None),
+        ];
+        for (decltype, expected) in cases {
+            assert_eq!(
+                expected,
+                ValueType::from_sqlite_decltype(decltype),
+                "{decltype}"
+            );
+        }
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, CrudEnum)]
+    enum Rank {
+        Bronze,
+        Silver,
+        Gold,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, CrudEnum)]
+    #[crud_enum = "discriminant"]
+    enum Tier {
+        Free = 1,
+        Pro = 5,
+        Team,
+    }
+
+    #[derive(Debug, Clone, PartialEq, HasCrudFields)]
+    struct Member {
+        #[primary_key]
+        id: i64,
+        rank: Rank,
+        tier: Option<Tier>,
+    }
+
+    #[test]
+    fn crud_enum_roundtrip() {
+        assert_eq!(ValueType::String, Rank::field().ty);
+        assert_eq!(ValueType::Integer, Tier::field().ty);
+        assert_eq!(
+            Value::String("Silver".to_string()),
+            Rank::Silver.into_value()
+        );
+        assert_eq!(Value::Integer(6), Tier::Team.into_value());
+
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+        <Member as Crud<Sqlite>>::create(&connection).unwrap();
+        let ranks = [Rank::Bronze, Rank::Silver, Rank::Gold];
+        let tiers = [Some(Tier::Free), Some(Tier::Pro), Some(Tier::Team), None];
+        let members = ranks
+            .iter()
+            .flat_map(|rank| tiers.iter().map(move |tier| (*rank, *tier)))
+            .enumerate()
+            .map(|(id, (rank, tier))| Member {
+                id: id as i64,
+                rank,
+                tier,
+            })
+            .collect::<Vec<_>>();
+        <Member as Crud<Sqlite>>::insert_many(&connection, &members).unwrap();
+        let read = <Member as Crud<Sqlite>>::read_all(&connection)
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(members, read);
+    }
+
+    #[test]
+    fn crud_enum_unknown_value() {
+        let err = Rank::maybe_from_value(&Value::String("Platinum".to_string()))
+            .err()
+            .unwrap();
+        assert_eq!("'Platinum' is not a Rank variant", err.to_string());
+        let err = Rank::maybe_from_value(&Value::Integer(0)).err().unwrap();
+        assert_eq!("not a string", err.to_string());
+        let err = Tier::maybe_from_value(&Value::Integer(2)).err().unwrap();
+        assert_eq!("2 is not a Tier discriminant", err.to_string());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn value_serde_roundtrip() {
+        let cases = [
+            (Value::Integer(-3), r#"{"type":"Integer","value":-3}"#),
+            (Value::Float(0.5), r#"{"type":"Float","value":0.5}"#),
+            (
+                Value::String("rawr".to_string()),
+                r#"{"type":"String","value":"rawr"}"#,
+            ),
+            (
+                Value::Bytes(vec![0, 1, 254, 255]),
+                r#"{"type":"Bytes","value":"AAH+/w=="}"#,
+            ),
+            (Value::Boolean(true), r#"{"type":"Boolean","value":true}"#),
+            (Value::None, r#"{"type":"None"}"#),
+        ];
+        for (value, json) in cases {
+            assert_eq!(json, serde_json::to_string(&value).unwrap());
+            assert_eq!(value, serde_json::from_str::<Value>(json).unwrap());
+        }
+        assert!(serde_json::from_str::<Value>(r#"{"type":"Bytes","value":"!"}"#).is_err());
+    }
+
+    #[derive(Debug, Clone, PartialEq, HasCrudFields)]
+    struct Tally {
+        #[primary_key]
+        id: i64,
+        #[nullable]
+        count: i64,
+    }
+
+    #[test]
+    fn nullable_without_option() {
+        assert!(Tally::crud_fields()[1].nullable);
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+        <Tally as Crud<Sqlite>>::create(&connection).unwrap();
+        connection
+            .execute_batch("INSERT INTO tally (id, count) VALUES (0, NULL);")
+            .unwrap();
+        Crud::<Sqlite>::insert(&Tally { id: 1, count: 3 }, &connection).unwrap();
+        let read = <Tally as Crud<Sqlite>>::read_all(&connection)
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(
+            vec![Tally { id: 0, count: 0 }, Tally { id: 1, count: 3 }],
+            read
+        );
+
+        let fields = FieldMap::from([("id", Value::Integer(2)), ("count", Value::None)]);
+        assert_eq!(
+            Tally { id: 2, count: 0 },
+            Tally::try_from_crud_fields(&fields).unwrap()
+        );
+    }
+
+    #[derive(Debug, Clone, PartialEq, HasCrudFields)]
+    struct Subscriber {
+        #[primary_key]
+        #[collate = "NOCASE"]
+        email: String,
+        name: String,
+    }
+
+    #[test]
+    fn collate_nocase() {
+        assert_eq!(
+            "\"email\" TEXT PRIMARY KEY NOT NULL COLLATE NOCASE",
+            Subscriber::crud_fields()[0].sqlite_create_field()
+        );
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+        <Subscriber as Crud<Sqlite>>::create(&connection).unwrap();
+        Crud::<Sqlite>::insert(
+            &Subscriber {
+                email: "A@x.com".into(),
+                name: "first".into(),
+            },
+            &connection,
+        )
+        .unwrap();
+        let err = Crud::<Sqlite>::insert(
+            &Subscriber {
+                email: "a@x.com".into(),
+                name: "second".into(),
+            },
+            &connection,
+        )
+        .unwrap_err();
+        assert!(
+            format!("{err:?}").contains("UNIQUE constraint failed"),
+            "{err:?}"
+        );
+        let read = <Subscriber as Crud<Sqlite>>::read(&connection, "a@X.COM".to_string())
+            .unwrap()
+            .map(|r| r.unwrap().name)
+            .collect::<Vec<_>>();
+        assert_eq!(vec!["first".to_string()], read);
+    }
+
+    #[derive(Debug, Clone, PartialEq, HasCrudFields)]
+    struct SplitPlayer {
+        #[primary_key]
+        id: i64,
+        first: String,
+        last: String,
+    }
+
+    fn split_name(
+        any: Box<dyn core::any::Any>,
+    ) -> Result<Box<dyn core::any::Any>, snafu::Whatever> {
+        let PlayerV1 { id, name } = *any.downcast::<PlayerV1>().unwrap();
+        let Some((first, last)) = name.split_once(' ') else {
+            snafu::whatever!("`{name}` has no last name");
+        };
+        Ok(Box::new(SplitPlayer {
+            id,
+            first: first.to_string(),
+            last: last.to_string(),
+        }))
+    }
+
+    #[test]
+    fn with_version_fn() {
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+        <PlayerV1 as Crud<Sqlite>>::create(&connection).unwrap();
+        for (id, name) in [(0, "ada lovelace"), (1, "grace hopper")] {
+            Crud::<Sqlite>::insert(
+                &PlayerV1 {
+                    id,
+                    name: name.to_string(),
+                },
+                &connection,
+            )
+            .unwrap();
+        }
+        Migrations::<PlayerV1, Sqlite>::default()
+            .with_version_fn::<SplitPlayer>(split_name)
+            .run(&connection)
+            .unwrap();
+        let players = <SplitPlayer as Crud<Sqlite>>::read_all(&connection)
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                SplitPlayer {
+                    id: 0,
+                    first: "ada".into(),
+                    last: "lovelace".into(),
+                },
+                SplitPlayer {
+                    id: 1,
+                    first: "grace".into(),
+                    last: "hopper".into(),
+                },
+            ],
+            players
+        );
+
+        Crud::<Sqlite>::insert(
+            &PlayerV1 {
+                id: 2,
+                name: "cher".into(),
+            },
+            &connection,
+        )
+        .unwrap();
+        let err = Migrations::<PlayerV1, Sqlite>::default()
+            .with_version_fn::<SplitPlayer>(split_name)
+            .run(&connection)
+            .unwrap_err();
+        assert_eq!(
+            "could not convert a row of playerv1 to splitplayer",
+            err.to_string()
+        );
+        assert_eq!(
+            "`cher` has no last name",
+            std::error::Error::source(&err).unwrap().to_string()
+        );
+        // the row that failed is left where it was
+        assert_eq!(
+            1,
+            <PlayerV1 as Crud<Sqlite>>::read(&connection, 2)
+                .unwrap()
+                .count()
+        );
+    }
+
+    #[test]
+    fn table_prefix() {
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+        let player = |id, name: &str| PlayerV1 {
+            id,
+            name: name.to_string(),
+        };
+        for (prefix, name) in [("tenant1_", "ada"), ("tenant2_", "grace")] {
+            crate::with_table_prefix(prefix, || {
+                assert_eq!(format!("{prefix}playerv1"), PlayerV1::table_name());
+                <PlayerV1 as Crud<Sqlite>>::create(&connection).unwrap();
+                Crud::<Sqlite>::insert(&player(0, name), &connection).unwrap();
+                Crud::<Sqlite>::insert(&player(1, "shared"), &connection).unwrap();
+            });
+        }
+        assert_eq!("playerv1", PlayerV1::table_name());
+        assert!(<PlayerV1 as Crud<Sqlite>>::read_all(&connection).is_err());
+
+        crate::with_table_prefix("tenant1_", || {
+            Crud::<Sqlite>::update(&player(1, "tenant1"), &connection).unwrap();
+            let read = <PlayerV1 as Crud<Sqlite>>::read_all(&connection)
+                .unwrap()
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap();
+            assert_eq!(vec![player(0, "ada"), player(1, "tenant1")], read);
+            Crud::<Sqlite>::delete(player(0, "ada"), &connection).unwrap();
+            assert_eq!(1, <PlayerV1 as Crud<Sqlite>>::count(&connection).unwrap());
+        });
+        crate::with_table_prefix("tenant2_", || {
+            let read = <PlayerV1 as Crud<Sqlite>>::read_all(&connection)
+                .unwrap()
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap();
+            assert_eq!(vec![player(0, "grace"), player(1, "shared")], read);
+            // migrations also stay within the prefix
+            Migrations::<PlayerV1, Sqlite>::default()
+                .with_version::<PlayerV2>()
+                .run(&connection)
+                .unwrap();
+            assert_eq!(2, <PlayerV2 as Crud<Sqlite>>::count(&connection).unwrap());
+            assert_eq!(0, <PlayerV1 as Crud<Sqlite>>::count(&connection).unwrap());
+        });
+        crate::with_table_prefix("tenant1_", || {
+            assert_eq!(1, <PlayerV1 as Crud<Sqlite>>::count(&connection).unwrap());
+        });
+    }
+
+    #[test]
+    fn busy_retry() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("busy.db");
+        let locker = rusqlite::Connection::open(&path).unwrap();
+        <PlayerV1 as Crud<Sqlite>>::create(&locker).unwrap();
+        let connection = rusqlite::Connection::open(&path).unwrap();
+        // fail on the first lock instead of waiting in sqlite's busy handler
+        connection.busy_timeout(std::time::Duration::ZERO).unwrap();
+        let player = PlayerV1 {
+            id: 0,
+            name: "tymigrawr".into(),
+        };
 
-        let float_value = Value::Float(600.66);
-        let float_dydb = AttributeValue::from(float_value.clone());
-        assert_eq!(float_value, Value::from(float_dydb));
+        locker.execute_batch("BEGIN EXCLUSIVE;").unwrap();
+        let quick = crate::BusyRetry {
+            max_retries: 2,
+            initial_backoff: std::time::Duration::from_millis(1),
+            max_backoff: std::time::Duration::from_millis(1),
+        };
+        let err = crate::with_busy_retry(quick, || Crud::<Sqlite>::insert(&player, &connection))
+            .unwrap_err();
+        assert_eq!(
+            "insert into `playerv1` timed out, the database was still busy after 2 retries",
+            err.to_string()
+        );
 
-        //let float_value = Value::Float(600.0);
-        //let float_dydb = AttributeValue::from(float_value.clone());
-        //assert_eq!(float_value, Value::from(float_dydb));
+        // the lock is released while the insert retries
+        let unlock = std::thread::spawn(move || {
+            std::thread::sleep(std::time::Duration::from_millis(50));
+            locker.execute_batch("COMMIT;").unwrap();
+        });
+        Crud::<Sqlite>::insert(&player, &connection).unwrap();
+        unlock.join().unwrap();
+        let players = <PlayerV1 as Crud<Sqlite>>::read_all(&connection)
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(vec![player], players);
     }
 }
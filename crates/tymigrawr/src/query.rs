@@ -0,0 +1,238 @@
+//! Queries with multiple conditions.
+use std::marker::PhantomData;
+
+use crate::{IsCrudField, Value};
+
+/// How a column is compared to a value in a [`Query`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Like,
+}
+
+impl Comparison {
+    pub const ALL: [Comparison; 7] = [
+        Comparison::Eq,
+        Comparison::Ne,
+        Comparison::Lt,
+        Comparison::Le,
+        Comparison::Gt,
+        Comparison::Ge,
+        Comparison::Like,
+    ];
+
+    /// Find the comparison for a SQL operator.
+    pub fn from_sql(op: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|comparison| comparison.as_sql().eq_ignore_ascii_case(op))
+    }
+
+    /// The SQL operator for this comparison.
+    pub fn as_sql(&self) -> &'static str {
+        match self {
+            Comparison::Eq => "=",
+            Comparison::Ne => "!=",
+            Comparison::Lt => "<",
+            Comparison::Le => "<=",
+            Comparison::Gt => ">",
+            Comparison::Ge => ">=",
+            Comparison::Like => "LIKE",
+        }
+    }
+}
+
+/// A `LIKE` pattern matching literal text.
+///
+/// `%`, `_` and the escape character `\` in the text are escaped, so user
+/// input can't add wildcards. Use it with
+/// [`Crud::read_like`](crate::Crud::read_like), which adds the `ESCAPE '\'`
+/// clause.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LikePattern(String);
+
+impl LikePattern {
+    /// The character escaping wildcards in the pattern.
+    pub const ESCAPE: char = '\\';
+
+    fn escape(text: &str) -> String {
+        let mut escaped = String::with_capacity(text.len());
+        for c in text.chars() {
+            if matches!(c, '%' | '_' | Self::ESCAPE) {
+                escaped.push(Self::ESCAPE);
+            }
+            escaped.push(c);
+        }
+        escaped
+    }
+
+    /// Matches values starting with `prefix`.
+    pub fn starts_with(prefix: &str) -> Self {
+        LikePattern(format!("{}%", Self::escape(prefix)))
+    }
+
+    /// Matches values ending with `suffix`.
+    pub fn ends_with(suffix: &str) -> Self {
+        LikePattern(format!("%{}", Self::escape(suffix)))
+    }
+
+    /// Matches values containing `text`.
+    pub fn contains(text: &str) -> Self {
+        LikePattern(format!("%{}%", Self::escape(text)))
+    }
+
+    /// Matches values equal to `text`, ignoring ASCII case in sqlite.
+    pub fn exact(text: &str) -> Self {
+        LikePattern(Self::escape(text))
+    }
+
+    /// The escaped pattern.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+/// One `column comparison value` clause of a [`Query`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Condition {
+    pub column: String,
+    pub comparison: Comparison,
+    pub value: Value,
+}
+
+impl Condition {
+    pub fn new(column: impl Into<String>, comparison: Comparison, value: impl IsCrudField) -> Self {
+        Condition {
+            column: column.into(),
+            comparison,
+            value: value.into_value(),
+        }
+    }
+}
+
+/// Reads rows of `T` matching all of its conditions.
+///
+/// A query without conditions reads every row.
+#[derive(Debug, Clone)]
+pub struct Query<T> {
+    conditions: Vec<Condition>,
+    _table: PhantomData<T>,
+}
+
+impl<T> Default for Query<T> {
+    fn default() -> Self {
+        Self {
+            conditions: Default::default(),
+            _table: PhantomData,
+        }
+    }
+}
+
+impl<T> Query<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a condition that rows must match.
+    pub fn and(
+        mut self,
+        column: impl Into<String>,
+        comparison: Comparison,
+        value: impl IsCrudField,
+    ) -> Self {
+        self.conditions
+            .push(Condition::new(column, comparison, value));
+        self
+    }
+
+    pub fn conditions(&self) -> &[Condition] {
+        &self.conditions
+    }
+}
+
+impl<T> FromIterator<Condition> for Query<T> {
+    fn from_iter<I: IntoIterator<Item = Condition>>(iter: I) -> Self {
+        Self {
+            conditions: iter.into_iter().collect(),
+            _table: PhantomData,
+        }
+    }
+}
+
+/// One `ORDER BY` column of a [`Select`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OrderBy {
+    pub column: String,
+    pub descending: bool,
+}
+
+/// Reads rows of `T` that match its filters, sorted and limited in one
+/// statement, eg. the top 10 players over level 5 by score.
+///
+/// Rows are sorted by the `order_by` columns in the order they were added,
+/// then by primary key.
+#[derive(Debug, Clone)]
+pub struct Select<T> {
+    query: Query<T>,
+    order_by: Vec<OrderBy>,
+    limit: Option<usize>,
+}
+
+impl<T> Default for Select<T> {
+    fn default() -> Self {
+        Self {
+            query: Query::default(),
+            order_by: vec![],
+            limit: None,
+        }
+    }
+}
+
+impl<T> Select<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a condition that rows must match.
+    pub fn filter(
+        mut self,
+        column: impl Into<String>,
+        comparison: Comparison,
+        value: impl IsCrudField,
+    ) -> Self {
+        self.query = self.query.and(column, comparison, value);
+        self
+    }
+
+    /// Sort by `column`, after any columns already sorted by.
+    pub fn order_by(mut self, column: impl Into<String>, descending: bool) -> Self {
+        self.order_by.push(OrderBy {
+            column: column.into(),
+            descending,
+        });
+        self
+    }
+
+    /// Read at most `n` rows.
+    pub fn limit(mut self, n: usize) -> Self {
+        self.limit = Some(n);
+        self
+    }
+
+    pub fn conditions(&self) -> &[Condition] {
+        self.query.conditions()
+    }
+
+    pub fn orderings(&self) -> &[OrderBy] {
+        &self.order_by
+    }
+
+    pub fn row_limit(&self) -> Option<usize> {
+        self.limit
+    }
+}
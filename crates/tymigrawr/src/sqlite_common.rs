@@ -0,0 +1,404 @@
+//! SQL shared by the sqlite and rusqlite backends.
+use std::{cell::RefCell, time::Duration};
+
+use snafu::ResultExt;
+
+use crate::{
+    history_table_name, AuditOp, Condition, CrudField, FieldMap, HasCrudFields, SqlDialect, Value,
+    ValueType,
+};
+
+/// How the sqlite and rusqlite backends retry `insert`, `update` and
+/// `delete` while another connection holds a lock, ie. on `SQLITE_BUSY` and
+/// `SQLITE_LOCKED`.
+///
+/// Set it with [`with_busy_retry`].
+#[derive(Debug, Clone)]
+pub struct BusyRetry {
+    /// How many times to retry before giving up.
+    pub max_retries: usize,
+    /// Delay before the first retry, doubled on each subsequent retry.
+    pub initial_backoff: Duration,
+    /// The longest delay between two retries.
+    pub max_backoff: Duration,
+}
+
+impl Default for BusyRetry {
+    fn default() -> Self {
+        Self {
+            max_retries: 8,
+            initial_backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_secs(1),
+        }
+    }
+}
+
+thread_local! {
+    static BUSY_RETRY: RefCell<BusyRetry> = RefCell::new(BusyRetry::default());
+}
+
+/// Run `f` with writes on the current thread retrying per `config`, eg.
+/// `BusyRetry { max_retries: 0, ..Default::default() }` to fail right away.
+///
+/// The previous config is restored when `f` returns or panics.
+pub fn with_busy_retry<R>(config: BusyRetry, f: impl FnOnce() -> R) -> R {
+    struct Restore(Option<BusyRetry>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            if let Some(config) = self.0.take() {
+                BUSY_RETRY.with(|current| *current.borrow_mut() = config);
+            }
+        }
+    }
+
+    let _restore = Restore(Some(BUSY_RETRY.with(|current| current.replace(config))));
+    f()
+}
+
+/// Run `f`, retrying it with backoff while it fails because the database is
+/// busy, as told by `is_busy` for any error in the chain.
+///
+/// `f` must be safe to run again, eg. a single statement.
+pub(crate) fn retry_busy<R>(
+    operation: &str,
+    table_name: &str,
+    is_busy: fn(&(dyn std::error::Error + 'static)) -> bool,
+    mut f: impl FnMut() -> Result<R, snafu::Whatever>,
+) -> Result<R, snafu::Whatever> {
+    let config = BUSY_RETRY.with(|current| current.borrow().clone());
+    let mut backoff = config.initial_backoff;
+    let mut retries = 0;
+    loop {
+        let error = match f() {
+            Err(error) if busy_chain(&error, is_busy) => error,
+            result => return result,
+        };
+        if retries >= config.max_retries {
+            return Err(error).whatever_context(format!(
+                "{operation} `{table_name}` timed out, the database was still busy after \
+                 {retries} retries"
+            ));
+        }
+        log::debug!("{operation} `{table_name}` found the database busy, retrying in {backoff:?}");
+        std::thread::sleep(backoff);
+        backoff = (backoff * 2).min(config.max_backoff);
+        retries += 1;
+    }
+}
+
+fn busy_chain(
+    error: &snafu::Whatever,
+    is_busy: fn(&(dyn std::error::Error + 'static)) -> bool,
+) -> bool {
+    let mut source: Option<&(dyn std::error::Error + 'static)> = Some(error);
+    while let Some(error) = source {
+        if is_busy(error) {
+            return true;
+        }
+        source = error.source();
+    }
+    false
+}
+
+/// The dialect of the sqlite and rusqlite backends.
+pub struct SqliteDialect;
+
+impl SqlDialect for SqliteDialect {
+    fn column_type(ty: &ValueType) -> &'static str {
+        match ty {
+            ValueType::Integer => "INTEGER",
+            ValueType::Float => "REAL",
+            ValueType::String => "TEXT",
+            ValueType::Bytes => "BLOB",
+        }
+    }
+
+    fn placeholder(index: usize) -> String {
+        format!(":p{index}")
+    }
+
+    fn quote_ident(name: &str) -> String {
+        format!("\"{}\"", name.replace('"', "\"\""))
+    }
+}
+
+impl ValueType {
+    /// The declared column type of this value type in sqlite.
+    pub fn sqlite_type(&self) -> &'static str {
+        SqliteDialect::column_type(self)
+    }
+
+    /// The value type of a column declared as `decltype`, following sqlite's
+    /// rules for column affinity, eg. `VARCHAR(20)` is a string.
+    ///
+    /// Columns without a type are bytes. Returns `None` for `NUMERIC`
+    /// affinity, eg. `DECIMAL(10,5)` or `BOOLEAN`, which may hold integers
+    /// or floats.
+    ///
+    /// See <https://www.sqlite.org/datatype3.html#determination_of_column_affinity>.
+    pub fn from_sqlite_decltype(decltype: &str) -> Option<ValueType> {
+        let decltype = decltype.to_ascii_uppercase();
+        let has = |s: &str| decltype.contains(s);
+        if has("INT") {
+            Some(ValueType::Integer)
+        } else if has("CHAR") || has("CLOB") || has("TEXT") {
+            Some(ValueType::String)
+        } else if has("BLOB") || decltype.trim().is_empty() {
+            Some(ValueType::Bytes)
+        } else if has("REAL") || has("FLOA") || has("DOUB") {
+            Some(ValueType::Float)
+        } else {
+            None
+        }
+    }
+}
+
+impl CrudField {
+    pub fn sqlite_create_field(&self) -> String {
+        let Self {
+            name,
+            ty,
+            nullable,
+            primary_key,
+            auto_increment,
+            check,
+            // a table-level clause, see `CrudField::foreign_key_clause`
+            references: _,
+            collation,
+        } = self;
+        let name = SqliteDialect::quote_ident(name);
+        let mut parts = vec![name.as_str(), ty.sqlite_type()];
+        if *primary_key {
+            parts.push("PRIMARY KEY");
+        }
+        if *auto_increment {
+            parts.push("AUTOINCREMENT");
+        }
+        if !nullable {
+            parts.push("NOT NULL");
+        }
+        let mut sql = parts.join(" ");
+        if let Some(collation) = collation {
+            sql.push_str(&format!(" COLLATE {collation}"));
+        }
+        if let Some(check) = check {
+            sql.push_str(&format!(" CHECK ({check})"));
+        }
+        sql
+    }
+}
+
+/// The statement that creates `T`'s table.
+pub(crate) fn create_table_sql<T: HasCrudFields>() -> String {
+    create_table_sql_from(T::table_name(), T::crud_fields())
+}
+
+/// The statement that creates `table_name` with the columns of `fields`.
+pub(crate) fn create_table_sql_from(table_name: &str, fields: &[CrudField]) -> String {
+    format!(
+        "CREATE TABLE IF NOT EXISTS {} ({});",
+        SqliteDialect::quote_ident(table_name),
+        column_definitions(fields)
+    )
+}
+
+/// The statement that creates `T`'s table as a temporary table.
+///
+/// Errors if `T` is `#[audited]`, as its history and triggers would outlive
+/// the table, or has foreign keys, which sqlite can't enforce from the
+/// temporary schema.
+pub(crate) fn create_temp_table_sql<T: HasCrudFields>() -> Result<String, snafu::Whatever> {
+    let table_name = T::table_name();
+    let fields = T::crud_fields();
+    snafu::ensure_whatever!(
+        !T::audited(),
+        "can't create a temporary `{table_name}`, it is audited"
+    );
+    snafu::ensure_whatever!(
+        fields.iter().all(|field| field.references.is_none()),
+        "can't create a temporary `{table_name}`, it has foreign keys"
+    );
+    Ok(format!(
+        "CREATE TEMP TABLE IF NOT EXISTS {} ({});",
+        SqliteDialect::quote_ident(table_name),
+        column_definitions(fields)
+    ))
+}
+
+/// The column definitions and foreign key clauses of a `CREATE TABLE`.
+fn column_definitions(fields: &[CrudField]) -> String {
+    fields
+        .iter()
+        .map(CrudField::sqlite_create_field)
+        .chain(
+            fields
+                .iter()
+                .filter_map(CrudField::foreign_key_clause::<SqliteDialect>),
+        )
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// The statement that adds the column of `field` to `table_name`.
+///
+/// A foreign key can't be added as a table-level clause, so it's a column
+/// constraint here.
+pub(crate) fn add_column_sql(table_name: &str, field: &CrudField) -> String {
+    let mut column = field.sqlite_create_field();
+    if let Some((table, referenced)) = field.references {
+        column.push_str(&format!(
+            " REFERENCES {}({})",
+            SqliteDialect::quote_ident(crate::prefixed_table_name(table)),
+            SqliteDialect::quote_ident(referenced)
+        ));
+    }
+    format!(
+        "ALTER TABLE {} ADD COLUMN {column};",
+        SqliteDialect::quote_ident(table_name)
+    )
+}
+
+/// The statement that inserts `fields` into `table_name`, with the `i`th
+/// value bound to [`SqliteDialect::placeholder`]`(i)`.
+pub(crate) fn insert_sql(table_name: &str, fields: &FieldMap<'_>) -> String {
+    insert_sql_with("INSERT", table_name, fields, "")
+}
+
+/// [`insert_sql`] starting with `insert`, eg. `INSERT OR IGNORE`, and ending
+/// with `suffix`, eg. ` RETURNING *`.
+pub(crate) fn insert_sql_with(
+    insert: &str,
+    table_name: &str,
+    fields: &FieldMap<'_>,
+    suffix: &str,
+) -> String {
+    let table = SqliteDialect::quote_ident(table_name);
+    if fields.is_empty() {
+        return format!("{insert} INTO {table} DEFAULT VALUES{suffix};");
+    }
+    let columns = fields
+        .keys()
+        .map(|name| SqliteDialect::quote_ident(name))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let binds = (0..fields.len())
+        .map(SqliteDialect::placeholder)
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!("{insert} INTO {table} ({columns}) VALUES ({binds}){suffix};")
+}
+
+/// The values of `fields` bound as by [`insert_sql`].
+pub(crate) fn insert_binds(fields: FieldMap<'_>) -> Vec<(String, Value)> {
+    fields
+        .into_values()
+        .enumerate()
+        .map(|(i, value)| (SqliteDialect::placeholder(i), value))
+        .collect()
+}
+
+/// The statement that sets `columns` of the row of `table_name` whose
+/// `primary_key` is bound to `:key_value`, with the `i`th column bound to
+/// [`SqliteDialect::placeholder`]`(i)`.
+pub(crate) fn update_sql(table_name: &str, columns: &[&str], primary_key: &str) -> String {
+    let values = columns
+        .iter()
+        .enumerate()
+        .map(|(i, name)| {
+            format!(
+                "{} = {}",
+                SqliteDialect::quote_ident(name),
+                SqliteDialect::placeholder(i)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!(
+        "UPDATE {} SET {values} WHERE {} = :key_value;",
+        SqliteDialect::quote_ident(table_name),
+        SqliteDialect::quote_ident(primary_key)
+    )
+}
+
+/// The statement that deletes the rows of `table_name` matching `clause`, as
+/// made by [`where_clause`].
+pub(crate) fn delete_sql(table_name: &str, clause: &str) -> String {
+    format!(
+        "DELETE FROM {}{clause};",
+        SqliteDialect::quote_ident(table_name)
+    )
+}
+
+/// The statements that create the history table of an `#[audited]` `T`, and
+/// the triggers that fill it.
+pub(crate) fn create_history_sql<T: HasCrudFields>() -> Vec<String> {
+    let table_name = SqliteDialect::quote_ident(T::table_name());
+    let history_name = history_table_name::<T>();
+    let history = SqliteDialect::quote_ident(&history_name);
+    let fields = T::crud_fields();
+    let names = fields
+        .iter()
+        .map(|field| SqliteDialect::quote_ident(field.name))
+        .collect::<Vec<_>>();
+    let columns = fields
+        .iter()
+        .zip(&names)
+        .map(|(field, name)| format!("{name} {}", field.ty.sqlite_type()))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let mut statements = vec![format!(
+        "CREATE TABLE IF NOT EXISTS {history} ({columns}, _op TEXT NOT NULL, _at TEXT NOT NULL);"
+    )];
+    for (op, row) in [
+        (AuditOp::Insert, "NEW"),
+        (AuditOp::Update, "OLD"),
+        (AuditOp::Delete, "OLD"),
+    ] {
+        let op = op.as_str();
+        let values = names
+            .iter()
+            .map(|name| format!("{row}.{name}"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        statements.push(format!(
+            "CREATE TRIGGER IF NOT EXISTS {} AFTER {} ON {table_name} BEGIN \
+             INSERT INTO {history} ({}, _op, _at) \
+             VALUES ({values}, '{op}', strftime('%Y-%m-%dT%H:%M:%fZ', 'now')); END;",
+            SqliteDialect::quote_ident(&format!("{history_name}_{op}")),
+            op.to_ascii_uppercase(),
+            names.join(", "),
+        ));
+    }
+    statements
+}
+
+/// The ` WHERE ...` clause matching all `conditions` on `T`'s table, and the
+/// values to bind to it.
+///
+/// The clause is empty when there are no conditions.
+pub(crate) fn where_clause<T: HasCrudFields>(
+    conditions: &[Condition],
+) -> Result<(String, Vec<(String, Value)>), snafu::Whatever> {
+    let table_name = T::table_name();
+    let fields = T::crud_fields();
+    let mut clauses = vec![];
+    let mut binds = vec![];
+    for (i, condition) in conditions.iter().enumerate() {
+        let column = condition.column.as_str();
+        snafu::ensure_whatever!(
+            fields.iter().any(|field| field.name == column),
+            "table `{table_name}` has no column `{column}`"
+        );
+        let column = SqliteDialect::quote_ident(column);
+        let param = SqliteDialect::placeholder(i);
+        let op = condition.comparison.as_sql();
+        clauses.push(format!("{column} {op} {param}"));
+        binds.push((param, condition.value.clone()));
+    }
+    if clauses.is_empty() {
+        Ok((String::new(), binds))
+    } else {
+        Ok((format!(" WHERE {}", clauses.join(" AND ")), binds))
+    }
+}
@@ -0,0 +1,61 @@
+//! Runtime table name prefixes.
+//!
+//! Lets one type work with differently-prefixed tables, eg. one set per
+//! tenant, without declaring a type per table.
+use std::{cell::Cell, collections::BTreeSet, sync::Mutex};
+
+thread_local! {
+    static PREFIX: Cell<Option<&'static str>> = const { Cell::new(None) };
+}
+
+/// Run `f` with the table names of derived types prefixed by `prefix`, eg.
+/// `tenant42_players` for `players` with the prefix `tenant42_`.
+///
+/// The prefix is applied by [`HasCrudFields::table_name`](crate::HasCrudFields::table_name),
+/// so every statement built from it honors it, including those of
+/// migrations, history tables and `#[references(..)]` foreign keys. It only
+/// applies on the current thread, and is reset to the previous prefix when
+/// `f` returns or panics.
+///
+/// Each prefixed name is leaked the first time it's used, so this is meant
+/// for a bounded set of prefixes.
+pub fn with_table_prefix<R>(prefix: &str, f: impl FnOnce() -> R) -> R {
+    struct Restore(Option<&'static str>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            PREFIX.with(|current| current.set(self.0));
+        }
+    }
+
+    let prefix = intern(prefix.to_string());
+    let _restore = Restore(PREFIX.with(|current| current.replace(Some(prefix))));
+    f()
+}
+
+/// The prefix set by [`with_table_prefix`] on this thread, if any.
+pub fn table_prefix() -> Option<&'static str> {
+    PREFIX.with(Cell::get)
+}
+
+/// `table_name` with the current [`table_prefix`].
+///
+/// Used by the `HasCrudFields` derive.
+pub fn prefixed_table_name(table_name: &'static str) -> &'static str {
+    match table_prefix() {
+        None => table_name,
+        Some(prefix) => intern(format!("{prefix}{table_name}")),
+    }
+}
+
+/// A `'static` copy of `s`, leaked only the first time it's seen.
+fn intern(s: String) -> &'static str {
+    static INTERNED: Mutex<BTreeSet<&'static str>> = Mutex::new(BTreeSet::new());
+    let mut interned = INTERNED.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(s) = interned.get(s.as_str()) {
+        return s;
+    }
+    let s: &'static str = Box::leak(s.into_boxed_str());
+    interned.insert(s);
+    s
+}
@@ -0,0 +1,123 @@
+//! Async sqlite backend tests.
+#![cfg(feature = "backend_async_sqlite")]
+
+use snafu::prelude::*;
+use tymigrawr::{
+    with_table_prefix, AsyncCrud, AsyncSqlite, AsyncSqliteConnection, Comparison, HasCrudFields,
+    IsCrudField, Query,
+};
+
+#[derive(Debug, Clone, PartialEq, HasCrudFields)]
+pub struct Widget {
+    #[primary_key]
+    id: i64,
+    name: String,
+    note: Option<String>,
+}
+
+#[tokio::test]
+async fn async_sqlite_roundtrip() {
+    let connection = AsyncSqliteConnection::open_in_memory().await.unwrap();
+    <Widget as AsyncCrud<AsyncSqlite>>::create(&connection)
+        .await
+        .unwrap();
+    let widgets = (0..3)
+        .map(|id| Widget {
+            id,
+            name: format!("widget_{id}"),
+            note: (id == 1).then(|| "rawr".to_string()),
+        })
+        .collect::<Vec<_>>();
+    <Widget as AsyncCrud<AsyncSqlite>>::insert_many(&connection, widgets.clone())
+        .await
+        .unwrap();
+    let read = <Widget as AsyncCrud<AsyncSqlite>>::read_all(&connection)
+        .await
+        .unwrap();
+    assert_eq!(widgets, read);
+
+    let mut widget = widgets[1].clone();
+    widget.name = "sprocket".to_string();
+    <Widget as AsyncCrud<AsyncSqlite>>::update(&widget, &connection)
+        .await
+        .unwrap();
+    let read = <Widget as AsyncCrud<AsyncSqlite>>::read(&connection, 1)
+        .await
+        .unwrap();
+    assert_eq!(vec![widget.clone()], read);
+
+    let query = Query::<Widget>::new().and("id", Comparison::Ge, 1);
+    let deleted = <Widget as AsyncCrud<AsyncSqlite>>::delete_where(&connection, &query)
+        .await
+        .unwrap();
+    assert_eq!(2, deleted);
+    // futures are `Send`, so they can be spawned, though `snafu::Whatever`
+    // errors aren't
+    let count = tokio::spawn({
+        let connection = connection.clone();
+        async move {
+            <Widget as AsyncCrud<AsyncSqlite>>::count(&connection)
+                .await
+                .map_err(|e| e.to_string())
+        }
+    });
+    assert_eq!(Ok(1), count.await.unwrap());
+}
+
+#[tokio::test]
+async fn async_sqlite_error_keeps_source() {
+    let connection = AsyncSqliteConnection::open_in_memory().await.unwrap();
+    let err = <Widget as AsyncCrud<AsyncSqlite>>::read_all(&connection)
+        .await
+        .err()
+        .unwrap();
+    assert_eq!(
+        "table `widget` does not exist; call create() first",
+        err.to_string()
+    );
+    let source = std::error::Error::source(&err).unwrap();
+    assert_eq!("no such table: widget", source.to_string());
+}
+
+/// The prefix of the thread polling an operation is used on the connection's
+/// thread too.
+#[test]
+fn async_sqlite_table_prefix() {
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    let connection = rt
+        .block_on(AsyncSqliteConnection::open_in_memory())
+        .unwrap();
+    for (prefix, count) in [("tenant1_", 1), ("tenant2_", 2)] {
+        with_table_prefix(prefix, || {
+            rt.block_on(async {
+                <Widget as AsyncCrud<AsyncSqlite>>::create(&connection)
+                    .await
+                    .unwrap();
+                let widgets = (0..count)
+                    .map(|id| Widget {
+                        id,
+                        name: format!("{prefix}widget_{id}"),
+                        note: None,
+                    })
+                    .collect::<Vec<_>>();
+                <Widget as AsyncCrud<AsyncSqlite>>::insert_many(&connection, widgets)
+                    .await
+                    .unwrap();
+            })
+        });
+    }
+    for (prefix, count) in [("tenant1_", 1), ("tenant2_", 2)] {
+        let read = with_table_prefix(prefix, || {
+            rt.block_on(<Widget as AsyncCrud<AsyncSqlite>>::read_all(&connection))
+        })
+        .unwrap();
+        assert_eq!(count, read.len());
+        assert!(read.iter().all(|widget| widget.name.starts_with(prefix)));
+    }
+    // the unprefixed table was never created
+    assert!(rt
+        .block_on(<Widget as AsyncCrud<AsyncSqlite>>::read_all(&connection))
+        .is_err());
+}
@@ -0,0 +1,46 @@
+//! The shared `Crud` assertions, run for each backend.
+mod common;
+
+/// Also covers `Sqlite`, which is the same backend.
+#[cfg(feature = "backend_rusqlite")]
+#[test]
+fn rusqlite_crud_matrix() {
+    let connection = rusqlite::Connection::open_in_memory().unwrap();
+    common::crud_matrix::<tymigrawr::Rusqlite, _>(&connection);
+    common::migrate_matrix::<tymigrawr::Rusqlite, _>(&connection);
+}
+
+/// Skipped unless `MYSQL_URL` is set, see `tests/mysql.rs`.
+#[cfg(feature = "backend_mysql")]
+#[test]
+fn mysql_crud_matrix() {
+    let Ok(url) = std::env::var("MYSQL_URL") else {
+        eprintln!("MYSQL_URL is not set, skipping");
+        return;
+    };
+    let pool = mysql::Pool::new(url.as_str()).unwrap();
+    common::crud_matrix::<tymigrawr::MySql, _>(&pool);
+    common::migrate_matrix::<tymigrawr::MySql, _>(&pool);
+}
+
+/// Skipped unless `DYNAMODB_ENDPOINT` is set, see `tests/dynamodb.rs`.
+///
+/// DynamoDB can't migrate entire tables, so only the `Crud` part runs.
+#[cfg(feature = "backend_dynamodb")]
+#[test]
+fn dynamodb_crud_matrix() {
+    use aws_sdk_dynamodb::config::{BehaviorVersion, Credentials, Region};
+
+    let Ok(endpoint) = std::env::var("DYNAMODB_ENDPOINT") else {
+        eprintln!("DYNAMODB_ENDPOINT is not set, skipping");
+        return;
+    };
+    let config = aws_sdk_dynamodb::Config::builder()
+        .behavior_version(BehaviorVersion::latest())
+        .endpoint_url(endpoint)
+        .region(Region::new("us-east-1"))
+        .credentials_provider(Credentials::new("local", "local", None, None, "test"))
+        .build();
+    let client = aws_sdk_dynamodb::Client::from_conf(config);
+    common::crud_matrix::<tymigrawr::DynamoDb, _>(&client);
+}
@@ -0,0 +1,140 @@
+//! Assertions shared by every backend.
+//!
+//! Each backend runs [`crud_matrix`], and [`migrate_matrix`] if it can
+//! migrate, from `tests/backends.rs`, so they all agree on what the `Crud`
+//! operations and migrations do.
+use snafu::prelude::*;
+use tymigrawr::{Crud, HasCrudFields, IsCrudField, MigrateEntireTable, MigrationStep, Migrations};
+
+#[derive(Debug, Clone, PartialEq, HasCrudFields)]
+pub struct ThingV1 {
+    #[primary_key]
+    id: i64,
+    name: String,
+}
+
+#[derive(Debug, Clone, PartialEq, HasCrudFields)]
+pub struct Thing {
+    #[primary_key]
+    id: i64,
+    name: String,
+    weight: f64,
+    note: Option<String>,
+}
+
+impl From<ThingV1> for Thing {
+    fn from(ThingV1 { id, name }: ThingV1) -> Self {
+        Thing {
+            id,
+            name,
+            weight: 0.0,
+            note: None,
+        }
+    }
+}
+
+impl MigrationStep<ThingV1> for Thing {}
+
+/// Every `Thing` in `connection`, sorted by primary key since not every
+/// backend reads rows in order.
+fn read_things<'a, Backend, C>(connection: C) -> Vec<Thing>
+where
+    Thing: Crud<Backend, Connection<'a> = C>,
+{
+    let mut things = <Thing as Crud<Backend>>::read_all_vec(connection).unwrap();
+    things.sort_by_key(|thing| thing.id);
+    things
+}
+
+/// Insert, read, update and delete rows with `Backend`.
+///
+/// Any existing `thing` rows are cleared first.
+pub fn crud_matrix<'a, Backend, C>(connection: C)
+where
+    C: Copy,
+    Thing: Crud<Backend, Connection<'a> = C>,
+{
+    <Thing as Crud<Backend>>::create(connection).unwrap();
+    <Thing as Crud<Backend>>::clear(connection).unwrap();
+
+    // insert and read
+    let mut things = (0..)
+        .zip(["sprocket", "widget"])
+        .map(|(id, name)| Thing {
+            id,
+            name: name.to_string(),
+            weight: 0.0,
+            note: None,
+        })
+        .collect::<Vec<_>>();
+    <Thing as Crud<Backend>>::insert_many(connection, &things).unwrap();
+    let gizmo = Thing {
+        id: 2,
+        name: "gizmo".into(),
+        weight: 66.5,
+        note: Some("rawr".into()),
+    };
+    <Thing as Crud<Backend>>::insert(&gizmo, connection).unwrap();
+    things.push(gizmo.clone());
+    assert_eq!(things, read_things::<Backend, _>(connection));
+    let read = <Thing as Crud<Backend>>::read(connection, 2)
+        .unwrap()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(vec![gizmo.clone()], read);
+    assert_eq!(3, <Thing as Crud<Backend>>::count(connection).unwrap());
+
+    // update
+    let mut sprocket = things[0].clone();
+    sprocket.weight = 1.5;
+    sprocket.note = Some("oiled".into());
+    <Thing as Crud<Backend>>::update(&sprocket, connection).unwrap();
+    let read = <Thing as Crud<Backend>>::read(connection, 0)
+        .unwrap()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(vec![sprocket], read);
+
+    // delete
+    assert!(<Thing as Crud<Backend>>::delete(gizmo.clone(), connection).unwrap());
+    assert!(!<Thing as Crud<Backend>>::delete(gizmo, connection).unwrap());
+    assert_eq!(
+        0,
+        <Thing as Crud<Backend>>::read(connection, 2)
+            .unwrap()
+            .count()
+    );
+    assert_eq!(2, <Thing as Crud<Backend>>::count(connection).unwrap());
+}
+
+/// Migrate rows from `ThingV1` to `Thing` with `Backend`.
+///
+/// Any existing `thingv1` and `thing` rows are cleared first.
+pub fn migrate_matrix<'a, Backend, C>(connection: C)
+where
+    C: Copy,
+    Backend: MigrateEntireTable<Connection<'a> = C>,
+    ThingV1: Crud<Backend, Connection<'a> = C>,
+    Thing: Crud<Backend, Connection<'a> = C>,
+{
+    <ThingV1 as Crud<Backend>>::create(connection).unwrap();
+    <Thing as Crud<Backend>>::create(connection).unwrap();
+    <ThingV1 as Crud<Backend>>::clear(connection).unwrap();
+    <Thing as Crud<Backend>>::clear(connection).unwrap();
+
+    let old = (0..)
+        .zip(["sprocket", "widget"])
+        .map(|(id, name)| ThingV1 {
+            id,
+            name: name.to_string(),
+        })
+        .collect::<Vec<_>>();
+    <ThingV1 as Crud<Backend>>::insert_many(connection, &old).unwrap();
+    Migrations::<ThingV1, Backend>::default()
+        .with_version::<Thing>()
+        .run(connection)
+        .unwrap();
+    let things = old.into_iter().map(Thing::from).collect::<Vec<_>>();
+    assert_eq!(things, read_things::<Backend, _>(connection));
+    assert_eq!(0, <ThingV1 as Crud<Backend>>::count(connection).unwrap());
+}
@@ -0,0 +1,707 @@
+//! DynamoDB backend tests.
+//!
+//! Tests that talk to DynamoDB are skipped unless `DYNAMODB_ENDPOINT` is set,
+//! eg. to a DynamoDB Local instance at `http://localhost:8000`.
+#![cfg(feature = "backend_dynamodb")]
+
+use aws_sdk_dynamodb::{
+    config::{BehaviorVersion, Credentials, Region},
+    types::{AttributeValue, BillingMode, KeyType, ScalarAttributeType, TableStatus},
+};
+use snafu::prelude::*;
+use tymigrawr::{
+    BatchWriteConfig, Comparison, Condition, Crud, DynamoDb, HasCrudFields, IsCrudField,
+    LikePattern, Query, Value, ValueType,
+};
+
+#[derive(Debug, Clone, PartialEq, HasCrudFields)]
+pub struct Item {
+    #[primary_key]
+    id: i64,
+    name: String,
+}
+
+/// Larger than `Item`, so that scanning all of them takes more than one page.
+#[derive(Debug, Clone, PartialEq, HasCrudFields)]
+pub struct Record {
+    #[primary_key]
+    id: i64,
+    body: String,
+}
+
+#[derive(Debug, Clone, PartialEq, HasCrudFields)]
+pub struct Part {
+    #[primary_key]
+    id: i64,
+    kind: String,
+}
+
+#[derive(Debug, Clone, PartialEq, HasCrudFields)]
+pub struct Slot {
+    #[primary_key]
+    id: i64,
+    kind: String,
+}
+
+/// Drop `T`'s table, if it exists, and create it again.
+fn recreate_table<T: HasCrudFields + Clone + 'static>(
+    rt: &tokio::runtime::Runtime,
+    client: &aws_sdk_dynamodb::Client,
+) {
+    let _ = rt.block_on(client.delete_table().table_name(T::table_name()).send());
+    <T as Crud<DynamoDb>>::create(client).unwrap();
+}
+
+fn client_for(endpoint: impl Into<String>) -> aws_sdk_dynamodb::Client {
+    let config = aws_sdk_dynamodb::Config::builder()
+        .behavior_version(BehaviorVersion::latest())
+        .endpoint_url(endpoint)
+        .region(Region::new("us-east-1"))
+        .credentials_provider(Credentials::new("local", "local", None, None, "test"))
+        .build();
+    aws_sdk_dynamodb::Client::from_conf(config)
+}
+
+fn local_client() -> Option<aws_sdk_dynamodb::Client> {
+    let endpoint = std::env::var("DYNAMODB_ENDPOINT").ok()?;
+    Some(client_for(endpoint))
+}
+
+#[test]
+fn dynamodb_float_int_roundtrip() {
+    let int_value = Value::Integer(66);
+    let int_dydb = AttributeValue::try_from(int_value.clone()).unwrap();
+    assert_eq!(int_value, Value::from(int_dydb));
+
+    let float_value = Value::Float(600.66);
+    let float_dydb = AttributeValue::try_from(float_value.clone()).unwrap();
+    assert_eq!(float_value, Value::from(float_dydb));
+
+    // whole floats are written without a fraction and read as integers
+    let float_value = Value::Float(600.0);
+    let float_dydb = AttributeValue::try_from(float_value.clone()).unwrap();
+    let read = Value::from(float_dydb);
+    assert_eq!(Value::Integer(600), read);
+    assert_eq!(float_value, read.coerce_to(ValueType::Float).unwrap());
+}
+
+/// NaN and infinite floats error without making a request.
+#[test]
+fn dynamodb_non_finite() {
+    for f in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+        assert!(AttributeValue::try_from(Value::Float(f)).is_err());
+    }
+    // nothing listens here, the client is never used
+    let client = client_for("http://localhost:1");
+    for weight in [f64::NAN, f64::INFINITY] {
+        let gizmo = Gizmo {
+            id: 0,
+            name: "sprocket".into(),
+            weight,
+            data: vec![],
+            note: None,
+        };
+        let err = <Gizmo as Crud<DynamoDb>>::insert(&gizmo, &client)
+            .unwrap_err()
+            .to_string();
+        assert_eq!(
+            "can't store a NaN or infinite float in `gizmo`.`weight`, DynamoDB doesn't support them",
+            err
+        );
+        assert!(<Gizmo as Crud<DynamoDb>>::update(&gizmo, &client).is_err());
+    }
+}
+
+/// Operations DynamoDB has no equivalent for error without making a request.
+#[test]
+fn dynamodb_unsupported() {
+    // nothing listens here, the client is never used
+    let client = client_for("http://localhost:1");
+    let err = <Item as Crud<DynamoDb>>::validate_schema(&client)
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("has no schema"), "{err}");
+    let err = <Item as Crud<DynamoDb>>::sync_schema(&client)
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("has no schema"), "{err}");
+    let err = <Item as Crud<DynamoDb>>::read_all_rev(&client)
+        .err()
+        .unwrap()
+        .to_string();
+    assert!(err.contains("insertion order"), "{err}");
+    let err = <Item as Crud<DynamoDb>>::aggregate(&client, "name")
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("has no GROUP BY"), "{err}");
+    let err = <Item as Crud<DynamoDb>>::update_where(
+        &client,
+        "id",
+        1i64,
+        "name",
+        Comparison::Eq,
+        "x".to_string(),
+    )
+    .unwrap_err()
+    .to_string();
+    assert!(err.contains("keys are immutable"), "{err}");
+    let err = <Item as Crud<DynamoDb>>::read_column_cmp(&client, "id", "<", "name")
+        .err()
+        .unwrap()
+        .to_string();
+    assert!(err.contains("can't compare columns"), "{err}");
+    let err = <Item as Crud<DynamoDb>>::read_where(&client, "name", "LIKE", "x%".to_string())
+        .err()
+        .unwrap()
+        .to_string();
+    assert!(err.contains("comparison `LIKE`"), "{err}");
+    let err = <Item as Crud<DynamoDb>>::read_history(&client)
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("not audited on this backend"), "{err}");
+    let err = <Item as Crud<DynamoDb>>::read_like(&client, "name", LikePattern::contains("x"))
+        .err()
+        .unwrap()
+        .to_string();
+    assert!(err.contains("has no LIKE"), "{err}");
+    let err = <Item as Crud<DynamoDb>>::read_in(&client, "name", vec!["x".to_string()])
+        .err()
+        .unwrap()
+        .to_string();
+    assert!(err.contains("only do that for the primary key"), "{err}");
+    let read = <Item as Crud<DynamoDb>>::read_in(&client, "id", Vec::<i64>::new())
+        .unwrap()
+        .count();
+    assert_eq!(0, read);
+}
+
+#[test]
+fn dynamodb_bool_roundtrip() {
+    for b in [true, false] {
+        let bool_dydb = AttributeValue::try_from(Value::from(b)).unwrap();
+        assert_eq!(AttributeValue::Bool(b), bool_dydb);
+        assert_eq!(Value::Boolean(b), Value::from(bool_dydb));
+    }
+}
+
+#[test]
+fn dynamodb_insert_many() {
+    let Some(client) = local_client() else {
+        eprintln!("DYNAMODB_ENDPOINT is not set, skipping");
+        return;
+    };
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let table_name = Item::table_name();
+    recreate_table::<Item>(&rt, &client);
+
+    let items = (0..100)
+        .map(|id| Item {
+            id,
+            name: format!("item {id}"),
+        })
+        .collect::<Vec<_>>();
+    DynamoDb::insert_many_with(
+        &client,
+        &items,
+        &BatchWriteConfig {
+            concurrency: 2,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    <Item as Crud<DynamoDb>>::insert(
+        &Item {
+            id: 100,
+            name: "one more".into(),
+        },
+        &client,
+    )
+    .unwrap();
+
+    let mut count = 0;
+    let mut start_key = None;
+    loop {
+        let output = rt
+            .block_on(
+                client
+                    .scan()
+                    .table_name(table_name)
+                    .set_exclusive_start_key(start_key)
+                    .send(),
+            )
+            .unwrap();
+        count += output.items().len();
+        start_key = output.last_evaluated_key().cloned();
+        if start_key.is_none() {
+            break;
+        }
+    }
+    assert_eq!(101, count);
+}
+
+#[test]
+fn dynamodb_read() {
+    let Some(client) = local_client() else {
+        eprintln!("DYNAMODB_ENDPOINT is not set, skipping");
+        return;
+    };
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    recreate_table::<Record>(&rt, &client);
+
+    // scans return at most 1MB per page
+    let records = (0..100)
+        .map(|id| Record {
+            id,
+            body: format!("{id}").repeat(20_000),
+        })
+        .collect::<Vec<_>>();
+    <Record as Crud<DynamoDb>>::insert_many(&client, &records).unwrap();
+
+    let mut all = <Record as Crud<DynamoDb>>::read_all(&client)
+        .unwrap()
+        .map(|r| r.unwrap())
+        .collect::<Vec<_>>();
+    all.sort_by_key(|record| record.id);
+    assert_eq!(records, all);
+
+    let read = <Record as Crud<DynamoDb>>::read(&client, 42)
+        .unwrap()
+        .map(|r| r.unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(vec![records[42].clone()], read);
+
+    let mut read = <Record as Crud<DynamoDb>>::read_where(&client, "id", ">=", 98)
+        .unwrap()
+        .map(|r| r.unwrap())
+        .collect::<Vec<_>>();
+    read.sort_by_key(|record| record.id);
+    assert_eq!(records[98..].to_vec(), read);
+}
+
+#[test]
+fn dynamodb_delete_where() {
+    let Some(client) = local_client() else {
+        eprintln!("DYNAMODB_ENDPOINT is not set, skipping");
+        return;
+    };
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    recreate_table::<Part>(&rt, &client);
+
+    // more than one batch of deletes
+    let parts = (0..60)
+        .map(|id| Part {
+            id,
+            kind: if id % 2 == 0 { "bolt" } else { "nut" }.to_string(),
+        })
+        .collect::<Vec<_>>();
+    <Part as Crud<DynamoDb>>::insert_many(&client, &parts).unwrap();
+
+    let query = Query::<Part>::new()
+        .and("kind", Comparison::Eq, "bolt".to_string())
+        .and("id", Comparison::Ge, 10i64);
+    assert_eq!(
+        25,
+        <Part as Crud<DynamoDb>>::delete_where(&client, &query).unwrap()
+    );
+    let mut left = <Part as Crud<DynamoDb>>::read_all(&client)
+        .unwrap()
+        .map(|r| r.unwrap().id)
+        .collect::<Vec<_>>();
+    left.sort();
+    let expected = (0..60)
+        .filter(|id| id % 2 == 1 || *id < 10)
+        .collect::<Vec<_>>();
+    assert_eq!(expected, left);
+
+    assert!(<Part as Crud<DynamoDb>>::delete_where(&client, &Query::new()).is_err());
+}
+
+#[test]
+fn dynamodb_update_where() {
+    let Some(client) = local_client() else {
+        eprintln!("DYNAMODB_ENDPOINT is not set, skipping");
+        return;
+    };
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    recreate_table::<Slot>(&rt, &client);
+
+    let slots = (0..10)
+        .map(|id| Slot {
+            id,
+            kind: "empty".to_string(),
+        })
+        .collect::<Vec<_>>();
+    <Slot as Crud<DynamoDb>>::insert_many(&client, &slots).unwrap();
+    let updated = <Slot as Crud<DynamoDb>>::update_where(
+        &client,
+        "kind",
+        "full".to_string(),
+        "id",
+        Comparison::Ge,
+        7i64,
+    )
+    .unwrap();
+    assert_eq!(3, updated);
+    let mut full = <Slot as Crud<DynamoDb>>::read_all(&client)
+        .unwrap()
+        .map(|r| r.unwrap())
+        .filter(|slot| slot.kind == "full")
+        .map(|slot| slot.id)
+        .collect::<Vec<_>>();
+    full.sort();
+    assert_eq!(vec![7, 8, 9], full);
+    let updated = <Slot as Crud<DynamoDb>>::update_where(
+        &client,
+        "kind",
+        "full".to_string(),
+        "id",
+        Comparison::Gt,
+        100i64,
+    )
+    .unwrap();
+    assert_eq!(0, updated);
+}
+
+#[test]
+fn dynamodb_insert_returning() {
+    let Some(client) = local_client() else {
+        eprintln!("DYNAMODB_ENDPOINT is not set, skipping");
+        return;
+    };
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    recreate_table::<Item>(&rt, &client);
+    let item = Item {
+        id: 0,
+        name: "returned".into(),
+    };
+    assert_eq!(
+        item,
+        <Item as Crud<DynamoDb>>::insert_returning(&item, &client).unwrap()
+    );
+}
+
+#[test]
+fn dynamodb_insert_or_ignore() {
+    let Some(client) = local_client() else {
+        eprintln!("DYNAMODB_ENDPOINT is not set, skipping");
+        return;
+    };
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    recreate_table::<Item>(&rt, &client);
+    let item = Item {
+        id: 0,
+        name: "kept".into(),
+    };
+    assert!(<Item as Crud<DynamoDb>>::insert_or_ignore(&item, &client).unwrap());
+    // the stored item is kept
+    let renamed = Item {
+        name: "renamed".into(),
+        ..item.clone()
+    };
+    assert!(!<Item as Crud<DynamoDb>>::insert_or_ignore(&renamed, &client).unwrap());
+    let read = <Item as Crud<DynamoDb>>::read_all(&client)
+        .unwrap()
+        .map(|r| r.unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(vec![item], read);
+}
+
+#[test]
+fn dynamodb_update_diff_count() {
+    let Some(client) = local_client() else {
+        eprintln!("DYNAMODB_ENDPOINT is not set, skipping");
+        return;
+    };
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    recreate_table::<Item>(&rt, &client);
+    let get_name = |id: i64| {
+        rt.block_on(
+            client
+                .get_item()
+                .table_name(Item::table_name())
+                .key("id", AttributeValue::N(id.to_string()))
+                .consistent_read(true)
+                .send(),
+        )
+        .unwrap()
+        .item
+        .map(|item| item["name"].as_s().unwrap().clone())
+    };
+
+    let item = Item {
+        id: 0,
+        name: "before".into(),
+    };
+    <Item as Crud<DynamoDb>>::insert(&item, &client).unwrap();
+    let renamed = Item {
+        id: 0,
+        name: "after".into(),
+    };
+    assert_eq!(
+        1,
+        <Item as Crud<DynamoDb>>::update_diff_count(&renamed, &item, &client).unwrap()
+    );
+    assert_eq!(
+        0,
+        <Item as Crud<DynamoDb>>::update_diff_count(&renamed, &renamed, &client).unwrap()
+    );
+    assert_eq!(Some("after".to_string()), get_name(0));
+
+    // no item is created for a missing key
+    let missing = Item {
+        id: 66,
+        ..renamed.clone()
+    };
+    let missing_old = Item { id: 66, ..item };
+    assert_eq!(
+        0,
+        <Item as Crud<DynamoDb>>::update_diff_count(&missing, &missing_old, &client).unwrap()
+    );
+    assert_eq!(None, get_name(66));
+}
+
+#[test]
+fn dynamodb_count_and_pages() {
+    let Some(client) = local_client() else {
+        eprintln!("DYNAMODB_ENDPOINT is not set, skipping");
+        return;
+    };
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    recreate_table::<Item>(&rt, &client);
+    let items = (0..100)
+        .map(|id| Item {
+            id,
+            name: format!("item {id}"),
+        })
+        .collect::<Vec<_>>();
+    <Item as Crud<DynamoDb>>::insert_many(&client, &items).unwrap();
+
+    assert_eq!(100, <Item as Crud<DynamoDb>>::count(&client).unwrap());
+    let mut paged = vec![];
+    for offset in (0..100).step_by(30) {
+        let page = <Item as Crud<DynamoDb>>::read_page_typed(&client, 30, offset).unwrap();
+        assert_eq!(100, page.total);
+        assert_eq!(offset < 70, page.has_more);
+        paged.extend(page.items);
+    }
+    paged.sort_by_key(|item| item.id);
+    assert_eq!(items, paged);
+    let past_the_end = <Item as Crud<DynamoDb>>::read_page_typed(&client, 30, 200).unwrap();
+    assert!(past_the_end.items.is_empty());
+}
+
+#[test]
+fn dynamodb_read_by_keys() {
+    let Some(client) = local_client() else {
+        eprintln!("DYNAMODB_ENDPOINT is not set, skipping");
+        return;
+    };
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    recreate_table::<Item>(&rt, &client);
+    let items = (0..150)
+        .map(|id| Item {
+            id,
+            name: format!("item {id}"),
+        })
+        .collect::<Vec<_>>();
+    <Item as Crud<DynamoDb>>::insert_many(&client, &items).unwrap();
+
+    // more than one batch of gets, missing keys are skipped
+    let keys = (0..150).rev().chain([500]).collect::<Vec<i64>>();
+    let read = <Item as Crud<DynamoDb>>::read_by_keys(&client, keys.clone()).unwrap();
+    assert_eq!(items, read);
+    let read = <Item as Crud<DynamoDb>>::read_in(&client, "id", keys)
+        .unwrap()
+        .map(|r| r.unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(items, read);
+    let read = <Item as Crud<DynamoDb>>::read_by_keys(&client, Vec::<i64>::new()).unwrap();
+    assert!(read.is_empty());
+
+    let mut read = <Item as Crud<DynamoDb>>::read_all_parallel(&client, 3).unwrap();
+    read.sort_by_key(|item| item.id);
+    assert_eq!(items, read);
+
+    // projected rows come back sorted by primary key, with only the asked for columns
+    let names = <Item as Crud<DynamoDb>>::read_projected(&client, &["name"]).unwrap();
+    assert_eq!(items.len(), names.len());
+    for (item, row) in items.iter().zip(&names) {
+        assert_eq!(1, row.len());
+        assert_eq!(Some(&Value::String(item.name.clone())), row.get("name"));
+    }
+    assert!(<Item as Crud<DynamoDb>>::read_projected(&client, &["colour"]).is_err());
+}
+
+#[test]
+fn dynamodb_delete_all_where_and_clear() {
+    let Some(client) = local_client() else {
+        eprintln!("DYNAMODB_ENDPOINT is not set, skipping");
+        return;
+    };
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    recreate_table::<Item>(&rt, &client);
+    let items = (0..50)
+        .map(|id| Item {
+            id,
+            name: format!("item {}", id % 2),
+        })
+        .collect::<Vec<_>>();
+    <Item as Crud<DynamoDb>>::insert_many(&client, &items).unwrap();
+
+    let conditions = [
+        Condition::new("id", Comparison::Lt, 20i64),
+        Condition::new("name", Comparison::Eq, "item 0".to_string()),
+    ];
+    let deleted = <Item as Crud<DynamoDb>>::delete_all_where(&client, &conditions).unwrap();
+    assert_eq!(10, deleted);
+    assert_eq!(40, <Item as Crud<DynamoDb>>::count(&client).unwrap());
+    assert!(<Item as Crud<DynamoDb>>::delete_all_where(&client, &[]).is_err());
+
+    assert_eq!(40, <Item as Crud<DynamoDb>>::clear(&client).unwrap());
+    assert_eq!(0, <Item as Crud<DynamoDb>>::count(&client).unwrap());
+}
+
+#[derive(Debug, Clone, PartialEq, HasCrudFields)]
+#[append_only]
+pub struct Event {
+    #[primary_key]
+    id: i64,
+    name: String,
+}
+
+/// Append-only tables are rejected before any request is made.
+#[test]
+fn dynamodb_append_only() {
+    // nothing listens here, the client is never used
+    let client = client_for("http://localhost:1");
+    let event = Event {
+        id: 0,
+        name: "created".to_string(),
+    };
+    let err = <Event as Crud<DynamoDb>>::update_diff_count(&event, &event, &client)
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("append-only"), "{err}");
+    let conditions = [Condition::new("id", Comparison::Eq, 0i64)];
+    let err = <Event as Crud<DynamoDb>>::delete_all_where(&client, &conditions)
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("append-only"), "{err}");
+    let err = <Event as Crud<DynamoDb>>::clear(&client)
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("append-only"), "{err}");
+}
+
+#[derive(Debug, Clone, PartialEq, HasCrudFields)]
+pub struct Gizmo {
+    #[primary_key]
+    id: i64,
+    name: String,
+    weight: f64,
+    data: Vec<u8>,
+    note: Option<String>,
+}
+
+#[test]
+fn dynamodb_crud() {
+    let Some(client) = local_client() else {
+        eprintln!("DYNAMODB_ENDPOINT is not set, skipping");
+        return;
+    };
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    recreate_table::<Gizmo>(&rt, &client);
+
+    let mut gizmo = Gizmo {
+        id: 0,
+        name: "sprocket".into(),
+        weight: 66.5,
+        data: vec![0, 159, 146, 150],
+        note: None,
+    };
+    <Gizmo as Crud<DynamoDb>>::insert(&gizmo, &client).unwrap();
+    let read = <Gizmo as Crud<DynamoDb>>::read(&client, 0)
+        .unwrap()
+        .map(|r| r.unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(vec![gizmo.clone()], read);
+
+    gizmo.name = "widget".into();
+    gizmo.note = Some("rawr".into());
+    <Gizmo as Crud<DynamoDb>>::update(&gizmo, &client).unwrap();
+    let other = Gizmo {
+        id: 1,
+        name: "cog".into(),
+        weight: 1.0,
+        data: vec![],
+        note: None,
+    };
+    <Gizmo as Crud<DynamoDb>>::insert(&other, &client).unwrap();
+    let all = <Gizmo as Crud<DynamoDb>>::read_all(&client)
+        .unwrap()
+        .map(|r| r.unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(vec![gizmo.clone(), other.clone()], all);
+
+    assert!(<Gizmo as Crud<DynamoDb>>::delete(gizmo, &client).unwrap());
+    assert_eq!(
+        0,
+        <Gizmo as Crud<DynamoDb>>::read(&client, 0).unwrap().count()
+    );
+    let all = <Gizmo as Crud<DynamoDb>>::read_all(&client)
+        .unwrap()
+        .map(|r| r.unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(vec![other], all);
+}
+
+#[derive(Debug, Clone, PartialEq, HasCrudFields)]
+pub struct Account {
+    #[primary_key]
+    email: String,
+    credits: i64,
+}
+
+#[test]
+fn dynamodb_create() {
+    let Some(client) = local_client() else {
+        eprintln!("DYNAMODB_ENDPOINT is not set, skipping");
+        return;
+    };
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let _ = rt.block_on(client.delete_table().table_name("account").send());
+    <Account as Crud<DynamoDb>>::create(&client).unwrap();
+    // creating it again leaves the table alone
+    <Account as Crud<DynamoDb>>::create(&client).unwrap();
+
+    let table = rt
+        .block_on(client.describe_table().table_name("account").send())
+        .unwrap()
+        .table
+        .unwrap();
+    assert_eq!(Some(&TableStatus::Active), table.table_status());
+    let key_schema = table.key_schema();
+    assert_eq!(1, key_schema.len());
+    assert_eq!("email", key_schema[0].attribute_name());
+    assert_eq!(&KeyType::Hash, key_schema[0].key_type());
+    let attributes = table.attribute_definitions();
+    assert_eq!(1, attributes.len());
+    assert_eq!(&ScalarAttributeType::S, attributes[0].attribute_type());
+    assert_eq!(
+        Some(&BillingMode::PayPerRequest),
+        table
+            .billing_mode_summary()
+            .and_then(|summary| summary.billing_mode())
+    );
+
+    let account = Account {
+        email: "a@x.com".into(),
+        credits: 3,
+    };
+    <Account as Crud<DynamoDb>>::insert(&account, &client).unwrap();
+    let read = <Account as Crud<DynamoDb>>::read(&client, "a@x.com".to_string())
+        .unwrap()
+        .map(|r| r.unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(vec![account], read);
+}
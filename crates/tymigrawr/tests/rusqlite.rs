@@ -0,0 +1,253 @@
+//! Rusqlite backend tests.
+#![cfg(feature = "backend_rusqlite")]
+
+use snafu::prelude::*;
+use tymigrawr::{
+    with_busy_retry, BusyRetry, Comparison, Crud, HasCrudFields, IsCrudField, LikePattern,
+    MigrationStep, Migrations, Query, Rusqlite, Value,
+};
+
+#[derive(Debug, Clone, PartialEq, HasCrudFields)]
+pub struct GadgetV1 {
+    #[primary_key]
+    id: i64,
+    name: String,
+}
+
+#[derive(Debug, Clone, PartialEq, HasCrudFields)]
+pub struct Gadget {
+    #[primary_key]
+    id: i64,
+    name: String,
+    weight: f64,
+    data: Vec<u8>,
+    note: Option<String>,
+}
+
+impl From<GadgetV1> for Gadget {
+    fn from(GadgetV1 { id, name }: GadgetV1) -> Self {
+        Gadget {
+            id,
+            name,
+            weight: 0.0,
+            data: vec![],
+            note: None,
+        }
+    }
+}
+
+impl MigrationStep<GadgetV1> for Gadget {}
+
+#[test]
+fn rusqlite_value_roundtrip() {
+    for value in [
+        Value::Integer(-66),
+        Value::Float(6.5),
+        Value::String("rawr".to_string()),
+        Value::Bytes(vec![0, 159, 146, 150]),
+        Value::None,
+    ] {
+        let rusqlite_value = rusqlite::types::Value::from(value.clone());
+        assert_eq!(value, Value::from(rusqlite_value));
+    }
+    assert_eq!(
+        rusqlite::types::Value::Integer(1),
+        rusqlite::types::Value::from(Value::Boolean(true))
+    );
+}
+
+#[test]
+fn rusqlite_crud() {
+    let connection = rusqlite::Connection::open_in_memory().unwrap();
+    <Gadget as Crud<Rusqlite>>::create(&connection).unwrap();
+    <Gadget as Crud<Rusqlite>>::validate_schema(&connection).unwrap();
+    <Gadget as Crud<Rusqlite>>::healthcheck(&connection).unwrap();
+
+    let mut gadget = Gadget {
+        id: 0,
+        name: "sprocket".into(),
+        weight: 66.5,
+        data: vec![0, 159, 146, 150],
+        note: None,
+    };
+    <Gadget as Crud<Rusqlite>>::insert(&gadget, &connection).unwrap();
+    let read = <Gadget as Crud<Rusqlite>>::read(&connection, 0)
+        .unwrap()
+        .map(|r| r.unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(vec![gadget.clone()], read);
+
+    gadget.name = "widget".into();
+    gadget.note = Some("shiny".into());
+    <Gadget as Crud<Rusqlite>>::update(&gadget, &connection).unwrap();
+    let more = (1..4)
+        .map(|id| Gadget {
+            id,
+            name: format!("gadget {id}"),
+            weight: id as f64,
+            data: vec![],
+            note: None,
+        })
+        .collect::<Vec<_>>();
+    <Gadget as Crud<Rusqlite>>::insert_many(&connection, &more).unwrap();
+    let all = <Gadget as Crud<Rusqlite>>::read_all(&connection)
+        .unwrap()
+        .map(|r| r.unwrap())
+        .collect::<Vec<_>>();
+    let expected = std::iter::once(gadget.clone())
+        .chain(more)
+        .collect::<Vec<_>>();
+    assert_eq!(expected, all);
+    assert_eq!(4, <Gadget as Crud<Rusqlite>>::count(&connection).unwrap());
+
+    let page = <Gadget as Crud<Rusqlite>>::read_page_typed(&connection, 2, 1).unwrap();
+    assert_eq!(expected[1..3].to_vec(), page.items);
+    let heavy = <Gadget as Crud<Rusqlite>>::read_where(&connection, "weight", ">", 2.0)
+        .unwrap()
+        .map(|r| r.unwrap().id)
+        .collect::<Vec<_>>();
+    assert_eq!(vec![0, 3], heavy);
+    let like = <Gadget as Crud<Rusqlite>>::read_like(
+        &connection,
+        "name",
+        LikePattern::starts_with("gadget"),
+    )
+    .unwrap()
+    .count();
+    assert_eq!(3, like);
+    let by_keys = <Gadget as Crud<Rusqlite>>::read_by_keys(&connection, vec![1, 3]).unwrap();
+    assert_eq!(vec![expected[1].clone(), expected[3].clone()], by_keys);
+
+    assert!(<Gadget as Crud<Rusqlite>>::delete(gadget.clone(), &connection).unwrap());
+    assert!(!<Gadget as Crud<Rusqlite>>::delete(gadget, &connection).unwrap());
+    assert_eq!(
+        0,
+        <Gadget as Crud<Rusqlite>>::read(&connection, 0)
+            .unwrap()
+            .count()
+    );
+    assert_eq!(
+        2,
+        <Gadget as Crud<Rusqlite>>::update_where(
+            &connection,
+            "name",
+            "heavy".to_string(),
+            "weight",
+            Comparison::Ge,
+            2.0
+        )
+        .unwrap()
+    );
+    let query = Query::<Gadget>::new().and("name", Comparison::Eq, "heavy".to_string());
+    assert_eq!(
+        2,
+        <Gadget as Crud<Rusqlite>>::delete_where(&connection, &query).unwrap()
+    );
+    assert_eq!(1, <Gadget as Crud<Rusqlite>>::clear(&connection).unwrap());
+}
+
+#[test]
+fn rusqlite_repeated_reads() {
+    let connection = rusqlite::Connection::open_in_memory().unwrap();
+    <GadgetV1 as Crud<Rusqlite>>::create(&connection).unwrap();
+    for id in 0..10 {
+        let name = format!("gadget {id}");
+        <GadgetV1 as Crud<Rusqlite>>::insert(&GadgetV1 { id, name }, &connection).unwrap();
+    }
+    // every iteration after the first reuses the cached statements
+    for i in 0..1000 {
+        let id = i % 10;
+        let read = <GadgetV1 as Crud<Rusqlite>>::read(&connection, id)
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![format!("gadget {id}")],
+            read.into_iter().map(|g| g.name).collect::<Vec<_>>()
+        );
+        let found = <GadgetV1 as Crud<Rusqlite>>::read_where(&connection, "id", "<", id)
+            .unwrap()
+            .count();
+        assert_eq!(id as usize, found);
+    }
+}
+
+#[test]
+fn rusqlite_insert_or_ignore() {
+    let connection = rusqlite::Connection::open_in_memory().unwrap();
+    <GadgetV1 as Crud<Rusqlite>>::create(&connection).unwrap();
+    let gadget = GadgetV1 {
+        id: 0,
+        name: "sprocket".to_string(),
+    };
+    assert!(<GadgetV1 as Crud<Rusqlite>>::insert_or_ignore(&gadget, &connection).unwrap());
+    let duplicate = GadgetV1 {
+        id: 0,
+        name: "widget".to_string(),
+    };
+    assert!(!<GadgetV1 as Crud<Rusqlite>>::insert_or_ignore(&duplicate, &connection).unwrap());
+    let read = <GadgetV1 as Crud<Rusqlite>>::read(&connection, 0)
+        .unwrap()
+        .map(|r| r.unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(vec![gadget], read);
+}
+
+#[test]
+fn rusqlite_migrate() {
+    let connection = rusqlite::Connection::open_in_memory().unwrap();
+    <GadgetV1 as Crud<Rusqlite>>::create(&connection).unwrap();
+    for (id, name) in (0..).zip(["sprocket", "widget"]) {
+        let name = name.to_string();
+        <GadgetV1 as Crud<Rusqlite>>::insert(&GadgetV1 { id, name }, &connection).unwrap();
+    }
+
+    <Gadget as Crud<Rusqlite>>::create(&connection).unwrap();
+    Migrations::<GadgetV1, Rusqlite>::default()
+        .with_version::<Gadget>()
+        .run(&connection)
+        .unwrap();
+    let names = <Gadget as Crud<Rusqlite>>::read_all(&connection)
+        .unwrap()
+        .map(|r| r.unwrap().name)
+        .collect::<Vec<_>>();
+    assert_eq!(vec!["sprocket", "widget"], names);
+    assert_eq!(0, <GadgetV1 as Crud<Rusqlite>>::count(&connection).unwrap());
+}
+
+#[test]
+fn rusqlite_busy_retry() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("busy.db");
+    let locker = rusqlite::Connection::open(&path).unwrap();
+    <GadgetV1 as Crud<Rusqlite>>::create(&locker).unwrap();
+    let connection = rusqlite::Connection::open(&path).unwrap();
+    // rely on the retries instead of sqlite's own busy timeout
+    connection.busy_timeout(std::time::Duration::ZERO).unwrap();
+    let gadget = GadgetV1 {
+        id: 0,
+        name: "sprocket".into(),
+    };
+
+    locker.execute_batch("BEGIN EXCLUSIVE;").unwrap();
+    let none = BusyRetry {
+        max_retries: 0,
+        ..Default::default()
+    };
+    let err = with_busy_retry(none, || {
+        <GadgetV1 as Crud<Rusqlite>>::insert(&gadget, &connection)
+    })
+    .unwrap_err();
+    assert_eq!(
+        "insert into `gadgetv1` timed out, the database was still busy after 0 retries",
+        err.to_string()
+    );
+
+    let unlock = std::thread::spawn(move || {
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        locker.execute_batch("COMMIT;").unwrap();
+    });
+    <GadgetV1 as Crud<Rusqlite>>::insert(&gadget, &connection).unwrap();
+    unlock.join().unwrap();
+    assert_eq!(1, <GadgetV1 as Crud<Rusqlite>>::count(&connection).unwrap());
+}
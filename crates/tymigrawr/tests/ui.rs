@@ -0,0 +1,28 @@
+//! Compile tests, see `tests/ui`.
+
+#[test]
+fn primary_key() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/missing_primary_key.rs");
+    t.compile_fail("tests/ui/multiple_primary_keys.rs");
+    t.compile_fail("tests/ui/wrong_key_type.rs");
+    t.pass("tests/ui/implicit_primary_key.rs");
+}
+
+#[test]
+fn migration_step() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/wrong_migration_order.rs");
+}
+
+#[test]
+fn nested_option() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/nested_option.rs");
+}
+
+#[test]
+fn enum_with_fields() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/enum_with_fields.rs");
+}
@@ -0,0 +1,9 @@
+use tymigrawr::CrudEnum;
+
+#[derive(CrudEnum)]
+pub enum Shape {
+    Point,
+    Circle(f64),
+}
+
+fn main() {}
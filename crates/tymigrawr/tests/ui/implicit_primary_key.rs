@@ -0,0 +1,13 @@
+use snafu::prelude::*;
+use tymigrawr::{HasCrudFields, IsCrudField};
+
+#[derive(HasCrudFields)]
+#[implicit_primary_key]
+pub struct Player {
+    pub name: String,
+    pub id: i64,
+}
+
+fn main() {
+    assert_eq!("name", Player::primary_key_name());
+}
@@ -0,0 +1,10 @@
+use snafu::prelude::*;
+use tymigrawr::{HasCrudFields, IsCrudField};
+
+#[derive(HasCrudFields)]
+pub struct Player {
+    pub name: String,
+    pub id: i64,
+}
+
+fn main() {}
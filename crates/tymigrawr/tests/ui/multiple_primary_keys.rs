@@ -0,0 +1,12 @@
+use snafu::prelude::*;
+use tymigrawr::{HasCrudFields, IsCrudField};
+
+#[derive(HasCrudFields)]
+pub struct Player {
+    #[primary_key]
+    pub id: i64,
+    #[primary_key]
+    pub name: String,
+}
+
+fn main() {}
@@ -0,0 +1,11 @@
+use snafu::prelude::*;
+use tymigrawr::{HasCrudFields, IsCrudField};
+
+#[derive(HasCrudFields)]
+pub struct Player {
+    #[primary_key]
+    pub id: i64,
+    pub nickname: Option<Option<String>>,
+}
+
+fn main() {}
@@ -0,0 +1,15 @@
+use snafu::prelude::*;
+use tymigrawr::{Crud, HasCrudFields, IsCrudField, Sqlite};
+
+#[derive(Clone, HasCrudFields)]
+pub struct Player {
+    #[primary_key]
+    pub id: i64,
+    pub name: String,
+}
+
+fn read_zero(connection: <Player as Crud<Sqlite>>::Connection<'_>) {
+    let _ = <Player as Crud<Sqlite>>::read(connection, "zero".to_string());
+}
+
+fn main() {}
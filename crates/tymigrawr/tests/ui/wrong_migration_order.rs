@@ -0,0 +1,37 @@
+use snafu::prelude::*;
+use tymigrawr::{HasCrudFields, IsCrudField, MigrationStep, Migrations, Sqlite};
+
+#[derive(Clone, HasCrudFields)]
+pub struct PlayerV1 {
+    #[primary_key]
+    pub id: i64,
+}
+
+#[derive(Clone, HasCrudFields)]
+pub struct PlayerV2 {
+    #[primary_key]
+    pub id: i64,
+    pub name: String,
+}
+
+impl From<PlayerV1> for PlayerV2 {
+    fn from(PlayerV1 { id }: PlayerV1) -> Self {
+        PlayerV2 {
+            id,
+            name: String::new(),
+        }
+    }
+}
+
+impl From<PlayerV2> for PlayerV1 {
+    fn from(PlayerV2 { id, .. }: PlayerV2) -> Self {
+        PlayerV1 { id }
+    }
+}
+
+impl MigrationStep<PlayerV1> for PlayerV2 {}
+
+fn main() {
+    let _forward = Migrations::<PlayerV1, Sqlite>::default().with_version::<PlayerV2>();
+    let _wrong_order = Migrations::<PlayerV2, Sqlite>::default().with_version::<PlayerV1>();
+}